@@ -0,0 +1,175 @@
+use near_sdk::borsh::{BorshDeserialize, BorshSerialize};
+use near_sdk::json_types::U128;
+use near_sdk::serde::{Deserialize, Serialize};
+use near_sdk::{env, Promise};
+use schemars::JsonSchema;
+
+use crate::flight::NANOS_PER_DAY;
+use crate::pool::mul_div;
+use crate::{Balance, Status, Trigger, TriggerId, TriggerPay, TriggerPayExt};
+
+// ============================================================================
+// Constants
+// ============================================================================
+
+const MAX_BPS: u16 = 10_000;
+const NANOS_PER_HOUR: u64 = 60 * 60 * 1_000_000_000;
+
+// ============================================================================
+// Types
+// ============================================================================
+
+/// Fee for cancelling before resolution. The fee falls linearly from
+/// `max_fee_bps` at the cutoff to `min_fee_bps` once `fee_window` or more
+/// remains before the event.
+#[derive(BorshDeserialize, BorshSerialize, Serialize, Deserialize, Clone, Debug, JsonSchema)]
+#[borsh(crate = "near_sdk::borsh")]
+#[serde(crate = "near_sdk::serde")]
+pub struct CancellationPolicy {
+    pub min_fee_bps: u16,
+    pub max_fee_bps: u16,
+    pub fee_window: u64, // Nanoseconds
+    pub cutoff: u64,     // Nanoseconds before the event when cancellation closes
+}
+
+impl Default for CancellationPolicy {
+    fn default() -> Self {
+        Self {
+            min_fee_bps: 200,             // 2%
+            max_fee_bps: 2_000,           // 20%
+            fee_window: 30 * NANOS_PER_DAY,
+            cutoff: 24 * NANOS_PER_HOUR,
+        }
+    }
+}
+
+// ============================================================================
+// Cancellation
+// ============================================================================
+
+#[near_sdk::near]
+impl TriggerPay {
+    /// Set the cancellation fee curve and cutoff (only owner can call)
+    pub fn set_cancellation_policy(&mut self, policy: CancellationPolicy) {
+        self.assert_owner();
+        assert!(
+            policy.min_fee_bps <= policy.max_fee_bps && policy.max_fee_bps <= MAX_BPS,
+            "Fees must satisfy min <= max <= 10000 bps"
+        );
+        assert!(policy.fee_window > 0, "Fee window must be positive");
+        self.cancellation_policy = policy;
+
+        env::log_str("Cancellation policy updated");
+    }
+
+    pub fn get_cancellation_policy(&self) -> CancellationPolicy {
+        self.cancellation_policy.clone()
+    }
+
+    /// Fee that cancelling the trigger now would cost, in the escrow's unit
+    pub fn get_cancellation_fee(&self, trigger_id: TriggerId) -> U128 {
+        let trigger = self.triggers.get(&trigger_id).expect("Trigger not found");
+        U128(self.cancellation_fee(&trigger, Self::refundable_escrow(&trigger)))
+    }
+
+    /// Cancel an active trigger before it resolves and refund the escrow,
+    /// less the cancellation fee. The fee goes to the treasury earnings, or
+    /// for token escrow to the token's fee balance. Refused once any attestation has found the
+    /// condition met, and inside the cutoff before the event.
+    pub fn cancel_trigger(&mut self, trigger_id: TriggerId) -> Promise {
+        let mut trigger = self.triggers.get(&trigger_id).expect("Trigger not found");
+        assert!(
+            env::predecessor_account_id() == trigger.owner,
+            "Only trigger owner can cancel"
+        );
+        assert!(trigger.status == Status::Active, "Trigger is not active");
+        assert!(
+            trigger.coverage.is_none(),
+            "Covered triggers cannot be cancelled"
+        );
+        assert!(
+            !self.has_positive_attestation(&trigger),
+            "Trigger has a positive attestation"
+        );
+        assert!(
+            !Self::instalment_in_flight(&trigger),
+            "An instalment is still being signed"
+        );
+        assert!(
+            env::block_timestamp() + self.cancellation_policy.cutoff < Self::trigger_event_start(&trigger),
+            "Cancellation window has closed"
+        );
+
+        self.transition(&mut trigger, Status::Cancelled);
+        self.release_exposure(&trigger);
+        self.unindex_expiry(&trigger);
+
+        let refundable = Self::refundable_escrow(&trigger);
+        let fee = self.cancellation_fee(&trigger, refundable);
+        let refund_fee = self.refund_fee(&trigger).min(refundable - fee);
+        let refund_amount = refundable - fee - refund_fee;
+
+        env::log_str(&format!(
+            "Trigger {} cancelled: refunding {} {} after a {} fee",
+            trigger_id,
+            refund_amount,
+            trigger.escrow_token.as_ref().map_or("yoctoNEAR", |token| token.as_str()),
+            fee
+        ));
+
+        // Contributors share the fee pro rata. It leaves the escrow for good,
+        // so a failed refund restores only what was to be refunded.
+        let refund = self.refund_escrow(&trigger, refund_amount, refund_fee);
+        trigger.funded_amount -= fee;
+        self.triggers.insert(&trigger.id, &trigger);
+        self.book_escrow_fee(&trigger.escrow_token, fee);
+        refund
+    }
+}
+
+// ============================================================================
+// Internal Methods
+// ============================================================================
+
+impl TriggerPay {
+    /// Escrow not yet released to scheduled instalments
+    pub(crate) fn refundable_escrow(trigger: &Trigger) -> Balance {
+        trigger
+            .funded_amount
+            .saturating_sub(Self::released_escrow(trigger))
+    }
+
+    fn cancellation_fee(&self, trigger: &Trigger, refundable: Balance) -> Balance {
+        let policy = &self.cancellation_policy;
+        let remaining = Self::trigger_event_start(trigger).saturating_sub(env::block_timestamp());
+        let discount = mul_div(
+            (policy.max_fee_bps - policy.min_fee_bps) as u128,
+            remaining.min(policy.fee_window) as u128,
+            policy.fee_window as u128,
+        );
+        let fee_bps = policy.max_fee_bps as u128 - discount;
+        mul_div(refundable, fee_bps, MAX_BPS as u128)
+    }
+
+    /// Earliest event among the trigger's conditions; open-ended conditions
+    /// count from expiry
+    fn trigger_event_start(trigger: &Trigger) -> u64 {
+        match &trigger.expression {
+            Some(expression) => expression
+                .leaves()
+                .iter()
+                .map(|leaf| Self::event_start(leaf, trigger.expires_at))
+                .min()
+                .unwrap_or(trigger.expires_at),
+            None => Self::event_start(&trigger.condition, trigger.expires_at),
+        }
+    }
+
+    fn has_positive_attestation(&self, trigger: &Trigger) -> bool {
+        trigger.leaves.iter().any(|leaf| leaf.result == Some(true))
+            || self
+                .attestations
+                .get(&trigger.id)
+                .is_some_and(|attestations| attestations.iter().any(|a| a.condition_met))
+    }
+}
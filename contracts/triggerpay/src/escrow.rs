@@ -0,0 +1,419 @@
+use near_sdk::borsh::{BorshDeserialize, BorshSerialize};
+use near_sdk::json_types::U128;
+use near_sdk::serde::{Deserialize, Serialize};
+use near_sdk::{env, AccountId, Gas, NearToken, Promise, PromiseError, PromiseOrValue};
+use schemars::JsonSchema;
+
+use crate::pool::mul_div;
+use crate::{
+    Balance, Condition, Payout, Status, Trigger, TriggerEvent, TriggerId, TriggerPay, TriggerPayExt,
+};
+
+// ============================================================================
+// Constants
+// ============================================================================
+
+const GAS_FOR_FT_TRANSFER: Gas = Gas::from_tgas(10);
+const GAS_FOR_REFUND_CALLBACK: Gas = Gas::from_tgas(10);
+const MIN_TOP_UP: Balance = crate::MINIMUM_DEPOSIT / 10; // 0.1 NEAR
+const MAX_CONTRIBUTORS: usize = 20; // Bounds the transfers made by one refund
+
+// ============================================================================
+// Types
+// ============================================================================
+
+/// A NEP-141 token accepted as trigger escrow
+#[derive(BorshDeserialize, BorshSerialize, Serialize, Deserialize, Clone, Debug, JsonSchema)]
+#[borsh(crate = "near_sdk::borsh")]
+#[serde(crate = "near_sdk::serde")]
+pub struct EscrowToken {
+    pub token_id: String,    // AccountId as string for JsonSchema compatibility
+    pub min_deposit: String, // In the token's smallest unit, string for JSON compatibility
+    pub refund_fee: String,  // Kept from refunds, in the token's smallest unit
+    pub fees: String,        // Cancellation and refund fees kept, withdrawable by the owner
+    pub enabled: bool,
+}
+
+/// Trigger parameters carried in the `msg` of `ft_transfer_call`
+#[derive(Serialize, Deserialize, Clone, Debug, JsonSchema)]
+#[serde(crate = "near_sdk::serde")]
+pub struct FtTriggerMsg {
+    pub condition: Condition,
+    pub payout: Payout,
+    #[serde(default)]
+    pub coverage: Option<String>, // Pool coverage; the transfer is then the premium
+}
+
+/// NEAR added to a trigger by someone other than its owner. The owner's
+/// share is whatever of `funded_amount` is not covered by contributions.
+#[derive(BorshDeserialize, BorshSerialize, Serialize, Deserialize, Clone, Debug, JsonSchema)]
+#[borsh(crate = "near_sdk::borsh")]
+#[serde(crate = "near_sdk::serde")]
+pub struct Contribution {
+    pub funder: String, // AccountId as string for JsonSchema compatibility
+    pub amount: String, // yoctoNEAR, string for JSON compatibility
+}
+
+/// How a new trigger is paid for
+pub(crate) struct Funding {
+    pub amount: Balance,            // Escrow, or the premium for pool-covered triggers
+    pub token: Option<AccountId>,   // NEP-141 token, None for NEAR
+    pub coverage: Option<Balance>,  // Payout underwritten by the pool
+}
+
+impl Funding {
+    pub fn near(amount: Balance) -> Self {
+        Self {
+            amount,
+            token: None,
+            coverage: None,
+        }
+    }
+}
+
+// `msg` that adds the transferred tokens to the liquidity pool
+const POOL_DEPOSIT_MSG: &str = "pool_deposit";
+
+// ============================================================================
+// Token Escrow
+// ============================================================================
+
+#[near_sdk::near]
+impl TriggerPay {
+    /// Whitelist a NEP-141 token for escrow (only owner can call)
+    pub fn register_escrow_token(&mut self, token_id: AccountId, min_deposit: U128, refund_fee: U128) {
+        self.assert_owner();
+        assert!(min_deposit.0 > 0, "Minimum deposit must be positive");
+        assert!(
+            refund_fee.0 < min_deposit.0,
+            "Refund fee must be below the minimum deposit"
+        );
+
+        let token = EscrowToken {
+            token_id: token_id.to_string(),
+            min_deposit: min_deposit.0.to_string(),
+            refund_fee: refund_fee.0.to_string(),
+            fees: "0".to_string(),
+            enabled: true,
+        };
+        self.escrow_tokens.insert(&token_id, &token);
+
+        env::log_str(&format!("Escrow token registered: {}", token_id));
+    }
+
+    /// Enable or disable a token for new triggers (only owner can call)
+    pub fn set_escrow_token_enabled(&mut self, token_id: AccountId, enabled: bool) {
+        self.assert_owner();
+        let mut token = self
+            .escrow_tokens
+            .get(&token_id)
+            .expect("Escrow token not registered");
+        token.enabled = enabled;
+        self.escrow_tokens.insert(&token_id, &token);
+
+        env::log_str(&format!("Escrow token {} enabled: {}", token_id, enabled));
+    }
+
+    pub fn get_escrow_tokens(&self) -> Vec<EscrowToken> {
+        self.escrow_tokens.values().collect()
+    }
+
+    pub fn get_escrow_token(&self, token_id: AccountId) -> Option<EscrowToken> {
+        self.escrow_tokens.get(&token_id)
+    }
+
+    /// Send kept cancellation and refund fees in an escrow token to the owner
+    /// (only owner can call)
+    pub fn withdraw_escrow_token_fees(&mut self, token_id: AccountId, amount: U128) -> Promise {
+        self.assert_owner();
+        let mut token = self
+            .escrow_tokens
+            .get(&token_id)
+            .expect("Escrow token not registered");
+        let fees = token.fees.parse::<Balance>().unwrap_or(0);
+        assert!(
+            amount.0 > 0 && amount.0 <= fees,
+            "Amount exceeds the token's fees"
+        );
+        token.fees = (fees - amount.0).to_string();
+        self.escrow_tokens.insert(&token_id, &token);
+
+        Promise::new(token_id.clone())
+            .function_call(
+                "ft_transfer".to_string(),
+                near_sdk::serde_json::json!({
+                    "receiver_id": self.owner,
+                    "amount": amount.0.to_string(),
+                    "memo": "TriggerPay fees",
+                })
+                .to_string()
+                .into_bytes(),
+                NearToken::from_yoctonear(1), // NEP-141 requires exactly 1 yoctoNEAR
+                GAS_FOR_FT_TRANSFER,
+            )
+            .then(
+                Self::ext(env::current_account_id())
+                    .with_static_gas(GAS_FOR_REFUND_CALLBACK)
+                    .on_fee_withdraw_transfer(token_id, amount),
+            )
+    }
+
+    /// NEP-141 receiver: create a trigger funded with the transferred tokens, or
+    /// provide pool liquidity when `msg` is "pool_deposit". Otherwise `msg` is a
+    /// JSON-encoded `FtTriggerMsg`. Panicking returns the tokens to the sender.
+    pub fn ft_on_transfer(&mut self, sender_id: AccountId, amount: U128, msg: String) -> PromiseOrValue<U128> {
+        let token_id = env::predecessor_account_id();
+        if msg == POOL_DEPOSIT_MSG {
+            assert!(
+                self.pool.token.as_ref() == Some(&token_id),
+                "Pool does not take deposits in this token"
+            );
+            self.internal_pool_deposit(&sender_id, amount.0);
+            return PromiseOrValue::Value(U128(0));
+        }
+
+        let params: FtTriggerMsg =
+            near_sdk::serde_json::from_str(&msg).expect("Invalid trigger parameters in msg");
+        let funding = Funding {
+            amount: amount.0,
+            token: Some(token_id),
+            coverage: params
+                .coverage
+                .map(|c| c.parse().expect("Invalid coverage amount")),
+        };
+        self.internal_create_trigger(sender_id, funding, params.condition, None, params.payout, None);
+
+        // The whole transfer is held in escrow
+        PromiseOrValue::Value(U128(0))
+    }
+
+    /// Add NEAR to an active trigger's escrow. Anyone registered for storage
+    /// may top up at least 0.1 NEAR; the owner may also raise the payout in
+    /// proportion to the added funding. Refunds are shared pro rata among
+    /// contributors, of which a trigger has at most 20.
+    #[payable]
+    pub fn fund_trigger(&mut self, trigger_id: TriggerId, payout_amount: Option<U128>) {
+        let amount = env::attached_deposit().as_yoctonear();
+        let funder = env::predecessor_account_id();
+        let mut trigger = self.triggers.get(&trigger_id).expect("Trigger not found");
+        assert!(trigger.status == Status::Active, "Trigger is not active");
+        assert!(env::block_timestamp() <= trigger.expires_at, "Trigger has expired");
+        assert!(
+            trigger.escrow_token.is_none() && trigger.coverage.is_none(),
+            "Only NEAR-escrowed triggers can be topped up"
+        );
+        assert!(amount >= MIN_TOP_UP, "Top-up is below the 0.1 NEAR minimum");
+
+        self.update_escrow(&mut trigger, |trigger| trigger.funded_amount += amount);
+        let previous_funding = trigger.funded_amount - amount;
+        if funder != trigger.owner {
+            match trigger.contributions.iter_mut().find(|c| c.funder == funder.as_str()) {
+                Some(existing) => {
+                    let total = existing.amount.parse::<Balance>().unwrap_or(0) + amount;
+                    existing.amount = total.to_string();
+                }
+                None => {
+                    assert!(
+                        trigger.contributions.len() < MAX_CONTRIBUTORS,
+                        "Trigger has reached the contributor limit"
+                    );
+                    trigger.contributions.push(Contribution {
+                        funder: funder.to_string(),
+                        amount: amount.to_string(),
+                    })
+                }
+            }
+        }
+
+        if let Some(payout_amount) = payout_amount {
+            assert!(funder == trigger.owner, "Only trigger owner can change the payout");
+            let current = trigger.payout.amount.parse::<Balance>().unwrap_or(0);
+            let covered = mul_div(current, trigger.funded_amount, previous_funding);
+            assert!(
+                payout_amount.0 >= current && payout_amount.0 <= covered,
+                "Payout must stay within the funded coverage"
+            );
+            // Re-count exposure at the new amount, subject to the caps
+            self.release_exposure(&trigger);
+            trigger.payout.amount = payout_amount.0.to_string();
+            self.reserve_exposure(&trigger);
+        }
+
+        // Exposure buckets are charged to the owner; the grown trigger to the funder
+        let initial_storage = env::storage_usage();
+        self.triggers.insert(&trigger_id, &trigger);
+        if funder == trigger.owner {
+            self.charge_trigger_storage(&trigger, initial_storage, true);
+        } else {
+            self.charge_storage(&funder, initial_storage, true);
+        }
+        self.record_event(
+            &trigger,
+            TriggerEvent::Funded {
+                amount: amount.to_string(),
+            },
+        );
+
+        env::log_str(&format!(
+            "Trigger {} topped up by {} with {} yoctoNEAR",
+            trigger_id, funder, amount
+        ));
+    }
+
+    /// Callback from `ft_transfer` during a token refund; restores the trigger
+    /// and takes back the booked refund fee if the transfer failed, so the
+    /// owner can claim again
+    #[private]
+    pub fn on_refund_transfer(&mut self, trigger_id: TriggerId, fee: U128) -> bool {
+        // ft_transfer returns no value, so only a failed promise counts
+        let succeeded = !matches!(env::promise_result_checked(0, 0), Err(PromiseError::Failed));
+        if !succeeded {
+            let mut trigger = self.triggers.get(&trigger_id).expect("Trigger not found");
+            if let Some(token_id) = &trigger.escrow_token {
+                if let Some(mut token) = self.escrow_tokens.get(token_id) {
+                    let fees = token.fees.parse::<Balance>().unwrap_or(0).saturating_sub(fee.0);
+                    token.fees = fees.to_string();
+                    self.escrow_tokens.insert(token_id, &token);
+                }
+            }
+            self.record_event(&trigger, TriggerEvent::RefundFailed);
+            self.transition(&mut trigger, Status::Active);
+            self.restore_exposure(&trigger);
+            // Past expiry the owner reclaims it with claim_refund; re-queueing
+            // it would let keepers sweep it again
+            if env::block_timestamp() <= trigger.expires_at {
+                self.index_expiry(&trigger);
+            }
+
+            env::log_str(&format!("Refund transfer for {} failed, escrow restored", trigger_id));
+        }
+        succeeded
+    }
+
+    /// Callback from `ft_transfer` during a fee withdrawal; restores the fees
+    /// if the transfer failed
+    #[private]
+    pub fn on_fee_withdraw_transfer(&mut self, token_id: AccountId, amount: U128) -> bool {
+        let succeeded = !matches!(env::promise_result_checked(0, 0), Err(PromiseError::Failed));
+        if !succeeded {
+            self.book_escrow_fee(&Some(token_id), amount.0);
+            env::log_str("Fee withdrawal failed, fees restored");
+        }
+        succeeded
+    }
+}
+
+// ============================================================================
+// Internal Methods
+// ============================================================================
+
+impl TriggerPay {
+    /// Check a token deposit against the whitelist and the token's minimum
+    pub(crate) fn assert_token_deposit(&self, token_id: &AccountId, amount: Balance) {
+        let token = self
+            .escrow_tokens
+            .get(token_id)
+            .expect("Token is not accepted for escrow");
+        assert!(token.enabled, "Token is not accepted for escrow");
+        assert!(
+            amount >= token.min_deposit.parse::<Balance>().unwrap_or(0),
+            "Deposit is below the token minimum"
+        );
+    }
+
+    /// Fee kept from a refund, in the escrow's own unit. NEAR escrow pays for
+    /// storage through NEP-145 instead.
+    pub(crate) fn refund_fee(&self, trigger: &Trigger) -> Balance {
+        match &trigger.escrow_token {
+            None => 0,
+            Some(token_id) => self
+                .escrow_tokens
+                .get(token_id)
+                .and_then(|token| token.refund_fee.parse().ok())
+                .unwrap_or(0),
+        }
+    }
+
+    /// Keep a fee taken from escrow: NEAR joins the treasury earnings, tokens
+    /// their registry entry's fee balance
+    pub(crate) fn book_escrow_fee(&mut self, escrow_token: &Option<AccountId>, fee: Balance) {
+        let Some(token_id) = escrow_token else {
+            self.treasury.earnings += fee;
+            return;
+        };
+        let mut token = self
+            .escrow_tokens
+            .get(token_id)
+            .expect("Escrow token not registered");
+        let fees = token.fees.parse::<Balance>().unwrap_or(0) + fee;
+        token.fees = fees.to_string();
+        self.escrow_tokens.insert(token_id, &token);
+    }
+
+    /// Send a refund in the trigger's escrow asset. Token refunds go through
+    /// `ft_transfer` with a callback that restores the trigger on failure;
+    /// NEAR refunds have no callback, so they must be covered by liquid NEAR
+    /// rather than stake. `fee` is the token refund fee kept back from the
+    /// escrow; it is booked to the token's fee balance.
+    pub(crate) fn refund_escrow(&mut self, trigger: &Trigger, amount: Balance, fee: Balance) -> Promise {
+        if trigger.escrow_token.is_none() {
+            assert!(
+                amount <= self.liquid_near(),
+                "Not enough liquid NEAR for the refund; the treasury must unstake first"
+            );
+        }
+        self.record_event(
+            trigger,
+            TriggerEvent::Refunded {
+                amount: amount.to_string(),
+            },
+        );
+        let Some(token_id) = &trigger.escrow_token else {
+            return Self::refund_contributors(trigger, amount);
+        };
+        self.book_escrow_fee(&trigger.escrow_token, fee);
+
+        Promise::new(token_id.clone())
+            .function_call(
+                "ft_transfer".to_string(),
+                near_sdk::serde_json::json!({
+                    "receiver_id": trigger.owner,
+                    "amount": amount.to_string(),
+                    "memo": format!("TriggerPay refund {}", trigger.id),
+                })
+                .to_string()
+                .into_bytes(),
+                NearToken::from_yoctonear(1), // NEP-141 requires exactly 1 yoctoNEAR
+                GAS_FOR_FT_TRANSFER,
+            )
+            .then(
+                Self::ext(env::current_account_id())
+                    .with_static_gas(GAS_FOR_REFUND_CALLBACK)
+                    .on_refund_transfer(trigger.id.clone(), U128(fee)),
+            )
+    }
+}
+
+impl TriggerPay {
+    /// Split a NEAR refund pro rata to funding; the owner keeps any rounding
+    fn refund_contributors(trigger: &Trigger, amount: Balance) -> Promise {
+        let mut owner_share = amount;
+        let mut transfers = Vec::new();
+        for contribution in &trigger.contributions {
+            let contributed = contribution.amount.parse::<Balance>().unwrap_or(0);
+            let share = mul_div(amount, contributed, trigger.funded_amount.max(1));
+            let Ok(funder) = contribution.funder.parse::<AccountId>() else {
+                continue;
+            };
+            if share > 0 {
+                owner_share -= share;
+                transfers.push(Promise::new(funder).transfer(NearToken::from_yoctonear(share)));
+            }
+        }
+
+        transfers.into_iter().fold(
+            Promise::new(trigger.owner.clone()).transfer(NearToken::from_yoctonear(owner_share)),
+            Promise::and,
+        )
+    }
+}
@@ -0,0 +1,192 @@
+use near_sdk::borsh::{BorshDeserialize, BorshSerialize};
+use near_sdk::env;
+use near_sdk::serde::{Deserialize, Serialize};
+use schemars::JsonSchema;
+
+use crate::flight::{format_iso_date, parse_iso_date, NANOS_PER_DAY};
+use crate::{Condition, ConditionType, TriggerPay, TriggerPayExt};
+
+// ============================================================================
+// Constants
+// ============================================================================
+
+const NANOS_PER_MINUTE: i64 = 60 * 1_000_000_000;
+
+// Airport UTC offsets range from UTC-12:00 to UTC+14:00
+const MIN_UTC_OFFSET_MINUTES: i32 = -12 * 60;
+const MAX_UTC_OFFSET_MINUTES: i32 = 14 * 60;
+
+const DEFAULT_MIN_EXPIRY: u64 = NANOS_PER_DAY; // 1 day
+const DEFAULT_MAX_EXPIRY: u64 = 400 * NANOS_PER_DAY; // 400 days
+
+// ============================================================================
+// Types
+// ============================================================================
+
+/// Range, relative to creation, within which users may pick a custom expiry
+#[derive(BorshDeserialize, BorshSerialize, Serialize, Deserialize, Clone, Debug, JsonSchema)]
+#[borsh(crate = "near_sdk::borsh")]
+#[serde(crate = "near_sdk::serde")]
+pub struct ExpiryBounds {
+    pub min_duration: u64, // Nanoseconds
+    pub max_duration: u64, // Nanoseconds
+}
+
+impl Default for ExpiryBounds {
+    fn default() -> Self {
+        Self {
+            min_duration: DEFAULT_MIN_EXPIRY,
+            max_duration: DEFAULT_MAX_EXPIRY,
+        }
+    }
+}
+
+/// Time allowed after a condition's settlement point for the outcome to be
+/// reported, before the escrow becomes refundable
+pub fn default_settlement_buffer(condition_type: &ConditionType) -> u64 {
+    match condition_type {
+        ConditionType::FlightCancellation => 2 * NANOS_PER_DAY,
+        ConditionType::PriceThreshold => 0,
+        ConditionType::HttpJsonPath | ConditionType::OnChainView => 30 * NANOS_PER_DAY,
+        ConditionType::Schedule => 7 * NANOS_PER_DAY,
+        ConditionType::SportsOutcome => 3 * NANOS_PER_DAY,
+        ConditionType::ShipmentStatus => 7 * NANOS_PER_DAY,
+    }
+}
+
+// ============================================================================
+// Expiry Configuration
+// ============================================================================
+
+#[near_sdk::near]
+impl TriggerPay {
+    /// Set the settlement buffer for a condition type (only owner can call)
+    pub fn set_settlement_buffer(&mut self, condition_type: ConditionType, buffer: u64) {
+        self.assert_owner();
+        self.settlement_buffers.insert(&condition_type, &buffer);
+
+        env::log_str(&format!(
+            "Settlement buffer for {:?} set: {} ns",
+            condition_type, buffer
+        ));
+    }
+
+    pub fn get_settlement_buffer(&self, condition_type: ConditionType) -> u64 {
+        self.settlement_buffers
+            .get(&condition_type)
+            .unwrap_or_else(|| default_settlement_buffer(&condition_type))
+    }
+
+    /// Set the range within which users may pick a custom expiry (only owner can call)
+    pub fn set_expiry_bounds(&mut self, min_duration: u64, max_duration: u64) {
+        self.assert_owner();
+        assert!(
+            min_duration <= max_duration,
+            "Minimum expiry must not exceed maximum expiry"
+        );
+        self.expiry_bounds = ExpiryBounds {
+            min_duration,
+            max_duration,
+        };
+
+        env::log_str(&format!(
+            "Expiry bounds set: {} - {} ns",
+            min_duration, max_duration
+        ));
+    }
+
+    pub fn get_expiry_bounds(&self) -> ExpiryBounds {
+        self.expiry_bounds.clone()
+    }
+}
+
+// ============================================================================
+// Internal Methods
+// ============================================================================
+
+impl TriggerPay {
+    /// When a condition stops accepting results: the user's custom expiry if
+    /// set, otherwise the settlement point plus the condition type's buffer
+    pub(crate) fn condition_expiry(&self, condition: &Condition, now: u64) -> u64 {
+        condition.expires_at.unwrap_or_else(|| {
+            Self::settlement_point(condition, now)
+                .checked_add(self.get_settlement_buffer(condition.condition_type.clone()))
+                .expect("Settlement time is too far in the future")
+        })
+    }
+
+    /// The moment a condition's outcome is decided. Open-ended conditions
+    /// (HTTP and on-chain checks) count from creation.
+    pub(crate) fn settlement_point(condition: &Condition, now: u64) -> u64 {
+        let point = match condition.condition_type {
+            ConditionType::FlightCancellation => {
+                parse_iso_date(&condition.flight_date).map(|day| {
+                    flight_day_end(day, condition.utc_offset_minutes)
+                })
+            }
+            ConditionType::PriceThreshold => condition.price.as_ref().map(|p| p.window_end),
+            ConditionType::Schedule => condition.schedule.as_ref().map(|s| s.last_due_at()),
+            ConditionType::SportsOutcome => condition.sports.as_ref().map(|s| s.kickoff_at),
+            ConditionType::ShipmentStatus => condition.shipment.as_ref().map(|s| s.deadline),
+            ConditionType::HttpJsonPath | ConditionType::OnChainView => None,
+        };
+        point.unwrap_or(now)
+    }
+
+    /// The moment a condition's event begins: the start of the local flight
+    /// day, a price window's opening, kickoff. Open-ended conditions have no
+    /// event and use `open_ended` instead.
+    pub(crate) fn event_start(condition: &Condition, open_ended: u64) -> u64 {
+        let start = match condition.condition_type {
+            ConditionType::FlightCancellation => parse_iso_date(&condition.flight_date).map(|day| {
+                flight_day_end(day, condition.utc_offset_minutes).saturating_sub(NANOS_PER_DAY)
+            }),
+            ConditionType::PriceThreshold => condition.price.as_ref().map(|p| p.window_start),
+            ConditionType::Schedule => condition.schedule.as_ref().map(|s| s.start_at),
+            ConditionType::SportsOutcome => condition.sports.as_ref().map(|s| s.kickoff_at),
+            ConditionType::ShipmentStatus => condition.shipment.as_ref().map(|s| s.deadline),
+            ConditionType::HttpJsonPath | ConditionType::OnChainView => None,
+        };
+        start.unwrap_or(open_ended)
+    }
+
+    pub(crate) fn validate_utc_offset(utc_offset_minutes: i32) {
+        assert!(
+            (MIN_UTC_OFFSET_MINUTES..=MAX_UTC_OFFSET_MINUTES).contains(&utc_offset_minutes),
+            "UTC offset must be between -720 and 840 minutes"
+        );
+    }
+
+    /// A custom expiry must fall within the admin bounds and leave room for the
+    /// condition to settle
+    pub(crate) fn validate_custom_expiry(&self, condition: &Condition) {
+        let Some(expires_at) = condition.expires_at else {
+            return;
+        };
+        let now = env::block_timestamp();
+        assert!(
+            expires_at >= now + self.expiry_bounds.min_duration
+                && expires_at <= now + self.expiry_bounds.max_duration,
+            "Custom expiry is outside the allowed bounds"
+        );
+        assert!(
+            expires_at >= Self::settlement_point(condition, now),
+            "Custom expiry is before the condition settles"
+        );
+    }
+}
+
+/// Calendar day a condition settles on: the local flight date for flights,
+/// otherwise the UTC date of the settlement point
+pub(crate) fn settlement_day(condition: &Condition, now: u64) -> String {
+    match condition.condition_type {
+        ConditionType::FlightCancellation => condition.flight_date.clone(),
+        _ => format_iso_date((TriggerPay::settlement_point(condition, now) / NANOS_PER_DAY) as i64),
+    }
+}
+
+/// End of a local calendar day at the departure airport, as UTC nanoseconds
+fn flight_day_end(day: i64, utc_offset_minutes: i32) -> u64 {
+    let end = (day + 1) * NANOS_PER_DAY as i64 - utc_offset_minutes as i64 * NANOS_PER_MINUTE;
+    end.max(0) as u64
+}
@@ -0,0 +1,208 @@
+use near_sdk::borsh::{BorshDeserialize, BorshSerialize};
+use near_sdk::serde::{Deserialize, Serialize};
+use near_sdk::env;
+use schemars::JsonSchema;
+
+use crate::expiry::settlement_day;
+use crate::{Balance, Condition, ConditionType, Payout, Trigger, TriggerPay, TriggerPayExt};
+
+// ============================================================================
+// Types
+// ============================================================================
+
+/// Caps on aggregate payout exposure in one payout token, in its smallest
+/// unit (wei). `None` leaves a dimension uncapped.
+#[derive(BorshDeserialize, BorshSerialize, Serialize, Deserialize, Clone, Debug, Default, JsonSchema)]
+#[borsh(crate = "near_sdk::borsh")]
+#[serde(crate = "near_sdk::serde")]
+pub struct ExposureLimits {
+    pub per_flight: Option<String>, // Per flight number and date
+    pub per_route: Option<String>,
+    pub per_chain: Option<String>,  // Per chain the token is paid out on
+    pub per_day: Option<String>,    // Per calendar day on which triggers settle
+}
+
+#[derive(Serialize, Deserialize, Debug, PartialEq, JsonSchema)]
+#[serde(crate = "near_sdk::serde")]
+pub struct ExposureCapacity {
+    pub bucket: String,            // "flight:AA1234:2026-02-15:ETH", "route:JFK-LAX:ETH", "chain:Base:ETH", "day:2026-02-15:ETH"
+    pub exposure: String,          // Strings for JSON compatibility
+    pub limit: Option<String>,
+    pub remaining: Option<String>, // None when uncapped
+}
+
+// ============================================================================
+// Exposure Limits
+// ============================================================================
+
+#[near_sdk::near]
+impl TriggerPay {
+    /// Set exposure caps for new triggers paying out in `token` (only owner
+    /// can call). Exposure in different tokens is counted apart.
+    pub fn set_exposure_limits(&mut self, token: String, limits: ExposureLimits) {
+        self.assert_owner();
+        let token = token.to_uppercase();
+        for limit in [&limits.per_flight, &limits.per_route, &limits.per_chain, &limits.per_day]
+            .into_iter()
+            .flatten()
+        {
+            assert!(limit.parse::<Balance>().is_ok(), "Invalid exposure limit");
+        }
+        self.exposure_limits.insert(&token, &limits);
+
+        env::log_str(&format!("Exposure limits updated for {}", token));
+    }
+
+    pub fn get_exposure_limits(&self, token: String) -> ExposureLimits {
+        self.exposure_limits
+            .get(&token.to_uppercase())
+            .unwrap_or_default()
+    }
+
+    /// Current exposure and remaining capacity of every bucket a trigger with
+    /// this condition and payout would count against
+    pub fn get_exposure_capacity(&self, condition: Condition, payout: Payout) -> Vec<ExposureCapacity> {
+        let mut condition = condition;
+        Self::normalize_condition(&mut condition);
+        let now = env::block_timestamp();
+        exposure_buckets(&[&condition], &payout, now)
+            .into_iter()
+            .map(|(bucket, kind)| {
+                let exposure = self.exposure.get(&bucket).unwrap_or(0);
+                let limit = self.exposure_limit(&payout.token, kind);
+                ExposureCapacity {
+                    bucket,
+                    exposure: exposure.to_string(),
+                    limit: limit.map(|l| l.to_string()),
+                    remaining: limit.map(|l| l.saturating_sub(exposure).to_string()),
+                }
+            })
+            .collect()
+    }
+}
+
+// ============================================================================
+// Internal Methods
+// ============================================================================
+
+/// Which cap a bucket counts against
+#[derive(Clone, Copy)]
+enum LimitKind {
+    Flight,
+    Route,
+    Chain,
+    Day,
+}
+
+impl TriggerPay {
+    /// Count a new trigger's payout against every bucket, rejecting it if a
+    /// cap would be exceeded. A bucket the trigger opens is charged to its owner.
+    pub(crate) fn reserve_exposure(&mut self, trigger: &Trigger) {
+        let initial_storage = env::storage_usage();
+        for (bucket, token, kind, amount) in trigger_exposure(trigger) {
+            let exposure = self.exposure.get(&bucket).unwrap_or(0) + amount;
+            if let Some(limit) = self.exposure_limit(&token, kind) {
+                assert!(exposure <= limit, "Exposure limit reached for {}", bucket);
+            }
+            self.exposure.insert(&bucket, &exposure);
+        }
+        self.charge_trigger_storage(trigger, initial_storage, false);
+    }
+
+    /// Remove a settled, refunded or expired trigger's payout from its
+    /// buckets, crediting its owner for any bucket it empties
+    pub(crate) fn release_exposure(&mut self, trigger: &Trigger) {
+        let initial_storage = env::storage_usage();
+        for (bucket, _, _, amount) in trigger_exposure(trigger) {
+            let exposure = self.exposure.get(&bucket).unwrap_or(0).saturating_sub(amount);
+            if exposure == 0 {
+                self.exposure.remove(&bucket);
+            } else {
+                self.exposure.insert(&bucket, &exposure);
+            }
+        }
+        self.charge_trigger_storage(trigger, initial_storage, false);
+    }
+
+    /// Put a trigger's payout back after a failed refund; the trigger was
+    /// already admitted, so caps are not re-checked
+    pub(crate) fn restore_exposure(&mut self, trigger: &Trigger) {
+        let initial_storage = env::storage_usage();
+        for (bucket, _, _, amount) in trigger_exposure(trigger) {
+            let exposure = self.exposure.get(&bucket).unwrap_or(0) + amount;
+            self.exposure.insert(&bucket, &exposure);
+        }
+        self.charge_trigger_storage(trigger, initial_storage, false);
+    }
+
+    fn exposure_limit(&self, token: &str, kind: LimitKind) -> Option<Balance> {
+        let limits = self.exposure_limits.get(&token.to_uppercase())?;
+        let limit = match kind {
+            LimitKind::Flight => limits.per_flight,
+            LimitKind::Route => limits.per_route,
+            LimitKind::Chain => limits.per_chain,
+            LimitKind::Day => limits.per_day,
+        };
+        limit.and_then(|l| l.parse().ok())
+    }
+}
+
+/// Most the trigger can pay into each of its buckets, with the bucket's
+/// payout token. Only one payout branch fires, so a bucket both branches
+/// count against takes the larger amount.
+fn trigger_exposure(trigger: &Trigger) -> Vec<(String, String, LimitKind, Balance)> {
+    let conditions = match &trigger.expression {
+        Some(expression) => expression.leaves(),
+        None => vec![&trigger.condition],
+    };
+
+    let mut exposure: Vec<(String, String, LimitKind, Balance)> = Vec::new();
+    for payout in std::iter::once(&trigger.payout).chain(&trigger.fallback_payout) {
+        let amount = payout_exposure(trigger, payout);
+        // Open-ended conditions settle from creation, so buckets stay stable
+        for (bucket, kind) in exposure_buckets(&conditions, payout, trigger.created_at) {
+            match exposure.iter_mut().find(|(existing, ..)| *existing == bucket) {
+                Some((.., total)) => *total = (*total).max(amount),
+                None => exposure.push((bucket, payout.token.to_uppercase(), kind, amount)),
+            }
+        }
+    }
+    exposure
+}
+
+/// Most one payout branch can pay: scheduled triggers pay the amount per instalment
+fn payout_exposure(trigger: &Trigger, payout: &Payout) -> Balance {
+    let amount = payout.amount.parse::<Balance>().unwrap_or(0);
+    match &trigger.condition.schedule {
+        Some(schedule) if trigger.condition.condition_type == ConditionType::Schedule => {
+            amount.saturating_mul(schedule.instalments as Balance)
+        }
+        _ => amount,
+    }
+}
+
+/// Buckets for a set of leaf conditions, without duplicates. Amounts in
+/// different tokens are not comparable, so every bucket is per payout token.
+fn exposure_buckets(conditions: &[&Condition], payout: &Payout, created_at: u64) -> Vec<(String, LimitKind)> {
+    let token = payout.token.to_uppercase();
+    let mut buckets = vec![(format!("chain:{:?}:{}", payout.chain, token), LimitKind::Chain)];
+    for condition in conditions {
+        if condition.condition_type == ConditionType::FlightCancellation {
+            buckets.push((
+                format!("flight:{}:{}:{}", condition.flight_number, condition.flight_date, token),
+                LimitKind::Flight,
+            ));
+            if !condition.route.is_empty() {
+                buckets.push((format!("route:{}:{}", condition.route, token), LimitKind::Route));
+            }
+        }
+        buckets.push((
+            format!("day:{}:{}", settlement_day(condition, created_at), token),
+            LimitKind::Day,
+        ));
+    }
+
+    let mut seen = std::collections::HashSet::new();
+    buckets.retain(|(bucket, _)| seen.insert(bucket.clone()));
+    buckets
+}
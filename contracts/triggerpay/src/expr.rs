@@ -0,0 +1,221 @@
+use near_sdk::borsh::{BorshDeserialize, BorshSerialize};
+use near_sdk::serde::{Deserialize, Serialize};
+use near_sdk::env;
+use schemars::JsonSchema;
+
+use crate::escrow::Funding;
+use crate::{http_spec_hash, Condition, ConditionType, Payout, TriggerId, TriggerPay, TriggerPayExt};
+
+// ============================================================================
+// Constants
+// ============================================================================
+
+pub const MAX_CONDITION_DEPTH: u32 = 4;
+pub const MAX_CONDITION_LEAVES: usize = 16;
+
+// ============================================================================
+// Types
+// ============================================================================
+
+/// Recursive condition expression. Leaves are numbered in depth-first order,
+/// and attestations for composite triggers name the leaf they report on.
+#[derive(BorshDeserialize, BorshSerialize, Serialize, Deserialize, Clone, Debug, JsonSchema)]
+#[borsh(crate = "near_sdk::borsh")]
+#[serde(crate = "near_sdk::serde")]
+pub enum ConditionExpr {
+    Leaf(Box<Condition>),
+    And(Vec<ConditionExpr>),
+    Or(Vec<ConditionExpr>),
+    Not(Box<ConditionExpr>),
+    Threshold { k: u32, of: Vec<ConditionExpr> }, // At least k of the children hold
+}
+
+#[derive(BorshDeserialize, BorshSerialize, Serialize, Deserialize, Clone, Debug, JsonSchema)]
+#[borsh(crate = "near_sdk::borsh")]
+#[serde(crate = "near_sdk::serde")]
+pub struct LeafState {
+    pub result: Option<bool>,           // Latest attested value, None until attested
+    pub condition_hash: Option<String>, // Normalized spec hash for HttpJsonPath leaves
+    pub attested_at: Option<u64>,       // Nanoseconds
+}
+
+impl ConditionExpr {
+    /// Leaf conditions in depth-first order
+    pub fn leaves(&self) -> Vec<&Condition> {
+        let mut leaves = Vec::new();
+        self.collect_leaves(&mut leaves);
+        leaves
+    }
+
+    fn collect_leaves<'a>(&'a self, leaves: &mut Vec<&'a Condition>) {
+        match self {
+            ConditionExpr::Leaf(condition) => leaves.push(condition.as_ref()),
+            ConditionExpr::And(children)
+            | ConditionExpr::Or(children)
+            | ConditionExpr::Threshold { of: children, .. } => {
+                children.iter().for_each(|c| c.collect_leaves(leaves))
+            }
+            ConditionExpr::Not(child) => child.collect_leaves(leaves),
+        }
+    }
+
+    /// Mutable leaf conditions in depth-first order
+    pub fn leaves_mut(&mut self) -> Vec<&mut Condition> {
+        match self {
+            ConditionExpr::Leaf(condition) => vec![condition.as_mut()],
+            ConditionExpr::And(children)
+            | ConditionExpr::Or(children)
+            | ConditionExpr::Threshold { of: children, .. } => {
+                children.iter_mut().flat_map(|c| c.leaves_mut()).collect()
+            }
+            ConditionExpr::Not(child) => child.leaves_mut(),
+        }
+    }
+
+    fn depth(&self) -> u32 {
+        match self {
+            ConditionExpr::Leaf(_) => 1,
+            ConditionExpr::And(children)
+            | ConditionExpr::Or(children)
+            | ConditionExpr::Threshold { of: children, .. } => {
+                1 + children.iter().map(|c| c.depth()).max().unwrap_or(0)
+            }
+            ConditionExpr::Not(child) => 1 + child.depth(),
+        }
+    }
+
+    /// Three-valued evaluation: None while the outcome still depends on unattested leaves
+    pub fn evaluate(&self, results: &[Option<bool>]) -> Option<bool> {
+        let mut next = 0;
+        self.evaluate_from(results, &mut next)
+    }
+
+    fn evaluate_from(&self, results: &[Option<bool>], next: &mut usize) -> Option<bool> {
+        match self {
+            ConditionExpr::Leaf(_) => {
+                let result = results.get(*next).copied().flatten();
+                *next += 1;
+                result
+            }
+            ConditionExpr::And(children) => {
+                let values: Vec<_> = children.iter().map(|c| c.evaluate_from(results, next)).collect();
+                if values.contains(&Some(false)) {
+                    Some(false)
+                } else if values.iter().all(|v| *v == Some(true)) {
+                    Some(true)
+                } else {
+                    None
+                }
+            }
+            ConditionExpr::Or(children) => {
+                let values: Vec<_> = children.iter().map(|c| c.evaluate_from(results, next)).collect();
+                if values.contains(&Some(true)) {
+                    Some(true)
+                } else if values.iter().all(|v| *v == Some(false)) {
+                    Some(false)
+                } else {
+                    None
+                }
+            }
+            ConditionExpr::Not(child) => child.evaluate_from(results, next).map(|v| !v),
+            ConditionExpr::Threshold { k, of } => {
+                let values: Vec<_> = of.iter().map(|c| c.evaluate_from(results, next)).collect();
+                let met = values.iter().filter(|v| **v == Some(true)).count() as u32;
+                let pending = values.iter().filter(|v| v.is_none()).count() as u32;
+                if met >= *k {
+                    Some(true)
+                } else if met + pending < *k {
+                    Some(false)
+                } else {
+                    None
+                }
+            }
+        }
+    }
+
+    fn validate_shape(&self) {
+        match self {
+            ConditionExpr::Leaf(_) => {}
+            ConditionExpr::And(children) | ConditionExpr::Or(children) => {
+                assert!(!children.is_empty(), "AND/OR need at least one child");
+                children.iter().for_each(|c| c.validate_shape());
+            }
+            ConditionExpr::Not(child) => child.validate_shape(),
+            ConditionExpr::Threshold { k, of } => {
+                assert!(
+                    *k >= 1 && *k as usize <= of.len(),
+                    "Threshold k must be between 1 and the number of children"
+                );
+                of.iter().for_each(|c| c.validate_shape());
+            }
+        }
+    }
+}
+
+// ============================================================================
+// Composite Triggers
+// ============================================================================
+
+#[near_sdk::near]
+impl TriggerPay {
+    /// Create a trigger whose condition is a tree of leaf conditions
+    #[payable]
+    pub fn create_composite_trigger(&mut self, expression: ConditionExpr, payout: Payout) -> TriggerId {
+        let deposit = env::attached_deposit();
+        let owner = env::predecessor_account_id();
+
+        // The first leaf doubles as the trigger's primary condition for simple clients
+        let primary = expression
+            .leaves()
+            .first()
+            .map(|leaf| (*leaf).clone())
+            .expect("Condition tree has no leaves");
+
+        self.internal_create_trigger(
+            owner,
+            Funding::near(deposit.as_yoctonear()),
+            primary,
+            Some(expression),
+            payout,
+            None,
+        )
+    }
+}
+
+// ============================================================================
+// Internal Methods
+// ============================================================================
+
+impl TriggerPay {
+    pub(crate) fn validate_expression(&self, expression: &ConditionExpr) {
+        assert!(
+            expression.depth() <= MAX_CONDITION_DEPTH,
+            "Condition tree is too deep"
+        );
+        let leaves = expression.leaves();
+        assert!(
+            leaves.len() <= MAX_CONDITION_LEAVES,
+            "Condition tree has too many leaves"
+        );
+        expression.validate_shape();
+        for leaf in &leaves {
+            assert!(
+                leaf.condition_type != ConditionType::Schedule,
+                "Schedule conditions cannot be combined"
+            );
+            self.validate_condition(leaf);
+        }
+    }
+
+    pub(crate) fn initial_leaf_states(expression: &ConditionExpr) -> Vec<LeafState> {
+        expression
+            .leaves()
+            .into_iter()
+            .map(|leaf| LeafState {
+                result: None,
+                condition_hash: leaf.http.as_ref().map(http_spec_hash),
+                attested_at: None,
+            })
+            .collect()
+    }
+}
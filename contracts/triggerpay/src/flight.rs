@@ -0,0 +1,160 @@
+use near_sdk::env;
+
+use crate::{TriggerPay, TriggerPayExt};
+
+// ============================================================================
+// Constants
+// ============================================================================
+
+pub const DEFAULT_BOOKING_HORIZON_DAYS: u32 = 365;
+pub(crate) const NANOS_PER_DAY: u64 = 24 * 60 * 60 * 1_000_000_000;
+
+// ============================================================================
+// Booking Horizon
+// ============================================================================
+
+#[near_sdk::near]
+impl TriggerPay {
+    /// Set how many days ahead flight triggers may be booked (only owner can call)
+    pub fn set_booking_horizon(&mut self, days: u32) {
+        self.assert_owner();
+        assert!(days > 0, "Booking horizon must be at least one day");
+        self.booking_horizon_days = days;
+
+        env::log_str(&format!("Booking horizon set: {} days", days));
+    }
+
+    pub fn get_booking_horizon(&self) -> u32 {
+        self.booking_horizon_days
+    }
+
+    /// Canonical form of a flight number ("aa 1234" -> "AA1234"), or None if invalid
+    pub fn normalize_flight_number(&self, flight_number: String) -> Option<String> {
+        normalize_flight_number(&flight_number)
+    }
+}
+
+// ============================================================================
+// Internal Methods
+// ============================================================================
+
+impl TriggerPay {
+    pub(crate) fn validate_flight(&self, flight_number: &str, flight_date: &str) {
+        assert!(
+            normalize_flight_number(flight_number).is_some(),
+            "Invalid flight number"
+        );
+        let flight_day = parse_iso_date(flight_date).expect("Invalid flight date");
+
+        let today = (env::block_timestamp() / NANOS_PER_DAY) as i64;
+        assert!(flight_day >= today, "Flight date is in the past");
+        assert!(
+            flight_day <= today + self.booking_horizon_days as i64,
+            "Flight date is beyond the booking horizon"
+        );
+    }
+}
+
+/// Parse an airline designator plus a 1-4 digit flight number. Spaces and
+/// hyphens are ignored, letters are uppercased and leading zeros dropped.
+/// Accepts IATA (2 characters, "AA", "U2") and ICAO (3 letters, "BAW") designators.
+pub fn normalize_flight_number(flight_number: &str) -> Option<String> {
+    let compact: String = flight_number
+        .chars()
+        .filter(|c| !c.is_whitespace() && *c != '-')
+        .collect::<String>()
+        .to_uppercase();
+    if !compact.is_ascii() {
+        return None;
+    }
+
+    if compact.len() < 3 {
+        return None;
+    }
+
+    let icao = compact[..3].bytes().all(|b| b.is_ascii_uppercase());
+    let (designator, number) = compact.split_at(if icao { 3 } else { 2 });
+
+    let valid_designator = icao
+        || (designator.bytes().all(|b| b.is_ascii_alphanumeric())
+            && designator.bytes().any(|b| b.is_ascii_uppercase()));
+    if !valid_designator || number.is_empty() || !number.bytes().all(|b| b.is_ascii_digit()) {
+        return None;
+    }
+
+    let number = match number.trim_start_matches('0') {
+        "" => "0",
+        trimmed => trimmed,
+    };
+    (number.len() <= 4).then(|| format!("{}{}", designator, number))
+}
+
+/// Airline designator of a flight number, as used to key risk data: "AA" for "aa 100"
+pub fn airline_designator(flight_number: &str) -> Option<String> {
+    let canonical = normalize_flight_number(flight_number)?;
+    let icao = canonical[..3].bytes().all(|b| b.is_ascii_uppercase());
+    Some(canonical[..if icao { 3 } else { 2 }].to_string())
+}
+
+/// Parse an origin-destination pair of IATA airport codes: "jfk-lax" becomes "JFK-LAX"
+pub fn normalize_route(route: &str) -> Option<String> {
+    let (origin, destination) = route.trim().split_once('-')?;
+    let valid = |code: &str| code.len() == 3 && code.bytes().all(|b| b.is_ascii_alphabetic());
+    (valid(origin) && valid(destination) && !origin.eq_ignore_ascii_case(destination))
+        .then(|| format!("{}-{}", origin, destination).to_uppercase())
+}
+
+/// Parse a strict "YYYY-MM-DD" calendar date into days since 1970-01-01
+pub fn parse_iso_date(date: &str) -> Option<i64> {
+    let bytes = date.as_bytes();
+    let digits_ok = bytes
+        .iter()
+        .enumerate()
+        .all(|(i, b)| if i == 4 || i == 7 { *b == b'-' } else { b.is_ascii_digit() });
+    if bytes.len() != 10 || !digits_ok {
+        return None;
+    }
+    let year: i64 = date[..4].parse().ok()?;
+    let month: u32 = date[5..7].parse().ok()?;
+    let day: u32 = date[8..10].parse().ok()?;
+
+    let leap = (year % 4 == 0 && year % 100 != 0) || year % 400 == 0;
+    let days_in_month = match month {
+        1 | 3 | 5 | 7 | 8 | 10 | 12 => 31,
+        4 | 6 | 9 | 11 => 30,
+        2 if leap => 29,
+        2 => 28,
+        _ => return None,
+    };
+    if day == 0 || day > days_in_month {
+        return None;
+    }
+
+    Some(days_from_civil(year, month, day))
+}
+
+/// Days since 1970-01-01 for a proleptic Gregorian date (Howard Hinnant's algorithm)
+fn days_from_civil(year: i64, month: u32, day: u32) -> i64 {
+    let year = if month <= 2 { year - 1 } else { year };
+    let era = year.div_euclid(400);
+    let year_of_era = year - era * 400;
+    let month = month as i64;
+    let day_of_year = (153 * (if month > 2 { month - 3 } else { month + 9 }) + 2) / 5 + day as i64 - 1;
+    let day_of_era = year_of_era * 365 + year_of_era / 4 - year_of_era / 100 + day_of_year;
+    era * 146_097 + day_of_era - 719_468
+}
+
+/// Format days since 1970-01-01 as "YYYY-MM-DD" (inverse of `days_from_civil`)
+pub fn format_iso_date(days: i64) -> String {
+    let days = days + 719_468;
+    let era = days.div_euclid(146_097);
+    let day_of_era = days - era * 146_097;
+    let year_of_era =
+        (day_of_era - day_of_era / 1_460 + day_of_era / 36_524 - day_of_era / 146_096) / 365;
+    let day_of_year = day_of_era - (365 * year_of_era + year_of_era / 4 - year_of_era / 100);
+    let mp = (5 * day_of_year + 2) / 153;
+    let day = day_of_year - (153 * mp + 2) / 5 + 1;
+    let month = if mp < 10 { mp + 3 } else { mp - 9 };
+    let year = year_of_era + era * 400 + if month <= 2 { 1 } else { 0 };
+    format!("{:04}-{:02}-{:02}", year, month, day)
+}
@@ -0,0 +1,124 @@
+use near_sdk::borsh::{BorshDeserialize, BorshSerialize};
+use near_sdk::collections::Vector;
+use near_sdk::serde::{Deserialize, Serialize};
+use near_sdk::env;
+use schemars::JsonSchema;
+
+use crate::{Status, StorageKey, Trigger, TriggerId, TriggerPay, TriggerPayExt, TriggerRole};
+
+// ============================================================================
+// Constants
+// ============================================================================
+
+/// Events kept per trigger; older events are overwritten
+pub const MAX_TRIGGER_EVENTS: u64 = 100;
+const MAX_HISTORY_PAGE: u64 = 50;
+
+// ============================================================================
+// Types
+// ============================================================================
+
+/// What happened to a trigger
+#[derive(BorshDeserialize, BorshSerialize, Serialize, Deserialize, Clone, Debug, PartialEq, JsonSchema)]
+#[borsh(crate = "near_sdk::borsh")]
+#[serde(crate = "near_sdk::serde", tag = "kind")]
+pub enum TriggerEvent {
+    Created { amount: String },           // Initial escrow, or the premium for covered triggers
+    Funded { amount: String },            // Top-up by the actor
+    Attested { condition_met: bool, leaf_index: Option<u32> },
+    StatusChanged { from: Status, to: Status },
+    PayoutRequested { instalment: Option<u32> },
+    PayoutSigned { instalment: Option<u32> },
+    PayoutFailed { instalment: Option<u32> },
+    PayoutConfirmed { tx_hash: String },
+    Refunded { amount: String },          // In the escrow's own unit
+    RefundFailed,
+    Transferred { from: String, to: String }, // Trigger token changed hands
+    PayoutChangeProposed { new_address: String, executable_at: u64 },
+    PayoutChangeApplied { old_address: String, new_address: String },
+    PayoutChangeCancelled,
+    RoleAssigned { role: TriggerRole, account_id: Option<String> }, // None clears the role
+}
+
+/// One entry of a trigger's timeline
+#[derive(BorshDeserialize, BorshSerialize, Serialize, Deserialize, Clone, Debug, JsonSchema)]
+#[borsh(crate = "near_sdk::borsh")]
+#[serde(crate = "near_sdk::serde")]
+pub struct TriggerEventView {
+    pub index: u64, // Position in the trigger's full history
+    pub block_height: u64,
+    pub timestamp: u64, // Nanoseconds
+    pub actor: String,  // Predecessor account; the contract itself for callbacks
+    pub event: TriggerEvent,
+}
+
+/// Bounded event log of one trigger. Once full, each new event overwrites
+/// the oldest slot.
+#[derive(BorshDeserialize, BorshSerialize)]
+#[borsh(crate = "near_sdk::borsh")]
+pub struct TriggerHistory {
+    events: Vector<TriggerEventView>,
+    total: u64, // Events ever recorded
+}
+
+// ============================================================================
+// History
+// ============================================================================
+
+#[near_sdk::near]
+impl TriggerPay {
+    /// A trigger's events in order, starting at `from_index` (default: the
+    /// oldest kept). Only the latest `MAX_TRIGGER_EVENTS` events are kept.
+    pub fn get_trigger_history(
+        &self,
+        trigger_id: TriggerId,
+        from_index: Option<u64>,
+        limit: Option<u64>,
+    ) -> Vec<TriggerEventView> {
+        let Some(history) = self.trigger_history.get(&trigger_id) else {
+            return Vec::new();
+        };
+        let oldest = history.total.saturating_sub(MAX_TRIGGER_EVENTS);
+        let start = from_index.unwrap_or(oldest).max(oldest);
+        let limit = limit.unwrap_or(MAX_HISTORY_PAGE).min(MAX_HISTORY_PAGE);
+
+        (start..history.total.min(start.saturating_add(limit)))
+            .filter_map(|index| history.events.get(index % MAX_TRIGGER_EVENTS))
+            .collect()
+    }
+}
+
+// ============================================================================
+// Internal Methods
+// ============================================================================
+
+impl TriggerPay {
+    /// Append an event to the trigger's history, charging its storage to the
+    /// trigger owner
+    pub(crate) fn record_event(&mut self, trigger: &Trigger, event: TriggerEvent) {
+        let initial_storage = env::storage_usage();
+        let mut history = self.trigger_history.get(&trigger.id).unwrap_or_else(|| TriggerHistory {
+            events: Vector::new(StorageKey::TriggerHistoryInner {
+                trigger_id: trigger.id.clone(),
+            }),
+            total: 0,
+        });
+
+        let entry = TriggerEventView {
+            index: history.total,
+            block_height: env::block_height(),
+            timestamp: env::block_timestamp(),
+            actor: env::predecessor_account_id().to_string(),
+            event,
+        };
+        if history.events.len() < MAX_TRIGGER_EVENTS {
+            history.events.push(&entry);
+        } else {
+            history.events.replace(history.total % MAX_TRIGGER_EVENTS, &entry);
+        }
+        history.total += 1;
+        self.trigger_history.insert(&trigger.id, &history);
+
+        self.charge_trigger_storage(trigger, initial_storage, false);
+    }
+}
@@ -0,0 +1,235 @@
+use std::cmp::Ordering;
+
+use near_sdk::borsh::{BorshDeserialize, BorshSerialize};
+use near_sdk::serde::{Deserialize, Serialize};
+use near_sdk::env;
+use schemars::JsonSchema;
+
+use crate::{TriggerPay, TriggerPayExt};
+
+// ============================================================================
+// Types
+// ============================================================================
+
+#[derive(BorshDeserialize, BorshSerialize, Serialize, Deserialize, Clone, Debug, PartialEq, JsonSchema)]
+#[borsh(crate = "near_sdk::borsh")]
+#[serde(crate = "near_sdk::serde")]
+pub enum CompareOp {
+    Eq,
+    Ne,
+    Gt,
+    Gte,
+    Lt,
+    Lte,
+}
+
+#[derive(BorshDeserialize, BorshSerialize, Serialize, Deserialize, Clone, Debug, JsonSchema)]
+#[borsh(crate = "near_sdk::borsh")]
+#[serde(crate = "near_sdk::serde")]
+pub struct HttpJsonPathCondition {
+    pub url_template: String, // "https://api.example.com/rain/{date}"
+    pub json_path: String,    // "$.data.rainfall_mm"
+    pub operator: CompareOp,
+    pub expected: String,     // Compared numerically for Gt/Gte/Lt/Lte
+}
+
+#[derive(BorshDeserialize, BorshSerialize, Serialize, Deserialize, Clone, Debug, JsonSchema)]
+#[borsh(crate = "near_sdk::borsh")]
+#[serde(crate = "near_sdk::serde")]
+pub struct HttpObservation {
+    pub spec_hash: String,       // Hex-encoded SHA256 of the normalized condition spec
+    pub extracted_value: String, // Value found at json_path
+    pub response_hash: String,   // Hex-encoded SHA256 of the raw response body
+}
+
+// ============================================================================
+// Domain Allowlist
+// ============================================================================
+
+#[near_sdk::near]
+impl TriggerPay {
+    /// Allow HttpJsonPath conditions to query a domain (only owner can call)
+    pub fn add_allowed_domain(&mut self, domain: String) {
+        self.assert_owner();
+        let domain = domain.trim().to_lowercase();
+        assert!(!domain.is_empty(), "Domain is required");
+        self.allowed_domains.insert(&domain);
+
+        env::log_str(&format!("Domain allowed: {}", domain));
+    }
+
+    /// Stop new HttpJsonPath conditions from querying a domain (only owner can call)
+    pub fn remove_allowed_domain(&mut self, domain: String) {
+        self.assert_owner();
+        let domain = domain.trim().to_lowercase();
+        assert!(self.allowed_domains.remove(&domain), "Domain is not allowed");
+
+        env::log_str(&format!("Domain removed: {}", domain));
+    }
+
+    /// Get all domains HttpJsonPath conditions may query
+    pub fn get_allowed_domains(&self) -> Vec<String> {
+        self.allowed_domains.to_vec()
+    }
+}
+
+// ============================================================================
+// Internal Methods
+// ============================================================================
+
+impl TriggerPay {
+    pub(crate) fn validate_http_condition(&self, condition: &HttpJsonPathCondition) {
+        let host = url_host(&condition.url_template).expect("URL must be https://<host>/...");
+        assert!(
+            self.allowed_domains.contains(&host),
+            "Domain is not on the allowlist"
+        );
+        assert!(
+            condition.json_path.trim().starts_with('$'),
+            "JSON path must start with $"
+        );
+        if is_numeric_op(&condition.operator) {
+            assert!(
+                compare_numeric(&condition.expected, &condition.expected).is_some(),
+                "Expected value must be numeric for ordered comparisons"
+            );
+        }
+    }
+
+    /// Check an observation against the condition and return whether the comparison holds.
+    /// Panics if the agent evaluated a different spec than the one stored.
+    pub(crate) fn evaluate_http(
+        &self,
+        condition: &HttpJsonPathCondition,
+        spec_hash: &str,
+        observation: &HttpObservation,
+    ) -> bool {
+        assert!(
+            observation.spec_hash == spec_hash,
+            "Observation is for a different condition spec"
+        );
+        assert!(
+            observation.response_hash.len() == 64,
+            "Invalid response hash"
+        );
+
+        compare_values(
+            &observation.extracted_value,
+            &condition.operator,
+            &condition.expected,
+        )
+        .expect("Extracted value is not numeric")
+    }
+}
+
+/// Apply `operator` to two values. Returns None if an ordered comparison
+/// is asked of a value that is not a decimal number.
+pub(crate) fn compare_values(actual: &str, operator: &CompareOp, expected: &str) -> Option<bool> {
+    let actual = actual.trim();
+    let expected = expected.trim();
+    let ordering = if is_numeric_op(operator) {
+        compare_numeric(actual, expected)?
+    } else {
+        // Eq/Ne compare numerically when both sides are numbers, otherwise as text
+        compare_numeric(actual, expected).unwrap_or_else(|| actual.cmp(expected))
+    };
+
+    Some(match operator {
+        CompareOp::Eq => ordering == Ordering::Equal,
+        CompareOp::Ne => ordering != Ordering::Equal,
+        CompareOp::Gt => ordering == Ordering::Greater,
+        CompareOp::Gte => ordering != Ordering::Less,
+        CompareOp::Lt => ordering == Ordering::Less,
+        CompareOp::Lte => ordering != Ordering::Greater,
+    })
+}
+
+/// Hex-encoded SHA256 over the normalized spec. Scheme and host are lowercased
+/// and surrounding whitespace is dropped so cosmetic differences hash the same.
+/// The operator is hashed in its JSON form, which agents can reproduce.
+pub fn http_spec_hash(condition: &HttpJsonPathCondition) -> String {
+    let url = condition.url_template.trim();
+    let url = match url.find("://") {
+        Some(scheme_end) => {
+            let host_end = url[scheme_end + 3..]
+                .find('/')
+                .map(|i| scheme_end + 3 + i)
+                .unwrap_or(url.len());
+            format!("{}{}", url[..host_end].to_lowercase(), &url[host_end..])
+        }
+        None => url.to_string(),
+    };
+
+    let operator = near_sdk::serde_json::to_string(&condition.operator).expect("Operator serializes");
+    let spec = format!(
+        "{}\n{}\n{}\n{}",
+        url,
+        condition.json_path.trim(),
+        operator,
+        condition.expected.trim()
+    );
+    hex::encode(env::sha256(spec.as_bytes()))
+}
+
+/// Extract the lowercased host from an https URL, rejecting templated hosts
+fn url_host(url: &str) -> Option<String> {
+    let rest = url.trim().strip_prefix("https://")?;
+    let host = rest.split(['/', '?', '#']).next()?.to_lowercase();
+    let host = host.split(':').next()?.to_string();
+    if host.is_empty() || host.contains(['{', '}', '@']) {
+        return None;
+    }
+    Some(host)
+}
+
+pub(crate) fn is_numeric_op(operator: &CompareOp) -> bool {
+    !matches!(operator, CompareOp::Eq | CompareOp::Ne)
+}
+
+/// Compare two decimal strings ("-1.5", "42") without floating point
+pub(crate) fn compare_numeric(a: &str, b: &str) -> Option<Ordering> {
+    let (a_neg, a_whole, a_frac) = split_decimal(a)?;
+    let (b_neg, b_whole, b_frac) = split_decimal(b)?;
+
+    let a_zero = a_whole.is_empty() && a_frac.is_empty();
+    let b_zero = b_whole.is_empty() && b_frac.is_empty();
+    let a_neg = a_neg && !a_zero;
+    let b_neg = b_neg && !b_zero;
+
+    let magnitude = a_whole
+        .len()
+        .cmp(&b_whole.len())
+        .then_with(|| a_whole.cmp(b_whole))
+        .then_with(|| {
+            let width = a_frac.len().max(b_frac.len());
+            format!("{:0<width$}", a_frac).cmp(&format!("{:0<width$}", b_frac))
+        });
+
+    Some(match (a_neg, b_neg) {
+        (false, false) => magnitude,
+        (true, true) => magnitude.reverse(),
+        (true, false) => Ordering::Less,
+        (false, true) => Ordering::Greater,
+    })
+}
+
+/// Split into sign, whole digits without leading zeros and fraction without trailing zeros
+fn split_decimal(value: &str) -> Option<(bool, &str, &str)> {
+    let value = value.trim();
+    let (negative, digits) = match value.strip_prefix('-') {
+        Some(rest) => (true, rest),
+        None => (false, value),
+    };
+    let (whole, fraction) = digits.split_once('.').unwrap_or((digits, ""));
+    if whole.is_empty()
+        || !whole.bytes().all(|b| b.is_ascii_digit())
+        || !fraction.bytes().all(|b| b.is_ascii_digit())
+    {
+        return None;
+    }
+    Some((
+        negative,
+        whole.trim_start_matches('0'),
+        fraction.trim_end_matches('0'),
+    ))
+}
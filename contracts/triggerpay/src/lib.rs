@@ -1,19 +1,77 @@
 use near_sdk::borsh::{BorshDeserialize, BorshSerialize};
-use near_sdk::collections::{LookupMap, UnorderedMap, Vector};
+use near_sdk::collections::{LookupMap, TreeMap, UnorderedMap, UnorderedSet, Vector};
 use near_sdk::serde::{Deserialize, Serialize};
-use near_sdk::{env, AccountId, BorshStorageKey, Gas, NearToken, Promise, PublicKey};
+use near_sdk::{env, AccountId, BorshStorageKey, Gas, NearToken, Promise, PublicKey, StorageUsage};
 use schemars::JsonSchema;
 
 pub type Balance = u128;
 use sha2::{Digest, Sha256};
 
+mod cancel;
+mod escrow;
+mod expiry;
+mod exposure;
+mod expr;
+mod flight;
+mod history;
+mod http;
+mod lifecycle;
+mod nft;
+mod onchain;
+mod payout_change;
+mod pool;
+mod price;
+mod pricing;
+mod roles;
+mod schedule;
+mod shares;
+mod shipment;
+mod sports;
+mod storage;
+mod sweep;
+mod template;
+mod treasury;
+
+pub use cancel::CancellationPolicy;
+use escrow::Funding;
+pub use escrow::{Contribution, EscrowToken, FtTriggerMsg};
+pub use expiry::{default_settlement_buffer, ExpiryBounds};
+pub use exposure::{ExposureCapacity, ExposureLimits};
+pub use expr::{ConditionExpr, LeafState, MAX_CONDITION_DEPTH, MAX_CONDITION_LEAVES};
+pub use flight::{
+    airline_designator, format_iso_date, normalize_flight_number, normalize_route, parse_iso_date,
+    DEFAULT_BOOKING_HORIZON_DAYS,
+};
+use history::TriggerHistory;
+pub use history::{TriggerEvent, TriggerEventView, MAX_TRIGGER_EVENTS};
+pub use http::{http_spec_hash, CompareOp, HttpJsonPathCondition, HttpObservation};
+pub use lifecycle::StatusChange;
+use nft::TokenApprovals;
+pub use nft::{NFTContractMetadata, Token, TokenMetadata};
+pub use onchain::OnChainCondition;
+pub use payout_change::{PayoutChange, DEFAULT_PAYOUT_CHANGE_DELAY};
+use pool::Pool;
+pub use pool::PoolView;
+pub use price::{AssetInfo, PriceCondition, PriceDirection, PriceObservation};
+pub use pricing::{PremiumQuote, PricingConfig, RiskRate};
+pub use roles::TriggerRole;
+pub use schedule::{Instalment, InstalmentStatus, ScheduleCondition};
+pub use shares::FungibleTokenMetadata;
+pub use shipment::{tracking_hash, ShipmentCondition, ShipmentObservation, ShipmentStage};
+pub use sports::{MatchStatus, OutcomeSelector, SportsCondition, SportsObservation};
+use storage::StorageAccount;
+pub use storage::{StorageBalance, StorageBalanceBounds};
+pub use sweep::DEFAULT_KEEPER_FEE;
+pub use template::{ConditionTemplate, ParamBounds, TemplateParams, TemplateStatus};
+use treasury::Treasury;
+pub use treasury::{TreasuryPolicy, TreasuryView};
+
 // ============================================================================
 // Constants
 // ============================================================================
 
 const MINIMUM_DEPOSIT: Balance = 1_000_000_000_000_000_000_000_000; // 1 NEAR
-const EXECUTION_FEE: Balance = 2_000_000_000_000_000_000_000_000; // 2 NEAR
-const GAS_FOR_SIGN: Gas = Gas::from_tgas(250);
+pub(crate) const GAS_FOR_SIGN: Gas = Gas::from_tgas(250);
 const MPC_CONTRACT: &str = "v1.signer-prod.testnet";
 
 // ============================================================================
@@ -28,6 +86,29 @@ enum StorageKey {
     UserTriggersInner { account_hash: Vec<u8> },
     Attestations,
     AttestationsInner { trigger_id: String },
+    Assets,
+    AllowedDomains,
+    AllowedViews,
+    SettlementBuffers,
+    Templates,
+    EscrowTokens,
+    StorageAccounts,
+    TriggerStorage,
+    LpShares,
+    CoverageRates,
+    RiskRates,
+    ExposureLimits,
+    Exposure,
+    StakingPools,
+    ExpiryIndex,
+    StatusHistory,
+    StatusHistoryInner { trigger_id: String },
+    TriggerHistory,
+    TriggerHistoryInner { trigger_id: String },
+    NftApprovals,
+    PayoutChanges,
+    RoleTriggers,
+    RoleTriggersInner { role_hash: Vec<u8> },
 }
 
 // ============================================================================
@@ -41,6 +122,12 @@ pub type TriggerId = String;
 #[serde(crate = "near_sdk::serde")]
 pub enum ConditionType {
     FlightCancellation,
+    PriceThreshold,
+    HttpJsonPath,
+    OnChainView,
+    Schedule,
+    SportsOutcome,
+    ShipmentStatus,
 }
 
 #[derive(BorshDeserialize, BorshSerialize, Serialize, Deserialize, Clone, Debug, JsonSchema)]
@@ -48,11 +135,31 @@ pub enum ConditionType {
 #[serde(crate = "near_sdk::serde")]
 pub struct Condition {
     pub condition_type: ConditionType,
+    #[serde(default)]
     pub flight_number: String,
+    #[serde(default)]
     pub flight_date: String, // ISO 8601 date: "2026-02-15"
+    #[serde(default)]
+    pub route: String, // Optional origin and destination IATA codes: "JFK-LAX"
+    #[serde(default)]
+    pub utc_offset_minutes: i32, // Departure airport's UTC offset: -300 for New York
+    #[serde(default)]
+    pub price: Option<PriceCondition>, // Required for PriceThreshold
+    #[serde(default)]
+    pub http: Option<HttpJsonPathCondition>, // Required for HttpJsonPath
+    #[serde(default)]
+    pub onchain: Option<OnChainCondition>, // Required for OnChainView
+    #[serde(default)]
+    pub schedule: Option<ScheduleCondition>, // Required for Schedule
+    #[serde(default)]
+    pub sports: Option<SportsCondition>, // Required for SportsOutcome
+    #[serde(default)]
+    pub shipment: Option<ShipmentCondition>, // Required for ShipmentStatus
+    #[serde(default)]
+    pub expires_at: Option<u64>, // Custom expiry in nanoseconds, within the admin bounds
 }
 
-#[derive(BorshDeserialize, BorshSerialize, Serialize, Deserialize, Clone, Debug, JsonSchema)]
+#[derive(BorshDeserialize, BorshSerialize, Serialize, Deserialize, Clone, Debug, PartialEq, JsonSchema)]
 #[borsh(crate = "near_sdk::borsh")]
 #[serde(crate = "near_sdk::serde")]
 pub enum Chain {
@@ -71,14 +178,29 @@ pub struct Payout {
     pub chain: Chain,      // Target blockchain
 }
 
+// Which payout a two-outcome trigger sent
+#[derive(BorshDeserialize, BorshSerialize, Serialize, Deserialize, Clone, Debug, PartialEq, JsonSchema)]
+#[borsh(crate = "near_sdk::borsh")]
+#[serde(crate = "near_sdk::serde")]
+pub enum PayoutBranch {
+    Primary,  // Condition met: `payout`
+    Fallback, // Deadline missed: `fallback_payout`
+}
+
 #[derive(BorshDeserialize, BorshSerialize, Serialize, Deserialize, Clone, Debug, PartialEq, JsonSchema)]
 #[borsh(crate = "near_sdk::borsh")]
 #[serde(crate = "near_sdk::serde")]
 pub enum Status {
-    Active,
-    Executed,
-    Refunded,
-    Expired,
+    Pending,       // Created, not yet watching its condition
+    Active,        // Watching its condition
+    PendingPayout, // Condition met, payout signature requested
+    PayoutSigned,  // Payout signed by the MPC network
+    Settled,       // Payout confirmed on the destination chain
+    PayoutFailed,  // Signing failed; may be retried
+    Disputed,      // Frozen until the owner resolves it
+    Cancelled,     // Withdrawn by its owner before resolution
+    Refunded,      // Condition not met; escrow returned
+    Expired,       // Unresolved by expiry; escrow returned
 }
 
 #[derive(BorshDeserialize, BorshSerialize, Serialize, Deserialize, Clone, Debug)]
@@ -86,14 +208,27 @@ pub enum Status {
 #[serde(crate = "near_sdk::serde")]
 pub struct Trigger {
     pub id: TriggerId,
-    pub owner: AccountId,
+    pub owner: AccountId,                // Funder: controls the trigger and receives refunds
+    pub traveller: Option<AccountId>,    // Covered party
+    pub beneficiary: Option<AccountId>,  // Controls the payout address; the owner if unset
     pub condition: Condition,
     pub payout: Payout,
     pub funded_amount: Balance,
+    pub escrow_token: Option<AccountId>, // NEP-141 token holding the escrow, None for NEAR
+    pub coverage: Option<Balance>,       // Payout reserved in the pool for covered triggers
+    pub contributions: Vec<Contribution>, // Top-ups from accounts other than the owner
     pub status: Status,
     pub created_at: u64,      // Nanoseconds
     pub expires_at: u64,      // Nanoseconds
     pub executed_tx: Option<String>, // Transaction hash if executed
+    pub condition_hash: Option<String>, // Normalized spec hash for HttpJsonPath
+    pub expression: Option<ConditionExpr>, // Set for composite triggers
+    pub leaves: Vec<LeafState>,           // Per-leaf state, in depth-first order
+    pub instalments: Vec<Instalment>,     // Payouts made by a Schedule condition
+    pub fallback_payout: Option<Payout>,  // Paid instead of `payout` if a deadline is missed
+    pub fired_branch: Option<PayoutBranch>,
+    pub template_id: Option<String>, // Set for triggers created from a template
+    pub yield_checkpoint: Balance,   // Treasury yield accumulator when last credited
 }
 
 #[derive(BorshDeserialize, BorshSerialize, Serialize, Deserialize, Clone, Debug, JsonSchema)]
@@ -106,6 +241,16 @@ pub struct Attestation {
     pub flight_status: String,     // "scheduled", "cancelled", "departed"
     pub condition_met: bool,
     pub signature: String,         // Hex-encoded Ed25519 signature from TEE
+    #[serde(default)]
+    pub price_observation: Option<PriceObservation>, // Required for PriceThreshold
+    #[serde(default)]
+    pub http_observation: Option<HttpObservation>, // Required for HttpJsonPath
+    #[serde(default)]
+    pub leaf_index: Option<u32>, // Required for composite triggers
+    #[serde(default)]
+    pub sports_observation: Option<SportsObservation>, // Required for SportsOutcome
+    #[serde(default)]
+    pub shipment_observation: Option<ShipmentObservation>, // Required for ShipmentStatus
 }
 
 // View types (for returning data without internal fields)
@@ -114,13 +259,25 @@ pub struct Attestation {
 pub struct TriggerView {
     pub id: TriggerId,
     pub owner: String, // AccountId as string for JsonSchema compatibility
+    pub traveller: Option<String>,
+    pub beneficiary: Option<String>,
     pub condition: Condition,
     pub payout: Payout,
     pub funded_amount: String, // String for JSON compatibility
+    pub escrow_token: Option<String>,
+    pub coverage: Option<String>,
+    pub contributions: Vec<Contribution>,
     pub status: Status,
     pub created_at: u64,
     pub expires_at: u64,
     pub executed_tx: Option<String>,
+    pub condition_hash: Option<String>,
+    pub expression: Option<ConditionExpr>,
+    pub leaves: Vec<LeafState>,
+    pub instalments: Vec<Instalment>,
+    pub fallback_payout: Option<Payout>,
+    pub fired_branch: Option<PayoutBranch>,
+    pub template_id: Option<String>,
     pub attestation_count: u32,
 }
 
@@ -142,6 +299,63 @@ pub struct TriggerPay {
     owner: AccountId,
     // Counter for generating unique IDs
     trigger_counter: u64,
+    // Assets that PriceThreshold conditions may reference
+    assets: UnorderedMap<String, AssetInfo>,
+    // Domains that HttpJsonPath conditions may query
+    allowed_domains: UnorderedSet<String>,
+    // Contract and view method pairs that OnChainView conditions may call
+    allowed_views: UnorderedSet<(AccountId, String)>,
+    // How many days ahead flight triggers may be booked
+    booking_horizon_days: u32,
+    // Per-condition-type overrides of the default settlement buffer
+    settlement_buffers: LookupMap<ConditionType, u64>,
+    // Range within which users may pick a custom expiry
+    expiry_bounds: ExpiryBounds,
+    // Admin-defined trigger products by template ID
+    templates: UnorderedMap<String, ConditionTemplate>,
+    // NEP-141 tokens accepted as escrow
+    escrow_tokens: UnorderedMap<AccountId, EscrowToken>,
+    // NEP-145 storage balances and bytes used per account
+    storage_accounts: LookupMap<AccountId, StorageAccount>,
+    // Bytes each trigger's state holds against its owner's storage balance
+    trigger_storage: LookupMap<TriggerId, StorageUsage>,
+    // Underwriting pool for covered triggers
+    pool: Pool,
+    // Pool asset backing one whole unit of each payout token
+    coverage_rates: LookupMap<String, Balance>,
+    // Pool shares held by each liquidity provider
+    lp_shares: LookupMap<AccountId, Balance>,
+    // Historical risk data keyed by route, airline and month
+    risk_rates: UnorderedMap<String, RiskRate>,
+    // Loadings and slippage applied to premium quotes
+    pricing: PricingConfig,
+    // Caps on aggregate payout exposure per payout token
+    exposure_limits: LookupMap<String, ExposureLimits>,
+    // Payout exposure of active triggers per flight, route, chain and day,
+    // each in one payout token
+    exposure: LookupMap<String, Balance>,
+    // Fee curve and cutoff for cancelling before resolution
+    cancellation_policy: CancellationPolicy,
+    // Staking pools the treasury may delegate to
+    staking_pools: UnorderedSet<AccountId>,
+    // Delegated idle NEAR and staking rewards
+    treasury: Treasury,
+    // Active triggers ordered by expiry, for the sweeper
+    expiry_index: TreeMap<(u64, TriggerId), ()>,
+    // Fee kept from each swept NEAR refund and paid to the keeper
+    keeper_fee: Balance,
+    // Every status transition of each trigger
+    status_history: LookupMap<TriggerId, Vector<StatusChange>>,
+    // Bounded event timeline of each trigger
+    trigger_history: LookupMap<TriggerId, TriggerHistory>,
+    // NEP-178 approvals of trigger tokens
+    nft_approvals: LookupMap<TriggerId, TokenApprovals>,
+    // Time-locked payout address changes awaiting their delay
+    payout_changes: LookupMap<TriggerId, PayoutChange>,
+    // Delay before a proposed payout address can be applied
+    payout_change_delay: u64,
+    // Triggers naming each account as traveller or beneficiary
+    role_triggers: LookupMap<(TriggerRole, AccountId), UnorderedSet<TriggerId>>,
 }
 
 // Implement Default to panic - we require explicit initialization via `new()`
@@ -162,6 +376,34 @@ impl TriggerPay {
             agent_public_key: None,
             owner,
             trigger_counter: 0,
+            assets: UnorderedMap::new(StorageKey::Assets),
+            allowed_domains: UnorderedSet::new(StorageKey::AllowedDomains),
+            allowed_views: UnorderedSet::new(StorageKey::AllowedViews),
+            booking_horizon_days: DEFAULT_BOOKING_HORIZON_DAYS,
+            settlement_buffers: LookupMap::new(StorageKey::SettlementBuffers),
+            expiry_bounds: ExpiryBounds::default(),
+            templates: UnorderedMap::new(StorageKey::Templates),
+            escrow_tokens: UnorderedMap::new(StorageKey::EscrowTokens),
+            storage_accounts: LookupMap::new(StorageKey::StorageAccounts),
+            trigger_storage: LookupMap::new(StorageKey::TriggerStorage),
+            pool: Pool::default(),
+            coverage_rates: LookupMap::new(StorageKey::CoverageRates),
+            lp_shares: LookupMap::new(StorageKey::LpShares),
+            risk_rates: UnorderedMap::new(StorageKey::RiskRates),
+            pricing: PricingConfig::default(),
+            exposure_limits: LookupMap::new(StorageKey::ExposureLimits),
+            exposure: LookupMap::new(StorageKey::Exposure),
+            cancellation_policy: CancellationPolicy::default(),
+            staking_pools: UnorderedSet::new(StorageKey::StakingPools),
+            treasury: Treasury::default(),
+            expiry_index: TreeMap::new(StorageKey::ExpiryIndex),
+            keeper_fee: DEFAULT_KEEPER_FEE,
+            status_history: LookupMap::new(StorageKey::StatusHistory),
+            trigger_history: LookupMap::new(StorageKey::TriggerHistory),
+            nft_approvals: LookupMap::new(StorageKey::NftApprovals),
+            payout_changes: LookupMap::new(StorageKey::PayoutChanges),
+            payout_change_delay: DEFAULT_PAYOUT_CHANGE_DELAY,
+            role_triggers: LookupMap::new(StorageKey::RoleTriggers),
         }
     }
 
@@ -181,90 +423,19 @@ impl TriggerPay {
     // ========================================================================
 
     /// Create a new trigger with attached deposit for escrow
+    /// Flight numbers are stored in canonical form ("aa 1234" -> "AA1234")
     #[payable]
     pub fn create_trigger(&mut self, condition: Condition, payout: Payout) -> TriggerId {
         let deposit = env::attached_deposit();
         let owner = env::predecessor_account_id();
 
-        // Validate deposit
-        assert!(
-            deposit.as_yoctonear() >= MINIMUM_DEPOSIT,
-            "Minimum deposit is 1 NEAR"
-        );
-
-        // Validate condition
-        assert!(
-            !condition.flight_number.is_empty(),
-            "Flight number is required"
-        );
-        assert!(
-            !condition.flight_date.is_empty(),
-            "Flight date is required"
-        );
-
-        // Validate payout
-        assert!(!payout.amount.is_empty(), "Payout amount is required");
-        assert!(!payout.address.is_empty(), "Payout address is required");
-        assert!(
-            payout.address.starts_with("0x") && payout.address.len() == 42,
-            "Invalid Ethereum address format"
-        );
-
-        // Generate unique ID
-        self.trigger_counter += 1;
-        let trigger_id = format!("trig_{:08x}", self.trigger_counter);
-
-        // Calculate expiration (flight date + 24 hours buffer)
-        // For MVP, set expiration to 30 days from now
-        let now = env::block_timestamp();
-        let thirty_days_ns = 30 * 24 * 60 * 60 * 1_000_000_000u64;
-        let expires_at = now + thirty_days_ns;
-
-        let trigger = Trigger {
-            id: trigger_id.clone(),
-            owner: owner.clone(),
-            condition,
-            payout,
-            funded_amount: deposit.as_yoctonear(),
-            status: Status::Active,
-            created_at: now,
-            expires_at,
-            executed_tx: None,
-        };
-
-        // Store trigger
-        self.triggers.insert(&trigger_id, &trigger);
-
-        // Add to user's triggers
-        let mut user_trigger_ids = self
-            .user_triggers
-            .get(&owner)
-            .unwrap_or_else(|| Vector::new(StorageKey::UserTriggersInner {
-                account_hash: env::sha256(owner.as_bytes()),
-            }));
-        user_trigger_ids.push(&trigger_id);
-        self.user_triggers.insert(&owner, &user_trigger_ids);
-
-        // Initialize attestations vector for this trigger
-        let attestations_vec = Vector::new(StorageKey::AttestationsInner {
-            trigger_id: trigger_id.clone(),
-        });
-        self.attestations.insert(&trigger_id, &attestations_vec);
-
-        env::log_str(&format!(
-            "Trigger created: {} by {} with {} yoctoNEAR",
-            trigger_id,
-            owner,
-            deposit.as_yoctonear()
-        ));
-
-        trigger_id
+        self.internal_create_trigger(owner, Funding::near(deposit.as_yoctonear()), condition, None, payout, None)
     }
 
     /// Submit an attestation from the TEE agent
     pub fn submit_attestation(&mut self, attestation: Attestation) -> Option<Promise> {
         // Get the trigger
-        let mut trigger = self
+        let trigger = self
             .triggers
             .get(&attestation.trigger_id)
             .expect("Trigger not found");
@@ -279,7 +450,12 @@ impl TriggerPay {
         // For MVP, we'll trust the attestation and add signature verification later
         // self.verify_attestation_signature(&attestation);
 
-        // Store the attestation
+        // Composite triggers name the leaf this attestation reports on
+        let (leaf, condition_hash, leaf_index) = Self::resolve_leaf(&trigger, attestation.leaf_index);
+        self.verify_leaf_attestation(&trigger, &leaf, condition_hash.as_deref(), &attestation);
+
+        // Store the attestation, charging its storage to the trigger owner
+        let initial_storage = env::storage_usage();
         let mut trigger_attestations = self
             .attestations
             .get(&attestation.trigger_id)
@@ -287,32 +463,31 @@ impl TriggerPay {
         trigger_attestations.push(&attestation);
         self.attestations
             .insert(&attestation.trigger_id, &trigger_attestations);
+        self.charge_trigger_storage(&trigger, initial_storage, false);
+        self.record_event(
+            &trigger,
+            TriggerEvent::Attested {
+                condition_met: attestation.condition_met,
+                leaf_index: attestation.leaf_index,
+            },
+        );
 
         env::log_str(&format!(
             "Attestation submitted for {}: status={}, condition_met={}",
             attestation.trigger_id, attestation.flight_status, attestation.condition_met
         ));
 
-        // If condition is met, trigger the payout
-        if attestation.condition_met {
-            env::log_str(&format!(
-                "Condition met for {}! Initiating payout...",
-                attestation.trigger_id
-            ));
-
-            // Update trigger status
-            trigger.status = Status::Executed;
-            self.triggers.insert(&attestation.trigger_id, &trigger);
-
-            // Initiate cross-chain payout via Chain Signatures
-            return Some(self.initiate_payout(&trigger));
+        // Two-outcome triggers pay the late branch once the deadline has passed
+        if let Some(promise) = self.maybe_pay_late_branch(&trigger, &attestation) {
+            return Some(promise);
         }
 
-        None
+        self.apply_condition_result(trigger, leaf_index, attestation.condition_met)
     }
 
     /// Claim refund for an expired or unmet trigger
-    pub fn claim_refund(&mut self, trigger_id: TriggerId) -> Promise {
+    /// Covered triggers have no escrow to refund; their coverage returns to the pool
+    pub fn claim_refund(&mut self, trigger_id: TriggerId) -> Option<Promise> {
         let mut trigger = self.triggers.get(&trigger_id).expect("Trigger not found");
 
         // Verify caller is the owner
@@ -332,21 +507,41 @@ impl TriggerPay {
             env::block_timestamp() > trigger.expires_at,
             "Trigger has not expired yet"
         );
+        assert!(
+            !Self::instalment_in_flight(&trigger),
+            "An instalment is still being signed"
+        );
+
+        // The premium was earned by the pool
+        if trigger.coverage.is_some() {
+            self.transition(&mut trigger, Status::Expired);
+            self.release_coverage(&trigger, false);
+            self.release_exposure(&trigger);
+            self.unindex_expiry(&trigger);
+            return None;
+        }
 
         // Update status
-        trigger.status = Status::Refunded;
-        self.triggers.insert(&trigger_id, &trigger);
+        self.transition(&mut trigger, Status::Refunded);
+        self.release_exposure(&trigger);
+        self.unindex_expiry(&trigger);
 
-        // Refund the deposit (minus a small fee for storage)
-        let refund_amount = trigger.funded_amount.saturating_sub(EXECUTION_FEE / 10); // Keep 0.2 NEAR for storage
+        // Refund the deposit (minus the token's refund fee, if any)
+        // Escrow already released to scheduled instalments is not refundable
+        let refundable = Self::refundable_escrow(&trigger);
+        let fee = self.refund_fee(&trigger).min(refundable);
+        let refund_amount = refundable - fee;
 
         env::log_str(&format!(
-            "Refund issued for {}: {} yoctoNEAR to {}",
-            trigger_id, refund_amount, trigger.owner
+            "Refund issued for {}: {} {} to {}",
+            trigger_id,
+            refund_amount,
+            trigger.escrow_token.as_ref().map_or("yoctoNEAR", |token| token.as_str()),
+            trigger.owner
         ));
 
         // Return the promise so NEAR executes the transfer
-        Promise::new(trigger.owner.clone()).transfer(NearToken::from_yoctonear(refund_amount))
+        Some(self.refund_escrow(&trigger, refund_amount, fee))
     }
 
     // ========================================================================
@@ -399,7 +594,12 @@ impl TriggerPay {
         let executed = self
             .triggers
             .iter()
-            .filter(|(_, t)| t.status == Status::Executed)
+            .filter(|(_, t)| {
+                matches!(
+                    t.status,
+                    Status::PendingPayout | Status::PayoutSigned | Status::PayoutFailed | Status::Settled
+                )
+            })
             .count() as u64;
         (total, active, executed)
     }
@@ -415,6 +615,347 @@ impl TriggerPay {
         );
     }
 
+    fn internal_create_trigger(
+        &mut self,
+        owner: AccountId,
+        funding: Funding,
+        mut condition: Condition,
+        mut expression: Option<ConditionExpr>,
+        payout: Payout,
+        fallback_payout: Option<Payout>,
+    ) -> TriggerId {
+        // Validate deposit; covered triggers pay a premium checked by the pool instead
+        match (&funding.coverage, &funding.token) {
+            (Some(_), _) => {}
+            (None, None) => assert!(funding.amount >= MINIMUM_DEPOSIT, "Minimum deposit is 1 NEAR"),
+            (None, Some(token_id)) => self.assert_token_deposit(token_id, funding.amount),
+        }
+
+        // Canonicalize flight numbers so agents match them exactly
+        Self::normalize_condition(&mut condition);
+        if let Some(expression) = expression.as_mut() {
+            expression.leaves_mut().into_iter().for_each(Self::normalize_condition);
+        }
+
+        // Validate condition
+        match &expression {
+            Some(expression) => self.validate_expression(expression),
+            None => self.validate_condition(&condition),
+        }
+
+        // Validate payouts
+        Self::validate_payout(&payout);
+        if let Some(fallback) = &fallback_payout {
+            Self::validate_payout(fallback);
+        }
+        self.reserve_coverage(&funding, &condition, &payout, fallback_payout.as_ref());
+
+        // Generate unique ID
+        self.trigger_counter += 1;
+        let trigger_id = format!("trig_{:08x}", self.trigger_counter);
+
+        // Composite triggers stay open until their longest-lived leaf expires
+        let now = env::block_timestamp();
+        let expires_at = match &expression {
+            Some(expression) => expression
+                .leaves()
+                .iter()
+                .map(|leaf| self.condition_expiry(leaf, now))
+                .max()
+                .unwrap_or(now),
+            None => self.condition_expiry(&condition, now),
+        };
+
+        let condition_hash = condition.http.as_ref().map(http_spec_hash);
+        let leaves = expression
+            .as_ref()
+            .map(Self::initial_leaf_states)
+            .unwrap_or_default();
+
+        let mut trigger = Trigger {
+            id: trigger_id.clone(),
+            owner: owner.clone(),
+            traveller: None,
+            beneficiary: None,
+            condition,
+            payout,
+            funded_amount: funding.amount,
+            escrow_token: funding.token.clone(),
+            coverage: funding.coverage,
+            contributions: Vec::new(),
+            status: Status::Pending,
+            created_at: now,
+            expires_at,
+            executed_tx: None,
+            condition_hash,
+            expression,
+            leaves,
+            instalments: Vec::new(),
+            fallback_payout,
+            fired_branch: None,
+            template_id: None,
+            yield_checkpoint: 0,
+        };
+
+        // Funded on creation, so the trigger is never stored Pending
+        self.activate_new_trigger(&mut trigger);
+
+        // Reject the trigger if it would push any bucket over its exposure cap
+        self.reserve_exposure(&trigger);
+
+        // Store trigger
+        let initial_storage = env::storage_usage();
+        self.triggers.insert(&trigger_id, &trigger);
+
+        // Add to user's triggers
+        self.add_user_trigger(&owner, &trigger_id);
+
+        // Initialize attestations vector for this trigger
+        let attestations_vec = Vector::new(StorageKey::AttestationsInner {
+            trigger_id: trigger_id.clone(),
+        });
+        self.attestations.insert(&trigger_id, &attestations_vec);
+        self.charge_trigger_storage(&trigger, initial_storage, true);
+        self.index_expiry(&trigger);
+        self.record_event(
+            &trigger,
+            TriggerEvent::Created {
+                amount: funding.amount.to_string(),
+            },
+        );
+        self.record_status_change(&trigger, Status::Pending, Status::Active);
+        Self::emit_nft_mint(&trigger);
+
+        env::log_str(&format!(
+            "Trigger created: {} by {} with {} {}",
+            trigger_id,
+            owner,
+            funding.amount,
+            funding.token.as_ref().map_or("yoctoNEAR", |token| token.as_str())
+        ));
+
+        trigger_id
+    }
+
+    /// Find the condition an attestation or check applies to: the trigger's own
+    /// condition, or the indexed leaf of a composite trigger
+    fn resolve_leaf(
+        trigger: &Trigger,
+        leaf_index: Option<u32>,
+    ) -> (Condition, Option<String>, Option<usize>) {
+        match &trigger.expression {
+            None => (trigger.condition.clone(), trigger.condition_hash.clone(), None),
+            Some(expression) => {
+                let index =
+                    leaf_index.expect("Leaf index is required for composite triggers") as usize;
+                let leaves = expression.leaves();
+                let leaf = leaves.get(index).expect("Leaf index out of range");
+                (
+                    (*leaf).clone(),
+                    trigger.leaves[index].condition_hash.clone(),
+                    Some(index),
+                )
+            }
+        }
+    }
+
+    /// Record a condition result and initiate the payout once the trigger's condition holds.
+    /// Composite triggers record the leaf result and re-evaluate the tree.
+    fn apply_condition_result(
+        &mut self,
+        mut trigger: Trigger,
+        leaf_index: Option<usize>,
+        met: bool,
+    ) -> Option<Promise> {
+        let condition_met = match (&trigger.expression, leaf_index) {
+            (Some(expression), Some(index)) => {
+                trigger.leaves[index].result = Some(met);
+                trigger.leaves[index].attested_at = Some(env::block_timestamp());
+                let results: Vec<Option<bool>> = trigger.leaves.iter().map(|l| l.result).collect();
+                let tree_met = expression.evaluate(&results) == Some(true);
+                self.triggers.insert(&trigger.id, &trigger);
+                tree_met
+            }
+            _ => met,
+        };
+
+        // If condition is met, trigger the payout
+        if condition_met {
+            env::log_str(&format!(
+                "Condition met for {}! Initiating payout...",
+                trigger.id
+            ));
+
+            // Initiate cross-chain payout via Chain Signatures
+            return Some(self.begin_payout(trigger, PayoutBranch::Primary));
+        }
+
+        None
+    }
+
+    /// Check the attestation's observation against a single leaf condition
+    fn verify_leaf_attestation(
+        &self,
+        trigger: &Trigger,
+        condition: &Condition,
+        condition_hash: Option<&str>,
+        attestation: &Attestation,
+    ) {
+        match condition.condition_type {
+            // Price conditions are evaluated on-chain from the observed price
+            ConditionType::PriceThreshold => {
+                let price = condition.price.as_ref().expect("Price condition missing");
+                let observation = attestation
+                    .price_observation
+                    .as_ref()
+                    .expect("Price observation is required");
+                assert!(
+                    self.evaluate_price(price, observation) == attestation.condition_met,
+                    "Attestation does not match the observed price"
+                );
+            }
+            // HTTP conditions must be evaluated against exactly the stored spec
+            ConditionType::HttpJsonPath => {
+                let http = condition.http.as_ref().expect("HTTP condition missing");
+                let spec_hash = condition_hash.expect("Condition hash missing");
+                let observation = attestation
+                    .http_observation
+                    .as_ref()
+                    .expect("HTTP observation is required");
+                assert!(
+                    self.evaluate_http(http, spec_hash, observation) == attestation.condition_met,
+                    "Attestation does not match the extracted value"
+                );
+            }
+            // On-chain conditions never take oracle input
+            ConditionType::OnChainView => {
+                panic!("On-chain conditions are checked via check_onchain_condition")
+            }
+            ConditionType::Schedule => panic!("Scheduled triggers execute via execute_schedule"),
+            // Results arriving after the trigger's settlement window leave it refundable
+            ConditionType::SportsOutcome => {
+                let sports = condition.sports.as_ref().expect("Sports condition missing");
+                let observation = attestation
+                    .sports_observation
+                    .as_ref()
+                    .expect("Sports observation is required");
+                assert!(
+                    env::block_timestamp() <= trigger.expires_at,
+                    "Match result arrived after the settlement window"
+                );
+                assert!(
+                    self.evaluate_sports(sports, observation) == attestation.condition_met,
+                    "Attestation does not match the match result"
+                );
+            }
+            ConditionType::ShipmentStatus => {
+                let shipment = condition.shipment.as_ref().expect("Shipment condition missing");
+                let observation = attestation
+                    .shipment_observation
+                    .as_ref()
+                    .expect("Shipment observation is required");
+                assert!(
+                    self.evaluate_shipment(shipment, observation) == attestation.condition_met,
+                    "Attestation does not match the shipment status"
+                );
+            }
+            ConditionType::FlightCancellation => {}
+        }
+    }
+
+    fn normalize_condition(condition: &mut Condition) {
+        if condition.condition_type == ConditionType::FlightCancellation {
+            if let Some(canonical) = normalize_flight_number(&condition.flight_number) {
+                condition.flight_number = canonical;
+            }
+            if let Some(canonical) = normalize_route(&condition.route) {
+                condition.route = canonical;
+            }
+        }
+        // Asset symbols are registered in uppercase
+        if let Some(price) = condition.price.as_mut() {
+            price.asset = price.asset.to_uppercase();
+        }
+    }
+
+    fn validate_payout(payout: &Payout) {
+        assert!(!payout.amount.is_empty(), "Payout amount is required");
+        assert!(payout.amount.parse::<Balance>().is_ok(), "Invalid payout amount");
+        Self::validate_payout_address(&payout.address);
+    }
+
+    fn validate_payout_address(address: &str) {
+        assert!(!address.is_empty(), "Payout address is required");
+        assert!(
+            address.starts_with("0x") && address.len() == 42,
+            "Invalid Ethereum address format"
+        );
+    }
+
+    fn validate_condition(&self, condition: &Condition) {
+        self.validate_custom_expiry(condition);
+        match condition.condition_type {
+            ConditionType::FlightCancellation => {
+                assert!(
+                    !condition.flight_number.is_empty(),
+                    "Flight number is required"
+                );
+                assert!(
+                    !condition.flight_date.is_empty(),
+                    "Flight date is required"
+                );
+                self.validate_flight(&condition.flight_number, &condition.flight_date);
+                assert!(
+                    condition.route.is_empty() || normalize_route(&condition.route).is_some(),
+                    "Invalid route"
+                );
+                Self::validate_utc_offset(condition.utc_offset_minutes);
+            }
+            ConditionType::PriceThreshold => {
+                let price = condition
+                    .price
+                    .as_ref()
+                    .expect("Price condition is required");
+                self.validate_price_condition(price);
+            }
+            ConditionType::HttpJsonPath => {
+                let http = condition
+                    .http
+                    .as_ref()
+                    .expect("HTTP condition is required");
+                self.validate_http_condition(http);
+            }
+            ConditionType::OnChainView => {
+                let onchain = condition
+                    .onchain
+                    .as_ref()
+                    .expect("On-chain condition is required");
+                self.validate_onchain_condition(onchain);
+            }
+            ConditionType::Schedule => {
+                let schedule = condition
+                    .schedule
+                    .as_ref()
+                    .expect("Schedule condition is required");
+                self.validate_schedule_condition(schedule);
+            }
+            ConditionType::SportsOutcome => {
+                let sports = condition
+                    .sports
+                    .as_ref()
+                    .expect("Sports condition is required");
+                self.validate_sports_condition(sports);
+            }
+            ConditionType::ShipmentStatus => {
+                let shipment = condition
+                    .shipment
+                    .as_ref()
+                    .expect("Shipment condition is required");
+                self.validate_shipment_condition(shipment);
+            }
+        }
+    }
+
     fn trigger_to_view(&self, trigger: &Trigger) -> TriggerView {
         let attestation_count = self
             .attestations
@@ -425,32 +966,45 @@ impl TriggerPay {
         TriggerView {
             id: trigger.id.clone(),
             owner: trigger.owner.to_string(),
+            traveller: trigger.traveller.as_ref().map(|account| account.to_string()),
+            beneficiary: trigger.beneficiary.as_ref().map(|account| account.to_string()),
             condition: trigger.condition.clone(),
             payout: trigger.payout.clone(),
-            funded_amount: trigger.funded_amount.to_string(),
+            funded_amount: (trigger.funded_amount + self.pending_yield(trigger)).to_string(),
+            escrow_token: trigger.escrow_token.as_ref().map(|token| token.to_string()),
+            coverage: trigger.coverage.map(|c| c.to_string()),
+            contributions: trigger.contributions.clone(),
             status: trigger.status.clone(),
             created_at: trigger.created_at,
             expires_at: trigger.expires_at,
             executed_tx: trigger.executed_tx.clone(),
+            condition_hash: trigger.condition_hash.clone(),
+            expression: trigger.expression.clone(),
+            leaves: trigger.leaves.clone(),
+            instalments: trigger.instalments.clone(),
+            fallback_payout: trigger.fallback_payout.clone(),
+            fired_branch: trigger.fired_branch.clone(),
+            template_id: trigger.template_id.clone(),
             attestation_count,
         }
     }
 
     /// Initiate cross-chain payout using Chain Signatures
-    fn initiate_payout(&self, trigger: &Trigger) -> Promise {
+    /// Scheduled triggers pass the instalment index so each instalment signs a distinct payload
+    fn initiate_payout(&self, trigger: &Trigger, payout: &Payout, instalment: Option<u32>) -> Promise {
         // Build the payload for Chain Signatures
         // This will request the MPC network to sign an Ethereum transaction
 
-        let payload = self.build_eth_transaction_payload(trigger);
+        let payload = self.build_eth_transaction_payload(trigger, payout, instalment);
 
         env::log_str(&format!(
             "Requesting Chain Signature for payout: {} {} to {}",
-            trigger.payout.amount, trigger.payout.token, trigger.payout.address
+            payout.amount, payout.token, payout.address
         ));
 
         // Call the MPC signer contract
         // The path determines which derived key to use
-        let path = match trigger.payout.chain {
+        let path = match payout.chain {
             Chain::Ethereum => "ethereum-1",
             Chain::Base => "base-1",
             Chain::Arbitrum => "arbitrum-1",
@@ -473,15 +1027,23 @@ impl TriggerPay {
     }
 
     /// Build the Ethereum transaction payload to be signed
-    fn build_eth_transaction_payload(&self, trigger: &Trigger) -> Vec<u8> {
+    fn build_eth_transaction_payload(
+        &self,
+        trigger: &Trigger,
+        payout: &Payout,
+        instalment: Option<u32>,
+    ) -> Vec<u8> {
         // For MVP, we'll build a simple ETH transfer transaction
         // In production, this would use proper RLP encoding
 
         // Create a hash of the transaction data that will be signed
         let mut hasher = Sha256::new();
-        hasher.update(trigger.payout.address.as_bytes());
-        hasher.update(trigger.payout.amount.as_bytes());
+        hasher.update(payout.address.as_bytes());
+        hasher.update(payout.amount.as_bytes());
         hasher.update(trigger.id.as_bytes());
+        if let Some(index) = instalment {
+            hasher.update(index.to_le_bytes());
+        }
 
         hasher.finalize().to_vec()
     }
@@ -494,23 +1056,64 @@ impl TriggerPay {
 #[cfg(test)]
 mod tests {
     use super::*;
-    use near_sdk::test_utils::VMContextBuilder;
-    use near_sdk::testing_env;
+    use near_sdk::json_types::U128;
+    use near_sdk::test_utils::{accounts, VMContextBuilder};
+    use near_sdk::{testing_env, PromiseError, PromiseOrValue};
+
+    const NOW: u64 = 1_770_681_600_000_000_000; // 2026-02-10T00:00:00Z
 
     fn get_context(predecessor: AccountId, deposit: Balance) -> VMContextBuilder {
         let mut builder = VMContextBuilder::new();
         builder
             .predecessor_account_id(predecessor)
             .attached_deposit(NearToken::from_yoctonear(deposit))
-            .block_timestamp(1_000_000_000_000_000_000); // 1 second in nanoseconds
+            .block_timestamp(NOW);
         builder
     }
 
+    fn fund_storage(contract: &mut TriggerPay, account: &AccountId) {
+        testing_env!(get_context(account.clone(), MINIMUM_DEPOSIT).build());
+        contract.storage_deposit(None, None);
+    }
+
+    fn base_condition(condition_type: ConditionType) -> Condition {
+        Condition {
+            condition_type,
+            flight_number: String::new(),
+            flight_date: String::new(),
+            route: String::new(),
+            utc_offset_minutes: 0,
+            price: None,
+            http: None,
+            onchain: None,
+            schedule: None,
+            sports: None,
+            shipment: None,
+            expires_at: None,
+        }
+    }
+
+    fn base_attestation(trigger_id: &str, met: bool) -> Attestation {
+        Attestation {
+            trigger_id: trigger_id.to_string(),
+            timestamp: 0,
+            api_response_hash: "00".repeat(32),
+            flight_status: String::new(),
+            condition_met: met,
+            signature: String::new(),
+            price_observation: None,
+            http_observation: None,
+            leaf_index: None,
+            sports_observation: None,
+            shipment_observation: None,
+        }
+    }
+
     fn sample_condition() -> Condition {
         Condition {
-            condition_type: ConditionType::FlightCancellation,
-            flight_number: "AA1234".to_sweitring(),
+            flight_number: "AA1234".to_string(),
             flight_date: "2026-02-15".to_string(),
+            ..base_condition(ConditionType::FlightCancellation)
         }
     }
 
@@ -534,6 +1137,8 @@ mod tests {
         let mut contract = TriggerPay::new(owner);
 
         // Create trigger with deposit
+        fund_storage(&mut contract, &user);
+
         let context = get_context(user.clone(), 10 * MINIMUM_DEPOSIT);
         testing_env!(context.build());
 
@@ -559,6 +1164,8 @@ mod tests {
         let mut contract = TriggerPay::new(owner);
 
         // Create two triggers
+        fund_storage(&mut contract, &user);
+
         let context = get_context(user.clone(), 10 * MINIMUM_DEPOSIT);
         testing_env!(context.build());
 
@@ -579,6 +1186,8 @@ mod tests {
 
         let mut contract = TriggerPay::new(owner);
 
+        fund_storage(&mut contract, &user);
+
         let context = get_context(user.clone(), 10 * MINIMUM_DEPOSIT);
         testing_env!(context.build());
 
@@ -626,4 +1235,2564 @@ mod tests {
 
         contract.create_trigger(sample_condition(), payout);
     }
+
+    const DAY_NS: u64 = 24 * 60 * 60 * 1_000_000_000;
+
+    fn price_condition(direction: PriceDirection, threshold: &str) -> Condition {
+        let now = NOW;
+        Condition {
+            price: Some(PriceCondition {
+                asset: "BTC".to_string(),
+                direction,
+                threshold: threshold.to_string(),
+                window_start: now,
+                window_end: now + DAY_NS,
+            }),
+            ..base_condition(ConditionType::PriceThreshold)
+        }
+    }
+
+    fn price_attestation(trigger_id: &str, price: &str, observed_at: u64, met: bool) -> Attestation {
+        Attestation {
+            timestamp: observed_at,
+            price_observation: Some(PriceObservation {
+                asset: "BTC".to_string(),
+                price: price.to_string(),
+                source: "coingecko".to_string(),
+                observed_at,
+            }),
+            ..base_attestation(trigger_id, met)
+        }
+    }
+
+    fn setup_price_contract() -> TriggerPay {
+        let owner: AccountId = "owner.near".parse().unwrap();
+        testing_env!(get_context(owner.clone(), 0).build());
+
+        let mut contract = TriggerPay::new(owner);
+        contract.register_asset("btc".to_string(), 2);
+        contract
+    }
+
+    #[test]
+    fn test_price_threshold_payout() {
+        let mut contract = setup_price_contract();
+        let user: AccountId = "alice.near".parse().unwrap();
+        fund_storage(&mut contract, &user);
+        testing_env!(get_context(user, 10 * MINIMUM_DEPOSIT).build());
+
+        let trigger_id = contract.create_trigger(
+            price_condition(PriceDirection::Below, "60000"),
+            sample_payout(),
+        );
+        let trigger = contract.get_trigger(trigger_id.clone()).unwrap();
+        assert_eq!(trigger.expires_at, NOW + DAY_NS);
+
+        let observed_at = NOW + DAY_NS / 2;
+        assert!(contract
+            .submit_attestation(price_attestation(&trigger_id, "61000.25", observed_at, false))
+            .is_none());
+        assert!(contract
+            .submit_attestation(price_attestation(&trigger_id, "59999.99", observed_at, true))
+            .is_some());
+
+        let trigger = contract.get_trigger(trigger_id).unwrap();
+        assert_eq!(trigger.status, Status::PendingPayout);
+        assert_eq!(trigger.attestation_count, 2);
+    }
+
+    #[test]
+    fn test_price_asset_lookup_ignores_case() {
+        let mut contract = setup_price_contract();
+        assert_eq!(contract.get_asset("btc".to_string()).unwrap().symbol, "BTC");
+
+        let user: AccountId = "alice.near".parse().unwrap();
+        fund_storage(&mut contract, &user);
+        testing_env!(get_context(user, 10 * MINIMUM_DEPOSIT).build());
+        let mut condition = price_condition(PriceDirection::Below, "60000");
+        condition.price.as_mut().unwrap().asset = "btc".to_string();
+        let trigger_id = contract.create_trigger(condition, sample_payout());
+
+        let trigger = contract.get_trigger(trigger_id.clone()).unwrap();
+        assert_eq!(trigger.condition.price.unwrap().asset, "BTC");
+        assert!(contract
+            .submit_attestation(price_attestation(&trigger_id, "59999.99", NOW + DAY_NS / 2, true))
+            .is_some());
+    }
+
+    #[test]
+    #[should_panic(expected = "Observation is outside the condition window")]
+    fn test_price_observation_outside_window() {
+        let mut contract = setup_price_contract();
+        let user: AccountId = "alice.near".parse().unwrap();
+        fund_storage(&mut contract, &user);
+        testing_env!(get_context(user, 10 * MINIMUM_DEPOSIT).build());
+
+        let trigger_id = contract.create_trigger(
+            price_condition(PriceDirection::Above, "70000"),
+            sample_payout(),
+        );
+        let observed_at = NOW + 2 * DAY_NS;
+        contract.submit_attestation(price_attestation(&trigger_id, "71000", observed_at, true));
+    }
+
+    #[test]
+    #[should_panic(expected = "Attestation does not match the observed price")]
+    fn test_price_attestation_mismatch() {
+        let mut contract = setup_price_contract();
+        let user: AccountId = "alice.near".parse().unwrap();
+        fund_storage(&mut contract, &user);
+        testing_env!(get_context(user, 10 * MINIMUM_DEPOSIT).build());
+
+        let trigger_id = contract.create_trigger(
+            price_condition(PriceDirection::Above, "70000"),
+            sample_payout(),
+        );
+        let observed_at = NOW + DAY_NS / 2;
+        contract.submit_attestation(price_attestation(&trigger_id, "69000", observed_at, true));
+    }
+
+    #[test]
+    #[should_panic(expected = "Asset not registered")]
+    fn test_price_condition_unregistered_asset() {
+        let mut contract = setup_price_contract();
+        let user: AccountId = "alice.near".parse().unwrap();
+        fund_storage(&mut contract, &user);
+        testing_env!(get_context(user, 10 * MINIMUM_DEPOSIT).build());
+
+        let mut condition = price_condition(PriceDirection::Above, "70000");
+        condition.price.as_mut().unwrap().asset = "DOGE".to_string();
+        contract.create_trigger(condition, sample_payout());
+    }
+
+    fn http_condition() -> Condition {
+        Condition {
+            http: Some(HttpJsonPathCondition {
+                url_template: "https://API.weather.example/rain/{date}".to_string(),
+                json_path: "$.rainfall_mm".to_string(),
+                operator: CompareOp::Gte,
+                expected: "50".to_string(),
+            }),
+            ..base_condition(ConditionType::HttpJsonPath)
+        }
+    }
+
+    fn http_attestation(trigger_id: &str, spec_hash: &str, value: &str, met: bool) -> Attestation {
+        Attestation {
+            http_observation: Some(HttpObservation {
+                spec_hash: spec_hash.to_string(),
+                extracted_value: value.to_string(),
+                response_hash: "11".repeat(32),
+            }),
+            ..base_attestation(trigger_id, met)
+        }
+    }
+
+    #[test]
+    fn test_http_condition_payout() {
+        let owner: AccountId = "owner.near".parse().unwrap();
+        let user: AccountId = "alice.near".parse().unwrap();
+        testing_env!(get_context(owner.clone(), 0).build());
+
+        let mut contract = TriggerPay::new(owner);
+        contract.add_allowed_domain("api.weather.example".to_string());
+
+        fund_storage(&mut contract, &user);
+
+        testing_env!(get_context(user, 10 * MINIMUM_DEPOSIT).build());
+        let trigger_id = contract.create_trigger(http_condition(), sample_payout());
+
+        let trigger = contract.get_trigger(trigger_id.clone()).unwrap();
+        let spec_hash = trigger.condition_hash.unwrap();
+        assert_eq!(spec_hash, http_spec_hash(http_condition().http.as_ref().unwrap()));
+        // sha256 of "https://api.weather.example/rain/{date}\n$.rainfall_mm\n\"Gte\"\n50"
+        assert_eq!(spec_hash, "a3ce55d9c186eed6c93b7ae2db4eb9cda310229c77aab5c06577a50283ea2bde");
+
+        assert!(contract
+            .submit_attestation(http_attestation(&trigger_id, &spec_hash, "49.9", false))
+            .is_none());
+        assert!(contract
+            .submit_attestation(http_attestation(&trigger_id, &spec_hash, "50.0", true))
+            .is_some());
+        assert_eq!(contract.get_trigger(trigger_id).unwrap().status, Status::PendingPayout);
+    }
+
+    #[test]
+    #[should_panic(expected = "Observation is for a different condition spec")]
+    fn test_http_condition_wrong_spec() {
+        let owner: AccountId = "owner.near".parse().unwrap();
+        let user: AccountId = "alice.near".parse().unwrap();
+        testing_env!(get_context(owner.clone(), 0).build());
+
+        let mut contract = TriggerPay::new(owner);
+        contract.add_allowed_domain("api.weather.example".to_string());
+
+        fund_storage(&mut contract, &user);
+
+        testing_env!(get_context(user, 10 * MINIMUM_DEPOSIT).build());
+        let trigger_id = contract.create_trigger(http_condition(), sample_payout());
+        contract.submit_attestation(http_attestation(&trigger_id, &"ab".repeat(32), "80", true));
+    }
+
+    #[test]
+    #[should_panic(expected = "Domain is not on the allowlist")]
+    fn test_http_condition_domain_not_allowed() {
+        let owner: AccountId = "owner.near".parse().unwrap();
+        let user: AccountId = "alice.near".parse().unwrap();
+        testing_env!(get_context(owner.clone(), 0).build());
+
+        let mut contract = TriggerPay::new(owner);
+
+        fund_storage(&mut contract, &user);
+
+        testing_env!(get_context(user, 10 * MINIMUM_DEPOSIT).build());
+        contract.create_trigger(http_condition(), sample_payout());
+    }
+
+    fn flight_attestation(trigger_id: &str, leaf_index: u32, met: bool) -> Attestation {
+        Attestation {
+            flight_status: if met { "cancelled" } else { "scheduled" }.to_string(),
+            leaf_index: Some(leaf_index),
+            ..base_attestation(trigger_id, met)
+        }
+    }
+
+    #[test]
+    fn test_composite_threshold_trigger() {
+        let owner: AccountId = "owner.near".parse().unwrap();
+        let user: AccountId = "alice.near".parse().unwrap();
+        testing_env!(get_context(owner.clone(), 0).build());
+        let mut contract = TriggerPay::new(owner);
+
+        fund_storage(&mut contract, &user);
+
+        testing_env!(get_context(user, 10 * MINIMUM_DEPOSIT).build());
+        let leaf = || ConditionExpr::Leaf(Box::new(sample_condition()));
+        let expression = ConditionExpr::Threshold {
+            k: 2,
+            of: vec![leaf(), leaf(), ConditionExpr::Not(Box::new(leaf()))],
+        };
+        let trigger_id = contract.create_composite_trigger(expression, sample_payout());
+
+        // One leaf met, the negated leaf still met: 2 of 3
+        assert!(contract
+            .submit_attestation(flight_attestation(&trigger_id, 0, true))
+            .is_none());
+        let trigger = contract.get_trigger(trigger_id.clone()).unwrap();
+        assert_eq!(trigger.leaves.len(), 3);
+        assert_eq!(trigger.leaves[0].result, Some(true));
+        assert_eq!(trigger.status, Status::Active);
+
+        assert!(contract
+            .submit_attestation(flight_attestation(&trigger_id, 2, false))
+            .is_some());
+        assert_eq!(contract.get_trigger(trigger_id).unwrap().status, Status::PendingPayout);
+    }
+
+    #[test]
+    fn test_composite_and_waits_for_all_leaves() {
+        let owner: AccountId = "owner.near".parse().unwrap();
+        let user: AccountId = "alice.near".parse().unwrap();
+        testing_env!(get_context(owner.clone(), 0).build());
+        let mut contract = TriggerPay::new(owner);
+
+        fund_storage(&mut contract, &user);
+
+        testing_env!(get_context(user, 10 * MINIMUM_DEPOSIT).build());
+        let expression = ConditionExpr::And(vec![
+            ConditionExpr::Leaf(Box::new(sample_condition())),
+            ConditionExpr::Or(vec![
+                ConditionExpr::Leaf(Box::new(sample_condition())),
+                ConditionExpr::Leaf(Box::new(sample_condition())),
+            ]),
+        ]);
+        let trigger_id = contract.create_composite_trigger(expression, sample_payout());
+
+        assert!(contract
+            .submit_attestation(flight_attestation(&trigger_id, 1, false))
+            .is_none());
+        assert!(contract
+            .submit_attestation(flight_attestation(&trigger_id, 0, true))
+            .is_none());
+        assert!(contract
+            .submit_attestation(flight_attestation(&trigger_id, 2, true))
+            .is_some());
+    }
+
+    #[test]
+    #[should_panic(expected = "Condition tree is too deep")]
+    fn test_composite_depth_limit() {
+        let owner: AccountId = "owner.near".parse().unwrap();
+        let user: AccountId = "alice.near".parse().unwrap();
+        testing_env!(get_context(owner.clone(), 0).build());
+        let mut contract = TriggerPay::new(owner);
+
+        fund_storage(&mut contract, &user);
+
+        testing_env!(get_context(user, 10 * MINIMUM_DEPOSIT).build());
+        let mut expression = ConditionExpr::Leaf(Box::new(sample_condition()));
+        for _ in 0..MAX_CONDITION_DEPTH {
+            expression = ConditionExpr::Not(Box::new(expression));
+        }
+        contract.create_composite_trigger(expression, sample_payout());
+    }
+
+    fn onchain_condition() -> Condition {
+        Condition {
+            onchain: Some(OnChainCondition {
+                contract_id: "usdc.near".to_string(),
+                method: "ft_balance_of".to_string(),
+                args: r#"{"account_id":"treasury.near"}"#.to_string(),
+                result_path: String::new(),
+                operator: CompareOp::Lt,
+                expected: "1000000".to_string(),
+            }),
+            ..base_condition(ConditionType::OnChainView)
+        }
+    }
+
+    #[test]
+    fn test_onchain_condition_callback() {
+        let owner: AccountId = "owner.near".parse().unwrap();
+        let user: AccountId = "alice.near".parse().unwrap();
+        testing_env!(get_context(owner.clone(), 0).build());
+        let mut contract = TriggerPay::new(owner);
+        contract.add_allowed_view("usdc.near".parse().unwrap(), "ft_balance_of".to_string());
+
+        fund_storage(&mut contract, &user);
+
+        testing_env!(get_context(user, 10 * MINIMUM_DEPOSIT).build());
+        let trigger_id = contract.create_trigger(onchain_condition(), sample_payout());
+        let _ = contract.check_onchain_condition(trigger_id.clone(), None);
+
+        // Callbacks run in their own receipt
+        let contract_account: AccountId = "alice.near".parse().unwrap();
+        testing_env!(get_context(contract_account, 0).build());
+
+        // Balance still above the floor
+        assert!(contract
+            .on_onchain_view(trigger_id.clone(), None, Ok(serde_json::json!("2500000")))
+            .is_none());
+        // Failed view calls leave the trigger untouched
+        assert!(contract
+            .on_onchain_view(trigger_id.clone(), None, Err(PromiseError::Failed))
+            .is_none());
+        assert_eq!(contract.get_trigger(trigger_id.clone()).unwrap().status, Status::Active);
+
+        assert!(contract
+            .on_onchain_view(trigger_id.clone(), None, Ok(serde_json::json!("999999")))
+            .is_some());
+        assert_eq!(contract.get_trigger(trigger_id).unwrap().status, Status::PendingPayout);
+    }
+
+    #[test]
+    #[should_panic(expected = "On-chain conditions are checked via check_onchain_condition")]
+    fn test_onchain_condition_rejects_attestations() {
+        let owner: AccountId = "owner.near".parse().unwrap();
+        let user: AccountId = "alice.near".parse().unwrap();
+        testing_env!(get_context(owner.clone(), 0).build());
+        let mut contract = TriggerPay::new(owner);
+        contract.add_allowed_view("usdc.near".parse().unwrap(), "ft_balance_of".to_string());
+
+        fund_storage(&mut contract, &user);
+
+        testing_env!(get_context(user, 10 * MINIMUM_DEPOSIT).build());
+        let trigger_id = contract.create_trigger(onchain_condition(), sample_payout());
+        let mut attestation = flight_attestation(&trigger_id, 0, true);
+        attestation.leaf_index = None;
+        contract.submit_attestation(attestation);
+    }
+
+    #[test]
+    #[should_panic(expected = "View is not on the allowlist")]
+    fn test_onchain_condition_view_not_allowed() {
+        let owner: AccountId = "owner.near".parse().unwrap();
+        let user: AccountId = "alice.near".parse().unwrap();
+        testing_env!(get_context(owner.clone(), 0).build());
+        let mut contract = TriggerPay::new(owner);
+        contract.add_allowed_view("usdc.near".parse().unwrap(), "ft_metadata".to_string());
+
+        fund_storage(&mut contract, &user);
+
+        testing_env!(get_context(user, 10 * MINIMUM_DEPOSIT).build());
+        contract.create_trigger(onchain_condition(), sample_payout());
+    }
+
+    #[test]
+    #[should_panic(expected = "View calls to staking pools are not allowed")]
+    fn test_onchain_view_rejects_staking_pool() {
+        let owner: AccountId = "owner.near".parse().unwrap();
+        testing_env!(get_context(owner.clone(), 0).build());
+        let mut contract = TriggerPay::new(owner);
+        let pool: AccountId = "pool.poolv1.near".parse().unwrap();
+        contract.add_staking_pool(pool.clone());
+        contract.add_allowed_view(pool, "get_account".to_string());
+    }
+
+    fn monthly_schedule(start_at: u64, instalments: u32) -> Condition {
+        Condition {
+            schedule: Some(ScheduleCondition {
+                start_at,
+                interval: Some(30 * DAY_NS),
+                instalments,
+            }),
+            ..base_condition(ConditionType::Schedule)
+        }
+    }
+
+    #[test]
+    fn test_recurring_schedule_instalments() {
+        let owner: AccountId = "owner.near".parse().unwrap();
+        let user: AccountId = "alice.near".parse().unwrap();
+        let now = NOW;
+        testing_env!(get_context(owner.clone(), 0).build());
+        let mut contract = TriggerPay::new(owner);
+
+        fund_storage(&mut contract, &user);
+
+        testing_env!(get_context(user, 9 * MINIMUM_DEPOSIT).build());
+        let trigger_id = contract.create_trigger(monthly_schedule(now + DAY_NS, 3), sample_payout());
+
+        // Each execution runs in its own block; the second instalment fails once and is retried
+        let attempts = [(0, true), (1, false), (1, true), (2, true)];
+        for (index, signed) in attempts {
+            let mut context = get_context("keeper.near".parse().unwrap(), 0);
+            context.block_timestamp(now + DAY_NS + index as u64 * 30 * DAY_NS);
+            testing_env!(context.build());
+            let _ = contract.execute_schedule(trigger_id.clone());
+
+            let result = if signed {
+                Ok(serde_json::json!({}))
+            } else {
+                Err(PromiseError::Failed)
+            };
+            contract.on_instalment_signed(trigger_id.clone(), index, result);
+        }
+
+        let trigger = contract.get_trigger(trigger_id).unwrap();
+        assert_eq!(trigger.status, Status::PayoutSigned);
+        assert_eq!(trigger.instalments.len(), 3);
+        for instalment in &trigger.instalments {
+            assert_eq!(instalment.status, InstalmentStatus::Signed);
+            assert_eq!(instalment.escrow_amount, (3 * MINIMUM_DEPOSIT).to_string());
+        }
+    }
+
+    #[test]
+    #[should_panic(expected = "Next instalment is not due yet")]
+    fn test_schedule_not_due() {
+        let owner: AccountId = "owner.near".parse().unwrap();
+        let user: AccountId = "alice.near".parse().unwrap();
+        let now = NOW;
+        testing_env!(get_context(owner.clone(), 0).build());
+        let mut contract = TriggerPay::new(owner);
+
+        fund_storage(&mut contract, &user);
+
+        testing_env!(get_context(user, 9 * MINIMUM_DEPOSIT).build());
+        let trigger_id = contract.create_trigger(monthly_schedule(now + DAY_NS, 3), sample_payout());
+
+        let mut context = get_context("keeper.near".parse().unwrap(), 0);
+        context.block_timestamp(now + DAY_NS);
+        testing_env!(context.build());
+        let _ = contract.execute_schedule(trigger_id.clone());
+        contract.on_instalment_signed(trigger_id.clone(), 0, Ok(serde_json::json!({})));
+        let _ = contract.execute_schedule(trigger_id);
+    }
+
+    #[test]
+    fn test_stuck_instalment_can_be_retried() {
+        let owner: AccountId = "owner.near".parse().unwrap();
+        let user: AccountId = "alice.near".parse().unwrap();
+        testing_env!(get_context(owner.clone(), 0).build());
+        let mut contract = TriggerPay::new(owner);
+
+        fund_storage(&mut contract, &user);
+        testing_env!(get_context(user, 9 * MINIMUM_DEPOSIT).build());
+        let trigger_id = contract.create_trigger(monthly_schedule(NOW + DAY_NS, 3), sample_payout());
+
+        let mut context = get_context("keeper.near".parse().unwrap(), 0);
+        context.block_timestamp(NOW + DAY_NS);
+        testing_env!(context.build());
+        let _ = contract.execute_schedule(trigger_id.clone());
+
+        // No callback ever arrived; an hour later the instalment is requested again
+        let mut context = get_context("keeper.near".parse().unwrap(), 0);
+        context.block_timestamp(NOW + DAY_NS + DAY_NS / 24);
+        testing_env!(context.build());
+        let _ = contract.execute_schedule(trigger_id.clone());
+        let instalments = contract.get_trigger(trigger_id).unwrap().instalments;
+        assert_eq!(instalments.len(), 1);
+        assert_eq!(instalments[0].status, InstalmentStatus::Requested);
+        assert_eq!(instalments[0].requested_at, NOW + DAY_NS + DAY_NS / 24);
+    }
+
+    #[test]
+    #[should_panic(expected = "An instalment is still being signed")]
+    fn test_refund_refused_while_instalment_in_flight() {
+        let owner: AccountId = "owner.near".parse().unwrap();
+        let user: AccountId = "alice.near".parse().unwrap();
+        testing_env!(get_context(owner.clone(), 0).build());
+        let mut contract = TriggerPay::new(owner);
+
+        fund_storage(&mut contract, &user);
+        testing_env!(get_context(user.clone(), 9 * MINIMUM_DEPOSIT).build());
+        let trigger_id = contract.create_trigger(monthly_schedule(NOW + DAY_NS, 3), sample_payout());
+        let expires_at = contract.get_trigger(trigger_id.clone()).unwrap().expires_at;
+
+        // The first instalment is requested after expiry, then a refund is claimed
+        let mut context = get_context(user, 0);
+        context.block_timestamp(expires_at + 1);
+        testing_env!(context.build());
+        let _ = contract.execute_schedule(trigger_id.clone());
+        assert_eq!(contract.sweep_expired(10), 0);
+        let _ = contract.claim_refund(trigger_id);
+    }
+
+    #[test]
+    #[should_panic(expected = "Schedule ends too far in the future")]
+    fn test_schedule_end_must_fit_in_a_timestamp() {
+        let owner: AccountId = "owner.near".parse().unwrap();
+        let user: AccountId = "alice.near".parse().unwrap();
+        testing_env!(get_context(owner.clone(), 0).build());
+        let mut contract = TriggerPay::new(owner);
+
+        fund_storage(&mut contract, &user);
+
+        testing_env!(get_context(user, 9 * MINIMUM_DEPOSIT).build());
+        contract.create_trigger(monthly_schedule(u64::MAX - 30 * DAY_NS, 3), sample_payout());
+    }
+
+    fn sports_condition(outcome: OutcomeSelector) -> Condition {
+        Condition {
+            sports: Some(SportsCondition {
+                league: "EPL".to_string(),
+                match_id: "m-42".to_string(),
+                outcome,
+                kickoff_at: NOW + DAY_NS,
+            }),
+            ..base_condition(ConditionType::SportsOutcome)
+        }
+    }
+
+    fn sports_attestation(trigger_id: &str, status: MatchStatus, home: u32, away: u32, met: bool) -> Attestation {
+        Attestation {
+            sports_observation: Some(SportsObservation {
+                match_id: "m-42".to_string(),
+                match_status: status,
+                home_score: home,
+                away_score: away,
+                advancing_team: None,
+            }),
+            ..base_attestation(trigger_id, met)
+        }
+    }
+
+    #[test]
+    fn test_sports_outcome_payout() {
+        let owner: AccountId = "owner.near".parse().unwrap();
+        let user: AccountId = "alice.near".parse().unwrap();
+        testing_env!(get_context(owner.clone(), 0).build());
+        let mut contract = TriggerPay::new(owner);
+
+        fund_storage(&mut contract, &user);
+
+        testing_env!(get_context(user, 10 * MINIMUM_DEPOSIT).build());
+        let line = OutcomeSelector::TotalOver { line: "2.5".to_string() };
+        let trigger_id = contract.create_trigger(sports_condition(line), sample_payout());
+
+        // A postponed match can never satisfy the condition
+        assert!(contract
+            .submit_attestation(sports_attestation(&trigger_id, MatchStatus::Postponed, 0, 0, false))
+            .is_none());
+        assert!(contract
+            .submit_attestation(sports_attestation(&trigger_id, MatchStatus::Finished, 2, 1, true))
+            .is_some());
+        assert_eq!(contract.get_trigger(trigger_id).unwrap().status, Status::PendingPayout);
+    }
+
+    #[test]
+    fn test_sports_unfinished_match_refundable() {
+        let owner: AccountId = "owner.near".parse().unwrap();
+        let user: AccountId = "alice.near".parse().unwrap();
+        testing_env!(get_context(owner.clone(), 0).build());
+        let mut contract = TriggerPay::new(owner);
+
+        fund_storage(&mut contract, &user);
+
+        testing_env!(get_context(user.clone(), 10 * MINIMUM_DEPOSIT).build());
+        let trigger_id = contract.create_trigger(sports_condition(OutcomeSelector::HomeWin), sample_payout());
+        contract.submit_attestation(sports_attestation(&trigger_id, MatchStatus::Abandoned, 1, 0, false));
+
+        let mut context = get_context(user, 0);
+        context.block_timestamp(contract.get_trigger(trigger_id.clone()).unwrap().expires_at + 1);
+        testing_env!(context.build());
+        let _ = contract.claim_refund(trigger_id.clone());
+        assert_eq!(contract.get_trigger(trigger_id).unwrap().status, Status::Refunded);
+    }
+
+    #[test]
+    #[should_panic(expected = "Match result arrived after the settlement window")]
+    fn test_sports_result_after_expiry() {
+        let owner: AccountId = "owner.near".parse().unwrap();
+        let user: AccountId = "alice.near".parse().unwrap();
+        testing_env!(get_context(owner.clone(), 0).build());
+        let mut contract = TriggerPay::new(owner);
+
+        fund_storage(&mut contract, &user);
+
+        testing_env!(get_context(user.clone(), 10 * MINIMUM_DEPOSIT).build());
+        let trigger_id = contract.create_trigger(sports_condition(OutcomeSelector::HomeWin), sample_payout());
+
+        let mut context = get_context(user, 0);
+        context.block_timestamp(contract.get_trigger(trigger_id.clone()).unwrap().expires_at + 1);
+        testing_env!(context.build());
+        contract.submit_attestation(sports_attestation(&trigger_id, MatchStatus::Finished, 3, 0, true));
+    }
+
+    fn shipment_condition(deadline: u64) -> Condition {
+        Condition {
+            shipment: Some(ShipmentCondition {
+                carrier: "DHL".to_string(),
+                tracking_hash: tracking_hash("jd0146 0000 1234"),
+                target_status: ShipmentStage::Delivered,
+                deadline,
+            }),
+            ..base_condition(ConditionType::ShipmentStatus)
+        }
+    }
+
+    fn shipment_attestation(trigger_id: &str, status: ShipmentStage, status_at: u64, met: bool) -> Attestation {
+        Attestation {
+            shipment_observation: Some(ShipmentObservation {
+                tracking_hash: tracking_hash("JD0146 0000 1234"),
+                status,
+                status_at,
+            }),
+            ..base_attestation(trigger_id, met)
+        }
+    }
+
+    fn buyer_payout() -> Payout {
+        Payout {
+            address: "0x1111111111111111111111111111111111111111".to_string(),
+            ..sample_payout()
+        }
+    }
+
+    #[test]
+    fn test_shipment_delivered_pays_carrier() {
+        let owner: AccountId = "owner.near".parse().unwrap();
+        let user: AccountId = "alice.near".parse().unwrap();
+        let now = NOW;
+        testing_env!(get_context(owner.clone(), 0).build());
+        let mut contract = TriggerPay::new(owner);
+
+        fund_storage(&mut contract, &user);
+
+        testing_env!(get_context(user, 10 * MINIMUM_DEPOSIT).build());
+        let trigger_id = contract.create_shipment_trigger(
+            shipment_condition(now + 2 * DAY_NS),
+            sample_payout(),
+            buyer_payout(),
+        );
+
+        let attestation = shipment_attestation(&trigger_id, ShipmentStage::Delivered, now + DAY_NS, true);
+        assert!(contract.submit_attestation(attestation).is_some());
+
+        let trigger = contract.get_trigger(trigger_id).unwrap();
+        assert_eq!(trigger.status, Status::PendingPayout);
+        assert_eq!(trigger.fired_branch, Some(PayoutBranch::Primary));
+    }
+
+    #[test]
+    fn test_shipment_late_pays_buyer() {
+        let owner: AccountId = "owner.near".parse().unwrap();
+        let user: AccountId = "alice.near".parse().unwrap();
+        let now = NOW;
+        testing_env!(get_context(owner.clone(), 0).build());
+        let mut contract = TriggerPay::new(owner);
+
+        fund_storage(&mut contract, &user);
+
+        testing_env!(get_context(user, 10 * MINIMUM_DEPOSIT).build());
+        let trigger_id = contract.create_shipment_trigger(
+            shipment_condition(now + 2 * DAY_NS),
+            sample_payout(),
+            buyer_payout(),
+        );
+
+        // Still in transit before the deadline: nothing happens
+        let attestation = shipment_attestation(&trigger_id, ShipmentStage::InTransit, now + DAY_NS, false);
+        assert!(contract.submit_attestation(attestation).is_none());
+
+        // Delivered a day late
+        let mut context = get_context("agent.near".parse().unwrap(), 0);
+        context.block_timestamp(now + 3 * DAY_NS);
+        testing_env!(context.build());
+        let attestation = shipment_attestation(&trigger_id, ShipmentStage::Delivered, now + 3 * DAY_NS, false);
+        assert!(contract.submit_attestation(attestation).is_some());
+
+        let trigger = contract.get_trigger(trigger_id).unwrap();
+        assert_eq!(trigger.status, Status::PendingPayout);
+        assert_eq!(trigger.fired_branch, Some(PayoutBranch::Fallback));
+    }
+
+    #[test]
+    fn test_shipment_fallback_counted_at_creation() {
+        let owner: AccountId = "owner.near".parse().unwrap();
+        let user: AccountId = "alice.near".parse().unwrap();
+        testing_env!(get_context(owner.clone(), 0).build());
+        let mut contract = TriggerPay::new(owner);
+        contract.set_exposure_limits("ETH".to_string(), ExposureLimits {
+            per_chain: Some("3000000000000000000".to_string()),
+            ..ExposureLimits::default()
+        });
+
+        fund_storage(&mut contract, &user);
+        testing_env!(get_context(user, 10 * MINIMUM_DEPOSIT).build());
+        let late = Payout {
+            amount: "2000000000000000000".to_string(), // 2 ETH
+            ..buyer_payout()
+        };
+        let trigger_id = contract.create_shipment_trigger(shipment_condition(NOW + 2 * DAY_NS), sample_payout(), late);
+
+        // Only one branch pays, so the chain holds the larger one
+        let capacity = contract.get_exposure_capacity(shipment_condition(NOW + 2 * DAY_NS), sample_payout());
+        assert_eq!(capacity[0].bucket, "chain:Ethereum:ETH");
+        assert_eq!(capacity[0].exposure, "2000000000000000000");
+
+        let metadata = contract.nft_token(trigger_id).unwrap().metadata.unwrap();
+        assert!(metadata
+            .description
+            .unwrap()
+            .contains("or 2000000000000000000 ETH on Ethereum if the deadline is missed"));
+    }
+
+    #[test]
+    #[should_panic(expected = "Exposure limit reached for chain:Ethereum:ETH")]
+    fn test_shipment_fallback_subject_to_exposure_caps() {
+        let owner: AccountId = "owner.near".parse().unwrap();
+        let user: AccountId = "alice.near".parse().unwrap();
+        testing_env!(get_context(owner.clone(), 0).build());
+        let mut contract = TriggerPay::new(owner);
+        contract.set_exposure_limits("ETH".to_string(), ExposureLimits {
+            per_chain: Some("1000000000000000000".to_string()),
+            ..ExposureLimits::default()
+        });
+
+        fund_storage(&mut contract, &user);
+        testing_env!(get_context(user, 10 * MINIMUM_DEPOSIT).build());
+        let late = Payout {
+            amount: "2000000000000000000".to_string(),
+            ..buyer_payout()
+        };
+        contract.create_shipment_trigger(shipment_condition(NOW + 2 * DAY_NS), sample_payout(), late);
+    }
+
+    #[test]
+    fn test_flight_number_normalized() {
+        let owner: AccountId = "owner.near".parse().unwrap();
+        let user: AccountId = "alice.near".parse().unwrap();
+        testing_env!(get_context(owner.clone(), 0).build());
+        let mut contract = TriggerPay::new(owner);
+
+        fund_storage(&mut contract, &user);
+
+        testing_env!(get_context(user, 10 * MINIMUM_DEPOSIT).build());
+        let condition = Condition {
+            flight_number: "aa 01234".to_string(),
+            ..sample_condition()
+        };
+        let trigger_id = contract.create_trigger(condition, sample_payout());
+        assert_eq!(contract.get_trigger(trigger_id).unwrap().condition.flight_number, "AA1234");
+
+        assert_eq!(normalize_flight_number("baw-12"), Some("BAW12".to_string()));
+        assert_eq!(normalize_flight_number("U2 8012"), Some("U28012".to_string()));
+        assert_eq!(normalize_flight_number("12345"), None);
+        assert_eq!(normalize_flight_number("AA12345"), None);
+        assert_eq!(parse_iso_date("2024-02-29"), Some(19_782));
+        assert_eq!(parse_iso_date("2026-02-29"), None);
+        assert_eq!(parse_iso_date("2026-2-01"), None);
+    }
+
+    #[test]
+    #[should_panic(expected = "Flight date is in the past")]
+    fn test_flight_date_in_past() {
+        let owner: AccountId = "owner.near".parse().unwrap();
+        let user: AccountId = "alice.near".parse().unwrap();
+        testing_env!(get_context(owner.clone(), 0).build());
+        let mut contract = TriggerPay::new(owner);
+
+        fund_storage(&mut contract, &user);
+
+        testing_env!(get_context(user, 10 * MINIMUM_DEPOSIT).build());
+        let condition = Condition {
+            flight_date: "2026-02-09".to_string(),
+            ..sample_condition()
+        };
+        contract.create_trigger(condition, sample_payout());
+    }
+
+    #[test]
+    #[should_panic(expected = "Flight date is beyond the booking horizon")]
+    fn test_flight_date_beyond_horizon() {
+        let owner: AccountId = "owner.near".parse().unwrap();
+        let user: AccountId = "alice.near".parse().unwrap();
+        testing_env!(get_context(owner.clone(), 0).build());
+        let mut contract = TriggerPay::new(owner);
+        contract.set_booking_horizon(30);
+
+        fund_storage(&mut contract, &user);
+
+        testing_env!(get_context(user, 10 * MINIMUM_DEPOSIT).build());
+        let condition = Condition {
+            flight_date: "2026-03-13".to_string(),
+            ..sample_condition()
+        };
+        contract.create_trigger(condition, sample_payout());
+    }
+
+    #[test]
+    fn test_flight_expiry_from_date() {
+        let owner: AccountId = "owner.near".parse().unwrap();
+        let user: AccountId = "alice.near".parse().unwrap();
+        testing_env!(get_context(owner.clone(), 0).build());
+        let mut contract = TriggerPay::new(owner.clone());
+
+        // Departs New York (UTC-5) on 2026-02-15; local day ends 2026-02-16T05:00Z
+        fund_storage(&mut contract, &user);
+        testing_env!(get_context(user.clone(), 10 * MINIMUM_DEPOSIT).build());
+        let condition = Condition {
+            utc_offset_minutes: -300,
+            ..sample_condition()
+        };
+        let trigger_id = contract.create_trigger(condition.clone(), sample_payout());
+        let day_end = NOW + 6 * DAY_NS + 5 * 60 * 60 * 1_000_000_000;
+        assert_eq!(contract.get_trigger(trigger_id).unwrap().expires_at, day_end + 2 * DAY_NS);
+
+        testing_env!(get_context(owner, 0).build());
+        contract.set_settlement_buffer(ConditionType::FlightCancellation, DAY_NS / 2);
+
+        testing_env!(get_context(user.clone(), 10 * MINIMUM_DEPOSIT).build());
+        let trigger_id = contract.create_trigger(condition.clone(), sample_payout());
+        assert_eq!(contract.get_trigger(trigger_id).unwrap().expires_at, day_end + DAY_NS / 2);
+
+        // Users may extend the expiry within the admin bounds
+        let custom = Condition {
+            expires_at: Some(NOW + 30 * DAY_NS),
+            ..condition
+        };
+        let trigger_id = contract.create_trigger(custom, sample_payout());
+        assert_eq!(contract.get_trigger(trigger_id).unwrap().expires_at, NOW + 30 * DAY_NS);
+    }
+
+    #[test]
+    #[should_panic(expected = "Custom expiry is before the condition settles")]
+    fn test_custom_expiry_before_flight() {
+        let owner: AccountId = "owner.near".parse().unwrap();
+        let user: AccountId = "alice.near".parse().unwrap();
+        testing_env!(get_context(owner.clone(), 0).build());
+        let mut contract = TriggerPay::new(owner);
+
+        fund_storage(&mut contract, &user);
+
+        testing_env!(get_context(user, 10 * MINIMUM_DEPOSIT).build());
+        let condition = Condition {
+            expires_at: Some(NOW + 2 * DAY_NS),
+            ..sample_condition()
+        };
+        contract.create_trigger(condition, sample_payout());
+    }
+
+    #[test]
+    #[should_panic(expected = "Custom expiry is outside the allowed bounds")]
+    fn test_custom_expiry_outside_bounds() {
+        let owner: AccountId = "owner.near".parse().unwrap();
+        let user: AccountId = "alice.near".parse().unwrap();
+        testing_env!(get_context(owner.clone(), 0).build());
+        let mut contract = TriggerPay::new(owner);
+        contract.set_expiry_bounds(DAY_NS, 20 * DAY_NS);
+
+        fund_storage(&mut contract, &user);
+
+        testing_env!(get_context(user, 10 * MINIMUM_DEPOSIT).build());
+        let condition = Condition {
+            expires_at: Some(NOW + 30 * DAY_NS),
+            ..sample_condition()
+        };
+        contract.create_trigger(condition, sample_payout());
+    }
+
+    fn flight_template() -> ConditionTemplate {
+        ConditionTemplate {
+            name: "Flight cancellation".to_string(),
+            condition_type: ConditionType::FlightCancellation,
+            bounds: ParamBounds {
+                max_payout_amount: Some("1000000000000000000".to_string()), // 1 ETH
+                min_lead_time: Some(DAY_NS),
+                max_lead_time: Some(60 * DAY_NS),
+            },
+            allowed_chains: vec![Chain::Ethereum, Chain::Base],
+            allowed_tokens: vec!["ETH".to_string()],
+            min_deposit: (2 * MINIMUM_DEPOSIT).to_string(),
+            expiry_buffer: DAY_NS,
+            status: TemplateStatus::Active,
+        }
+    }
+
+    #[test]
+    fn test_create_trigger_from_template() {
+        let owner: AccountId = "owner.near".parse().unwrap();
+        let user: AccountId = "alice.near".parse().unwrap();
+        testing_env!(get_context(owner.clone(), 0).build());
+        let mut contract = TriggerPay::new(owner);
+        contract.register_template("flight-basic".to_string(), flight_template());
+
+        fund_storage(&mut contract, &user);
+
+        testing_env!(get_context(user, 10 * MINIMUM_DEPOSIT).build());
+        let params = TemplateParams {
+            condition: sample_condition(),
+            payout: sample_payout(),
+        };
+        let trigger_id = contract.create_trigger_from_template("flight-basic".to_string(), params);
+
+        let trigger = contract.get_trigger(trigger_id).unwrap();
+        assert_eq!(trigger.template_id, Some("flight-basic".to_string()));
+        // Flight day 2026-02-15 ends at NOW + 6 days, plus the template's one-day buffer
+        assert_eq!(trigger.expires_at, NOW + 7 * DAY_NS);
+    }
+
+    #[test]
+    #[should_panic(expected = "Template is not accepting new triggers")]
+    fn test_paused_template_rejects_triggers() {
+        let owner: AccountId = "owner.near".parse().unwrap();
+        let user: AccountId = "alice.near".parse().unwrap();
+        testing_env!(get_context(owner.clone(), 0).build());
+        let mut contract = TriggerPay::new(owner);
+        contract.register_template("flight-basic".to_string(), flight_template());
+        contract.set_template_status("flight-basic".to_string(), TemplateStatus::Paused);
+
+        fund_storage(&mut contract, &user);
+
+        testing_env!(get_context(user, 10 * MINIMUM_DEPOSIT).build());
+        let params = TemplateParams {
+            condition: sample_condition(),
+            payout: sample_payout(),
+        };
+        contract.create_trigger_from_template("flight-basic".to_string(), params);
+    }
+
+    #[test]
+    #[should_panic(expected = "Payout token is not allowed by the template")]
+    fn test_template_token_not_allowed() {
+        let owner: AccountId = "owner.near".parse().unwrap();
+        let user: AccountId = "alice.near".parse().unwrap();
+        testing_env!(get_context(owner.clone(), 0).build());
+        let mut contract = TriggerPay::new(owner);
+        contract.register_template("flight-basic".to_string(), flight_template());
+
+        fund_storage(&mut contract, &user);
+
+        testing_env!(get_context(user, 10 * MINIMUM_DEPOSIT).build());
+        let params = TemplateParams {
+            condition: sample_condition(),
+            payout: Payout {
+                token: "USDC".to_string(),
+                ..sample_payout()
+            },
+        };
+        contract.create_trigger_from_template("flight-basic".to_string(), params);
+    }
+
+    fn usdc() -> AccountId {
+        "usdc.fakes.testnet".parse().unwrap()
+    }
+
+    fn token_trigger_msg() -> String {
+        serde_json::to_string(&FtTriggerMsg {
+            condition: sample_condition(),
+            payout: sample_payout(),
+            coverage: None,
+        })
+        .unwrap()
+    }
+
+    #[test]
+    fn test_ft_escrow_refund_restored_on_failure() {
+        let owner: AccountId = "owner.near".parse().unwrap();
+        let user: AccountId = "alice.near".parse().unwrap();
+        testing_env!(get_context(owner.clone(), 0).build());
+        let mut contract = TriggerPay::new(owner);
+        contract.register_escrow_token(usdc(), U128(10_000_000), U128(100_000)); // 10 USDC, 0.1 USDC fee
+
+        // The token contract calls ft_on_transfer on behalf of the sender
+        fund_storage(&mut contract, &user);
+        testing_env!(get_context(usdc(), 0).build());
+        let unused = contract.ft_on_transfer(user.clone(), U128(50_000_000), token_trigger_msg());
+        assert!(matches!(unused, PromiseOrValue::Value(U128(0))));
+
+        let trigger_id = contract.get_user_triggers(user.clone()).pop().unwrap().id;
+        let trigger = contract.get_trigger(trigger_id.clone()).unwrap();
+        assert_eq!(trigger.escrow_token, Some(usdc().to_string()));
+        assert_eq!(trigger.funded_amount, "50000000");
+
+        let mut context = get_context(user, 0);
+        context.block_timestamp(trigger.expires_at + 1);
+        testing_env!(context.build());
+        let _ = contract.claim_refund(trigger_id.clone());
+        assert_eq!(contract.get_trigger(trigger_id.clone()).unwrap().status, Status::Refunded);
+        // The refund fee kept back is booked to the token
+        assert_eq!(contract.get_escrow_token(usdc()).unwrap().fees, "100000");
+
+        // A failed ft_transfer puts the escrow back so the owner can claim again
+        testing_env!(
+            get_context(accounts(0), 0).current_account_id(accounts(0)).build(),
+            near_sdk::test_vm_config(),
+            near_sdk::RuntimeFeesConfig::test(),
+            Default::default(),
+            vec![near_sdk::PromiseResult::Failed],
+        );
+        assert!(!contract.on_refund_transfer(trigger_id.clone(), U128(100_000)));
+        assert_eq!(contract.get_trigger(trigger_id).unwrap().status, Status::Active);
+        assert_eq!(contract.get_escrow_token(usdc()).unwrap().fees, "0");
+    }
+
+    #[test]
+    fn test_sweep_failed_token_refund_not_requeued() {
+        let owner: AccountId = "owner.near".parse().unwrap();
+        let user: AccountId = "alice.near".parse().unwrap();
+        let keeper: AccountId = "keeper.near".parse().unwrap();
+        testing_env!(get_context(owner.clone(), 0).build());
+        let mut contract = TriggerPay::new(owner);
+        contract.register_escrow_token(usdc(), U128(10_000_000), U128(100_000));
+
+        fund_storage(&mut contract, &user);
+        testing_env!(get_context(usdc(), 0).build());
+        let _ = contract.ft_on_transfer(user.clone(), U128(50_000_000), token_trigger_msg());
+        let trigger = contract.get_user_triggers(user).pop().unwrap();
+
+        let mut context = get_context(keeper.clone(), 0);
+        context.block_timestamp(trigger.expires_at + 1);
+        testing_env!(context.build());
+        assert_eq!(contract.sweep_expired(10), 1);
+        // The keeper's bounty has left the trigger's escrow
+        assert_eq!(contract.get_trigger(trigger.id.clone()).unwrap().funded_amount, "49900000");
+
+        let mut context = get_context(accounts(0), 0);
+        context.current_account_id(accounts(0)).block_timestamp(trigger.expires_at + 1);
+        testing_env!(
+            context.build(),
+            near_sdk::test_vm_config(),
+            near_sdk::RuntimeFeesConfig::test(),
+            Default::default(),
+            vec![near_sdk::PromiseResult::Failed],
+        );
+        assert!(!contract.on_refund_transfer(trigger.id.clone(), U128(0)));
+        assert_eq!(contract.get_trigger(trigger.id.clone()).unwrap().status, Status::Active);
+
+        // Restored past expiry, it waits for claim_refund instead of another sweep
+        let mut context = get_context(keeper, 0);
+        context.block_timestamp(trigger.expires_at + 1);
+        testing_env!(context.build());
+        assert_eq!(contract.get_expired_count(10), 0);
+        assert_eq!(contract.sweep_expired(10), 0);
+    }
+
+    #[test]
+    #[should_panic(expected = "Token is not accepted for escrow")]
+    fn test_ft_escrow_unlisted_token() {
+        let owner: AccountId = "owner.near".parse().unwrap();
+        let user: AccountId = "alice.near".parse().unwrap();
+        testing_env!(get_context(owner.clone(), 0).build());
+        let mut contract = TriggerPay::new(owner);
+
+        fund_storage(&mut contract, &user);
+
+        testing_env!(get_context(usdc(), 0).build());
+        let _ = contract.ft_on_transfer(user, U128(50_000_000), token_trigger_msg());
+    }
+
+    #[test]
+    fn test_storage_charged_per_trigger() {
+        let owner: AccountId = "owner.near".parse().unwrap();
+        let user: AccountId = "alice.near".parse().unwrap();
+        testing_env!(get_context(owner.clone(), 0).build());
+        let mut contract = TriggerPay::new(owner);
+        let bounds = contract.storage_balance_bounds();
+        assert!(bounds.max.is_none());
+
+        fund_storage(&mut contract, &user);
+        let before = contract.storage_balance_of(user.clone()).unwrap();
+
+        testing_env!(get_context(user.clone(), 10 * MINIMUM_DEPOSIT).build());
+        let trigger_id = contract.create_trigger(sample_condition(), sample_payout());
+        let after = contract.storage_balance_of(user.clone()).unwrap();
+        assert_eq!(after.total, before.total);
+        let available = |b: &StorageBalance| b.available.parse::<Balance>().unwrap();
+        assert!(available(&after) < available(&before));
+
+        // Attestations on the trigger are charged to its owner
+        contract.submit_attestation(base_attestation(&trigger_id, false));
+        let attested = contract.storage_balance_of(user.clone()).unwrap();
+        assert!(available(&attested) < available(&after));
+
+        testing_env!(get_context(user.clone(), 1).build());
+        let withdrawn = contract.storage_withdraw(None);
+        assert_eq!(withdrawn.available, "0");
+        assert_eq!(contract.storage_balance_of(user).unwrap(), withdrawn);
+    }
+
+    #[test]
+    #[should_panic(expected = "Insufficient storage balance")]
+    fn test_create_trigger_insufficient_storage() {
+        let owner: AccountId = "owner.near".parse().unwrap();
+        let user: AccountId = "alice.near".parse().unwrap();
+        testing_env!(get_context(owner.clone(), 0).build());
+        let mut contract = TriggerPay::new(owner);
+
+        testing_env!(get_context(user.clone(), MINIMUM_DEPOSIT).build());
+        contract.storage_deposit(None, Some(true));
+
+        testing_env!(get_context(user, 10 * MINIMUM_DEPOSIT).build());
+        contract.create_trigger(sample_condition(), sample_payout());
+    }
+
+    fn flat_rate(cancellation_bps: u16) -> RiskRate {
+        RiskRate {
+            route: None,
+            airline: None,
+            month: None,
+            cancellation_bps,
+            delay_bps: 0,
+        }
+    }
+
+    /// Pool with a flat 10% claim rate and no loadings, so premiums are coverage / 10
+    fn setup_pool(contract: &mut TriggerPay, capital: Balance) {
+        testing_env!(get_context("owner.near".parse().unwrap(), 0).build());
+        contract.set_risk_rates(vec![flat_rate(1_000)]);
+        contract.register_asset("ETH".to_string(), 18);
+        contract.set_coverage_rate("ETH".to_string(), U128(10 * MINIMUM_DEPOSIT)); // 10 NEAR per ETH
+        contract.set_pricing_config(PricingConfig {
+            loading_bps: 0,
+            min_margin: "0".to_string(),
+            utilisation_surcharge_bps: 0,
+            slippage_bps: 100,
+        });
+        testing_env!(get_context("lp.near".parse().unwrap(), capital).build());
+        contract.pool_deposit();
+    }
+
+    #[test]
+    fn test_covered_trigger_paid_from_pool() {
+        let owner: AccountId = "owner.near".parse().unwrap();
+        let user: AccountId = "alice.near".parse().unwrap();
+        testing_env!(get_context(owner.clone(), 0).build());
+        let mut contract = TriggerPay::new(owner);
+        setup_pool(&mut contract, 100 * MINIMUM_DEPOSIT);
+        assert_eq!(contract.ft_balance_of("lp.near".parse().unwrap()).0, 100 * MINIMUM_DEPOSIT);
+
+        // 1 NEAR premium buys 10 NEAR of coverage at the flat 10% rate
+        fund_storage(&mut contract, &user);
+        testing_env!(get_context(user, MINIMUM_DEPOSIT).build());
+        let trigger_id = contract.create_covered_trigger(
+            sample_condition(),
+            sample_payout(),
+            U128(10 * MINIMUM_DEPOSIT),
+        );
+        let pool = contract.get_pool();
+        assert_eq!(pool.total_capital, (101 * MINIMUM_DEPOSIT).to_string());
+        assert_eq!(pool.total_exposure, (10 * MINIMUM_DEPOSIT).to_string());
+        assert_eq!(pool.free_capital, (91 * MINIMUM_DEPOSIT).to_string());
+
+        let _ = contract.submit_attestation(base_attestation(&trigger_id, true));
+        let pool = contract.get_pool();
+        assert_eq!(pool.total_exposure, "0");
+        assert_eq!(pool.total_capital, (91 * MINIMUM_DEPOSIT).to_string());
+    }
+
+    #[test]
+    fn test_covered_trigger_expiry_frees_capital() {
+        let owner: AccountId = "owner.near".parse().unwrap();
+        let user: AccountId = "alice.near".parse().unwrap();
+        testing_env!(get_context(owner.clone(), 0).build());
+        let mut contract = TriggerPay::new(owner);
+        setup_pool(&mut contract, 100 * MINIMUM_DEPOSIT);
+
+        fund_storage(&mut contract, &user);
+        testing_env!(get_context(user.clone(), MINIMUM_DEPOSIT).build());
+        let trigger_id = contract.create_covered_trigger(
+            sample_condition(),
+            sample_payout(),
+            U128(10 * MINIMUM_DEPOSIT),
+        );
+
+        let mut context = get_context(user, 0);
+        context.block_timestamp(contract.get_trigger(trigger_id.clone()).unwrap().expires_at + 1);
+        testing_env!(context.build());
+        assert!(contract.claim_refund(trigger_id.clone()).is_none());
+        assert_eq!(contract.get_trigger(trigger_id).unwrap().status, Status::Expired);
+
+        let pool = contract.get_pool();
+        assert_eq!(pool.total_exposure, "0");
+        assert_eq!(pool.free_capital, (101 * MINIMUM_DEPOSIT).to_string());
+    }
+
+    #[test]
+    #[should_panic(expected = "Withdrawal would exceed the pool utilisation limit")]
+    fn test_pool_withdraw_utilisation_limit() {
+        let owner: AccountId = "owner.near".parse().unwrap();
+        let user: AccountId = "alice.near".parse().unwrap();
+        let lp: AccountId = "lp.near".parse().unwrap();
+        testing_env!(get_context(owner.clone(), 0).build());
+        let mut contract = TriggerPay::new(owner);
+        setup_pool(&mut contract, 100 * MINIMUM_DEPOSIT);
+
+        fund_storage(&mut contract, &user);
+        testing_env!(get_context(user, 7 * MINIMUM_DEPOSIT).build());
+        contract.create_covered_trigger(sample_condition(), sample_payout(), U128(70 * MINIMUM_DEPOSIT));
+
+        // 70 reserved against 87 remaining is above the 80% limit
+        testing_env!(get_context(lp, 0).build());
+        let _ = contract.pool_withdraw(U128(20 * MINIMUM_DEPOSIT));
+    }
+
+    #[test]
+    fn test_pool_shares_priced_at_nav() {
+        let owner: AccountId = "owner.near".parse().unwrap();
+        let user: AccountId = "alice.near".parse().unwrap();
+        let lp: AccountId = "lp.near".parse().unwrap();
+        let bob: AccountId = "bob.near".parse().unwrap();
+        testing_env!(get_context(owner.clone(), 0).build());
+        let mut contract = TriggerPay::new(owner);
+        setup_pool(&mut contract, 100 * MINIMUM_DEPOSIT);
+
+        // Earn a 1 NEAR premium, then pay a 10 NEAR claim: capital is 91 NEAR
+        fund_storage(&mut contract, &user);
+        testing_env!(get_context(user, MINIMUM_DEPOSIT).build());
+        let trigger_id = contract.create_covered_trigger(
+            sample_condition(),
+            sample_payout(),
+            U128(10 * MINIMUM_DEPOSIT),
+        );
+        let _ = contract.submit_attestation(base_attestation(&trigger_id, true));
+
+        let pool = contract.get_pool();
+        assert_eq!(pool.nav_per_share, (MINIMUM_DEPOSIT / 100 * 91).to_string());
+        assert_eq!(pool.loss_ratio_bps, 100_000);
+        assert_eq!(pool.utilisation_bps, 0);
+
+        // A new LP buys in at NAV
+        testing_env!(get_context(bob.clone(), 91 * MINIMUM_DEPOSIT).build());
+        assert_eq!(contract.pool_deposit().0, 100 * MINIMUM_DEPOSIT);
+        assert_eq!(contract.ft_total_supply().0, 200 * MINIMUM_DEPOSIT);
+
+        // Shares are transferable to registered accounts
+        fund_storage(&mut contract, &bob);
+        testing_env!(get_context(lp.clone(), 1).build());
+        contract.ft_transfer(bob.clone(), U128(40 * MINIMUM_DEPOSIT), None);
+        assert_eq!(contract.ft_balance_of(lp).0, 60 * MINIMUM_DEPOSIT);
+        assert_eq!(contract.ft_balance_of(bob).0, 140 * MINIMUM_DEPOSIT);
+        assert_eq!(contract.ft_metadata().decimals, 24);
+    }
+
+    #[test]
+    #[should_panic(expected = "Payout exceeds the coverage")]
+    fn test_covered_payout_bounded_by_coverage() {
+        let owner: AccountId = "owner.near".parse().unwrap();
+        let user: AccountId = "alice.near".parse().unwrap();
+        testing_env!(get_context(owner.clone(), 0).build());
+        let mut contract = TriggerPay::new(owner);
+        setup_pool(&mut contract, 100 * MINIMUM_DEPOSIT);
+
+        // At 10 NEAR per ETH, 10 NEAR of coverage backs at most 1 ETH
+        fund_storage(&mut contract, &user);
+        testing_env!(get_context(user, MINIMUM_DEPOSIT).build());
+        let payout = Payout {
+            amount: "2000000000000000000".to_string(), // 2 ETH
+            ..sample_payout()
+        };
+        contract.create_covered_trigger(sample_condition(), payout, U128(10 * MINIMUM_DEPOSIT));
+    }
+
+    #[test]
+    #[should_panic(expected = "Pool capital is exhausted; deposits are closed")]
+    fn test_pool_deposit_rejected_after_capital_wiped_out() {
+        let owner: AccountId = "owner.near".parse().unwrap();
+        let user: AccountId = "alice.near".parse().unwrap();
+        testing_env!(get_context(owner.clone(), 0).build());
+        let mut contract = TriggerPay::new(owner);
+        setup_pool(&mut contract, 9 * MINIMUM_DEPOSIT);
+
+        // 9 NEAR capital plus a 1 NEAR premium pays a 10 NEAR claim
+        fund_storage(&mut contract, &user);
+        testing_env!(get_context(user, MINIMUM_DEPOSIT).build());
+        let trigger_id = contract.create_covered_trigger(
+            sample_condition(),
+            sample_payout(),
+            U128(10 * MINIMUM_DEPOSIT),
+        );
+        let _ = contract.submit_attestation(base_attestation(&trigger_id, true));
+        assert_eq!(contract.get_pool().total_capital, "0");
+
+        testing_env!(get_context("bob.near".parse().unwrap(), MINIMUM_DEPOSIT).build());
+        contract.pool_deposit();
+    }
+
+    #[test]
+    fn test_fund_trigger_refunds_contributors_pro_rata() {
+        let owner: AccountId = "owner.near".parse().unwrap();
+        let user: AccountId = "alice.near".parse().unwrap();
+        let employer: AccountId = "employer.near".parse().unwrap();
+        testing_env!(get_context(owner.clone(), 0).build());
+        let mut contract = TriggerPay::new(owner);
+
+        fund_storage(&mut contract, &user);
+        testing_env!(get_context(user.clone(), MINIMUM_DEPOSIT).build());
+        let trigger_id = contract.create_trigger(sample_condition(), sample_payout());
+
+        fund_storage(&mut contract, &employer);
+        testing_env!(get_context(employer.clone(), MINIMUM_DEPOSIT).build());
+        contract.fund_trigger(trigger_id.clone(), None);
+
+        // Doubling the escrow again allows the payout to double: 0.5 ETH to 1 ETH
+        testing_env!(get_context(user.clone(), 2 * MINIMUM_DEPOSIT).build());
+        contract.fund_trigger(trigger_id.clone(), Some(U128(1_000_000_000_000_000_000)));
+
+        let trigger = contract.get_trigger(trigger_id.clone()).unwrap();
+        assert_eq!(trigger.funded_amount, (4 * MINIMUM_DEPOSIT).to_string());
+        assert_eq!(trigger.payout.amount, "1000000000000000000");
+        assert_eq!(trigger.contributions.len(), 1);
+        assert_eq!(trigger.contributions[0].funder, "employer.near");
+
+        let mut context = get_context(user, 0);
+        context.block_timestamp(trigger.expires_at + 1);
+        testing_env!(context.build());
+        let _ = contract.claim_refund(trigger_id);
+
+        // The employer gets back a quarter, the owner the rest
+        let transfers: Vec<(String, Balance)> = near_sdk::test_utils::get_created_receipts()
+            .into_iter()
+            .filter_map(|receipt| match receipt.actions.first() {
+                Some(near_sdk::mock::MockAction::Transfer { deposit, .. }) => {
+                    Some((receipt.receiver_id.to_string(), deposit.as_yoctonear()))
+                }
+                _ => None,
+            })
+            .collect();
+        assert!(transfers.contains(&("employer.near".to_string(), MINIMUM_DEPOSIT)));
+        assert!(transfers.contains(&("alice.near".to_string(), 3 * MINIMUM_DEPOSIT)));
+    }
+
+    #[test]
+    #[should_panic(expected = "Trigger has reached the contributor limit")]
+    fn test_fund_trigger_contributor_limit() {
+        let owner: AccountId = "owner.near".parse().unwrap();
+        let user: AccountId = "alice.near".parse().unwrap();
+        testing_env!(get_context(owner.clone(), 0).build());
+        let mut contract = TriggerPay::new(owner);
+
+        fund_storage(&mut contract, &user);
+        testing_env!(get_context(user, MINIMUM_DEPOSIT).build());
+        let trigger_id = contract.create_trigger(sample_condition(), sample_payout());
+
+        // Twenty contributors fill the trigger; the twenty-first is refused
+        for i in 0..=20 {
+            let funder: AccountId = format!("funder{}.near", i).parse().unwrap();
+            fund_storage(&mut contract, &funder);
+            testing_env!(get_context(funder, MINIMUM_DEPOSIT / 10).build());
+            contract.fund_trigger(trigger_id.clone(), None);
+        }
+    }
+
+    #[test]
+    #[should_panic(expected = "Payout must stay within the funded coverage")]
+    fn test_fund_trigger_payout_above_coverage() {
+        let owner: AccountId = "owner.near".parse().unwrap();
+        let user: AccountId = "alice.near".parse().unwrap();
+        testing_env!(get_context(owner.clone(), 0).build());
+        let mut contract = TriggerPay::new(owner);
+
+        fund_storage(&mut contract, &user);
+        testing_env!(get_context(user.clone(), MINIMUM_DEPOSIT).build());
+        let trigger_id = contract.create_trigger(sample_condition(), sample_payout());
+
+        // 1 NEAR more covers at most 1 ETH
+        testing_env!(get_context(user, MINIMUM_DEPOSIT).build());
+        contract.fund_trigger(trigger_id, Some(U128(1_500_000_000_000_000_000)));
+    }
+
+    #[test]
+    fn test_cancel_trigger_fee_shrinks_with_time_remaining() {
+        let owner: AccountId = "owner.near".parse().unwrap();
+        let user: AccountId = "alice.near".parse().unwrap();
+        testing_env!(get_context(owner.clone(), 0).build());
+        let mut contract = TriggerPay::new(owner);
+
+        fund_storage(&mut contract, &user);
+        testing_env!(get_context(user.clone(), MINIMUM_DEPOSIT).build());
+        let trigger_id = contract.create_trigger(sample_condition(), sample_payout());
+
+        // 5 of 30 days remain before departure: 20% - 18% * 5/30 = 17%
+        assert_eq!(
+            contract.get_cancellation_fee(trigger_id.clone()).0,
+            MINIMUM_DEPOSIT / 100 * 17
+        );
+        let mut context = get_context(user.clone(), 0);
+        context.block_timestamp(NOW - 25 * DAY_NS);
+        testing_env!(context.build());
+        assert_eq!(
+            contract.get_cancellation_fee(trigger_id.clone()).0,
+            MINIMUM_DEPOSIT / 100 * 2
+        );
+
+        testing_env!(get_context(user, 0).build());
+        let _ = contract.cancel_trigger(trigger_id.clone());
+        assert_eq!(contract.get_trigger(trigger_id).unwrap().status, Status::Cancelled);
+        // The fee leaves the escrow for the treasury earnings
+        assert_eq!(
+            contract.get_treasury().earnings,
+            (MINIMUM_DEPOSIT / 100 * 17).to_string()
+        );
+    }
+
+    #[test]
+    #[should_panic(expected = "Cancellation window has closed")]
+    fn test_cancel_trigger_inside_cutoff() {
+        let owner: AccountId = "owner.near".parse().unwrap();
+        let user: AccountId = "alice.near".parse().unwrap();
+        testing_env!(get_context(owner.clone(), 0).build());
+        let mut contract = TriggerPay::new(owner);
+
+        fund_storage(&mut contract, &user);
+        testing_env!(get_context(user.clone(), MINIMUM_DEPOSIT).build());
+        let trigger_id = contract.create_trigger(sample_condition(), sample_payout());
+
+        // Twelve hours before the flight day begins
+        let mut context = get_context(user, 0);
+        context.block_timestamp(NOW + 4 * DAY_NS + DAY_NS / 2);
+        testing_env!(context.build());
+        let _ = contract.cancel_trigger(trigger_id);
+    }
+
+    #[test]
+    #[should_panic(expected = "Not enough liquid NEAR for the refund")]
+    fn test_refund_requires_liquid_near() {
+        let owner: AccountId = "owner.near".parse().unwrap();
+        let user: AccountId = "alice.near".parse().unwrap();
+        testing_env!(get_context(owner.clone(), 0).build());
+        let mut contract = TriggerPay::new(owner);
+
+        fund_storage(&mut contract, &user);
+        testing_env!(get_context(user.clone(), 10 * MINIMUM_DEPOSIT).build());
+        let trigger_id = contract.create_trigger(sample_condition(), sample_payout());
+        let expires_at = contract.get_trigger(trigger_id.clone()).unwrap().expires_at;
+
+        // Most of the escrow is delegated: the sweeper leaves the trigger queued
+        let mut context = get_context("keeper.near".parse().unwrap(), 0);
+        context
+            .block_timestamp(expires_at + 1)
+            .account_balance(NearToken::from_near(5));
+        testing_env!(context.build());
+        assert_eq!(contract.sweep_expired(10), 0);
+        assert_eq!(contract.get_expired_count(10), 1);
+
+        let mut context = get_context(user, 0);
+        context
+            .block_timestamp(expires_at + 1)
+            .account_balance(NearToken::from_near(5));
+        testing_env!(context.build());
+        let _ = contract.claim_refund(trigger_id);
+    }
+
+    /// Local stand-in for a staking pool's `deposit_and_stake`/`unstake`/`withdraw`
+    #[derive(Default)]
+    struct MockStakingPool {
+        staked: Balance,
+        unstaked: Balance,
+    }
+
+    impl MockStakingPool {
+        fn deposit_and_stake(&mut self, amount: Balance) {
+            self.staked += amount;
+        }
+
+        fn unstake(&mut self, amount: Balance) {
+            assert!(amount <= self.staked, "Not enough staked balance");
+            self.staked -= amount;
+            self.unstaked += amount;
+        }
+
+        fn withdraw(&mut self, amount: Balance) {
+            assert!(amount <= self.unstaked, "Not enough unstaked balance");
+            self.unstaked -= amount;
+        }
+
+        fn distribute_rewards(&mut self, rewards: Balance) {
+            self.staked += rewards;
+        }
+
+        fn get_account_total_balance(&self) -> U128 {
+            U128(self.staked + self.unstaked)
+        }
+    }
+
+    /// Run the next call as a callback receiving `result`
+    fn callback_env(result: near_sdk::PromiseResult) {
+        testing_env!(
+            get_context(accounts(0), 0).current_account_id(accounts(0)).build(),
+            near_sdk::test_vm_config(),
+            near_sdk::RuntimeFeesConfig::test(),
+            Default::default(),
+            vec![result],
+        );
+    }
+
+    fn setup_treasury(contract: &mut TriggerPay, pool: &AccountId) {
+        testing_env!(get_context("owner.near".parse().unwrap(), 0).build());
+        contract.add_staking_pool(pool.clone());
+        contract.set_treasury_policy(TreasuryPolicy {
+            staking_pool: Some(pool.to_string()),
+            ..TreasuryPolicy::default()
+        });
+    }
+
+    #[test]
+    fn test_treasury_stakes_idle_escrow_and_distributes_yield() {
+        let owner: AccountId = "owner.near".parse().unwrap();
+        let user: AccountId = "alice.near".parse().unwrap();
+        let staking_pool: AccountId = "validator.poolv1.near".parse().unwrap();
+        testing_env!(get_context(owner.clone(), 0).build());
+        let mut contract = TriggerPay::new(owner.clone());
+        setup_pool(&mut contract, 10 * MINIMUM_DEPOSIT);
+        setup_treasury(&mut contract, &staking_pool);
+
+        fund_storage(&mut contract, &user);
+        testing_env!(get_context(user.clone(), 10 * MINIMUM_DEPOSIT).build());
+        let trigger_id = contract.create_trigger(sample_condition(), sample_payout());
+
+        // 10 NEAR escrow and 10 NEAR pool capital: 30% stays liquid, half the rest is staked
+        testing_env!(get_context(owner.clone(), 0).build());
+        let view = contract.get_treasury();
+        assert_eq!(view.liabilities, (20 * MINIMUM_DEPOSIT).to_string());
+        assert_eq!(view.reserve, (6 * MINIMUM_DEPOSIT).to_string());
+        let liquid: Balance = view.liquid.parse().unwrap();
+        let stakeable = (liquid - 6 * MINIMUM_DEPOSIT) / 2;
+        assert_eq!(view.stakeable, stakeable.to_string());
+
+        let mut mock = MockStakingPool::default();
+        let _ = contract.treasury_stake();
+        mock.deposit_and_stake(stakeable);
+        callback_env(near_sdk::PromiseResult::Successful(vec![]));
+        assert!(contract.on_treasury_stake(U128(stakeable)));
+        assert_eq!(contract.get_treasury().staked, stakeable.to_string());
+
+        // 1 NEAR of rewards: 50% to escrow, 30% to LPs, 20% to the treasury
+        mock.distribute_rewards(MINIMUM_DEPOSIT);
+        testing_env!(get_context(owner.clone(), 0).build());
+        let _ = contract.treasury_harvest();
+        callback_env(near_sdk::PromiseResult::Successful(vec![]));
+        let rewards = contract.on_treasury_harvest(Ok(mock.get_account_total_balance()));
+        assert_eq!(rewards.0, MINIMUM_DEPOSIT);
+        assert_eq!(
+            contract.get_trigger(trigger_id).unwrap().funded_amount,
+            (10 * MINIMUM_DEPOSIT + MINIMUM_DEPOSIT / 2).to_string()
+        );
+        assert_eq!(contract.get_pool().total_capital, (10 * MINIMUM_DEPOSIT + MINIMUM_DEPOSIT / 10 * 3).to_string());
+        assert_eq!(contract.get_treasury().earnings, (MINIMUM_DEPOSIT / 10 * 2).to_string());
+        // Every reward is still owed to someone
+        assert_eq!(contract.get_treasury().liabilities, (21 * MINIMUM_DEPOSIT).to_string());
+
+        // A trigger funded after the harvest earns none of it
+        testing_env!(get_context(user, 10 * MINIMUM_DEPOSIT).build());
+        let later_id = contract.create_trigger(sample_condition(), sample_payout());
+        assert_eq!(
+            contract.get_trigger(later_id).unwrap().funded_amount,
+            (10 * MINIMUM_DEPOSIT).to_string()
+        );
+
+        // Unstake and withdraw the rewards
+        testing_env!(get_context(owner.clone(), 0).build());
+        let _ = contract.treasury_unstake(U128(MINIMUM_DEPOSIT));
+        mock.unstake(MINIMUM_DEPOSIT);
+        callback_env(near_sdk::PromiseResult::Successful(vec![]));
+        assert!(contract.on_treasury_unstake(U128(MINIMUM_DEPOSIT)));
+        testing_env!(get_context(owner, 0).build());
+        let _ = contract.treasury_withdraw();
+        mock.withdraw(MINIMUM_DEPOSIT);
+        callback_env(near_sdk::PromiseResult::Successful(vec![]));
+        assert!(contract.on_treasury_withdraw(U128(MINIMUM_DEPOSIT)));
+
+        let view = contract.get_treasury();
+        assert_eq!(view.staked, stakeable.to_string());
+        assert_eq!(view.unstaking, "0");
+        assert_eq!(mock.get_account_total_balance().0, stakeable);
+    }
+
+    #[test]
+    fn test_treasury_failed_stake_is_reverted() {
+        let owner: AccountId = "owner.near".parse().unwrap();
+        let user: AccountId = "alice.near".parse().unwrap();
+        let staking_pool: AccountId = "validator.poolv1.near".parse().unwrap();
+        testing_env!(get_context(owner.clone(), 0).build());
+        let mut contract = TriggerPay::new(owner.clone());
+        setup_treasury(&mut contract, &staking_pool);
+
+        fund_storage(&mut contract, &user);
+        testing_env!(get_context(user, 10 * MINIMUM_DEPOSIT).build());
+        contract.create_trigger(sample_condition(), sample_payout());
+
+        testing_env!(get_context(owner, 0).build());
+        let stakeable: Balance = contract.get_treasury().stakeable.parse().unwrap();
+        let _ = contract.treasury_stake();
+        callback_env(near_sdk::PromiseResult::Failed);
+        assert!(!contract.on_treasury_stake(U128(stakeable)));
+        assert_eq!(contract.get_treasury().staked, "0");
+    }
+
+    /// Receiver, method and deposit of each function call created so far
+    fn created_calls() -> Vec<(String, String, Balance)> {
+        near_sdk::test_utils::get_created_receipts()
+            .into_iter()
+            .flat_map(|receipt| {
+                let receiver = receipt.receiver_id.to_string();
+                receipt.actions.into_iter().filter_map(move |action| match action {
+                    near_sdk::mock::MockAction::FunctionCallWeight {
+                        method_name,
+                        attached_deposit,
+                        ..
+                    } => Some((
+                        receiver.clone(),
+                        String::from_utf8(method_name).unwrap(),
+                        attached_deposit.as_yoctonear(),
+                    )),
+                    _ => None,
+                })
+            })
+            .collect()
+    }
+
+    #[test]
+    fn test_treasury_callbacks_follow_pool_results() {
+        let owner: AccountId = "owner.near".parse().unwrap();
+        let user: AccountId = "alice.near".parse().unwrap();
+        let staking_pool: AccountId = "validator.poolv1.near".parse().unwrap();
+        let pool_call = |method: &str, deposit: Balance| (staking_pool.to_string(), method.to_string(), deposit);
+        testing_env!(get_context(owner.clone(), 0).build());
+        let mut contract = TriggerPay::new(owner.clone());
+        setup_treasury(&mut contract, &staking_pool);
+
+        fund_storage(&mut contract, &user);
+        testing_env!(get_context(user, 10 * MINIMUM_DEPOSIT).build());
+        contract.create_trigger(sample_condition(), sample_payout());
+
+        // deposit_and_stake carries the stake as its deposit
+        testing_env!(get_context(owner.clone(), 0).build());
+        let stakeable: Balance = contract.get_treasury().stakeable.parse().unwrap();
+        let _ = contract.treasury_stake();
+        assert!(created_calls().contains(&pool_call("deposit_and_stake", stakeable)));
+        callback_env(near_sdk::PromiseResult::Successful(vec![]));
+        assert!(contract.on_treasury_stake(U128(stakeable)));
+
+        // A failed unstake leaves the principal staked
+        testing_env!(get_context(owner.clone(), 0).build());
+        let _ = contract.treasury_unstake(U128(MINIMUM_DEPOSIT));
+        assert!(created_calls().contains(&pool_call("unstake", 0)));
+        callback_env(near_sdk::PromiseResult::Failed);
+        assert!(!contract.on_treasury_unstake(U128(MINIMUM_DEPOSIT)));
+        let view = contract.get_treasury();
+        assert_eq!(view.staked, stakeable.to_string());
+        assert_eq!(view.unstaking, "0");
+
+        // A failed withdraw keeps the NEAR unstaking until it is retried
+        testing_env!(get_context(owner.clone(), 0).build());
+        let _ = contract.treasury_unstake(U128(MINIMUM_DEPOSIT));
+        callback_env(near_sdk::PromiseResult::Successful(vec![]));
+        assert!(contract.on_treasury_unstake(U128(MINIMUM_DEPOSIT)));
+        testing_env!(get_context(owner.clone(), 0).build());
+        let _ = contract.treasury_withdraw();
+        assert!(created_calls().contains(&pool_call("withdraw", 0)));
+        callback_env(near_sdk::PromiseResult::Failed);
+        assert!(!contract.on_treasury_withdraw(U128(MINIMUM_DEPOSIT)));
+        assert_eq!(contract.get_treasury().unstaking, MINIMUM_DEPOSIT.to_string());
+        callback_env(near_sdk::PromiseResult::Successful(vec![]));
+        assert!(contract.on_treasury_withdraw(U128(MINIMUM_DEPOSIT)));
+        assert_eq!(contract.get_treasury().unstaking, "0");
+
+        // A failed balance view, or a balance below principal, books no rewards
+        testing_env!(get_context(owner, 0).build());
+        let _ = contract.treasury_harvest();
+        assert!(created_calls().contains(&pool_call("get_account_total_balance", 0)));
+        callback_env(near_sdk::PromiseResult::Failed);
+        assert_eq!(contract.on_treasury_harvest(Err(PromiseError::Failed)).0, 0);
+        let principal = stakeable - MINIMUM_DEPOSIT;
+        callback_env(near_sdk::PromiseResult::Successful(
+            serde_json::to_vec(&U128(principal - 1)).unwrap(),
+        ));
+        assert_eq!(contract.on_treasury_harvest(Ok(U128(principal - 1))).0, 0);
+        let view = contract.get_treasury();
+        assert_eq!(view.staked, principal.to_string());
+        assert_eq!(view.yield_distributed, "0");
+    }
+
+    #[test]
+    fn test_sweep_expired_refunds_oldest_first_and_pays_keeper() {
+        let owner: AccountId = "owner.near".parse().unwrap();
+        let user: AccountId = "alice.near".parse().unwrap();
+        let keeper: AccountId = "keeper.near".parse().unwrap();
+        testing_env!(get_context(owner.clone(), 0).build());
+        let mut contract = TriggerPay::new(owner);
+
+        fund_storage(&mut contract, &user);
+        let mut trigger_ids = Vec::new();
+        for flight_date in ["2026-02-20", "2026-02-15", "2026-03-01"] {
+            testing_env!(get_context(user.clone(), MINIMUM_DEPOSIT).build());
+            let condition = Condition {
+                flight_date: flight_date.to_string(),
+                ..sample_condition()
+            };
+            trigger_ids.push(contract.create_trigger(condition, sample_payout()));
+        }
+
+        // The two February flights have expired; only one is swept per call
+        let mut context = get_context(keeper.clone(), 0);
+        context.block_timestamp(contract.get_trigger(trigger_ids[0].clone()).unwrap().expires_at + 1);
+        testing_env!(context.build());
+        assert_eq!(contract.get_expired_count(10), 2);
+        assert_eq!(contract.sweep_expired(1), 1);
+        assert_eq!(contract.get_trigger(trigger_ids[1].clone()).unwrap().status, Status::Expired);
+        assert_eq!(contract.get_trigger(trigger_ids[0].clone()).unwrap().status, Status::Active);
+
+        assert_eq!(contract.sweep_expired(10), 1);
+        assert_eq!(contract.get_trigger(trigger_ids[0].clone()).unwrap().status, Status::Expired);
+        assert_eq!(contract.get_trigger(trigger_ids[2].clone()).unwrap().status, Status::Active);
+        assert_eq!(contract.sweep_expired(10), 0);
+
+        // Each sweep refunded the owner less the keeper fee, and paid the keeper
+        let transfers: Vec<(String, Balance)> = near_sdk::test_utils::get_created_receipts()
+            .into_iter()
+            .filter_map(|receipt| match receipt.actions.first() {
+                Some(near_sdk::mock::MockAction::Transfer { deposit, .. }) => {
+                    Some((receipt.receiver_id.to_string(), deposit.as_yoctonear()))
+                }
+                _ => None,
+            })
+            .collect();
+        assert!(transfers.contains(&("alice.near".to_string(), MINIMUM_DEPOSIT - DEFAULT_KEEPER_FEE)));
+        assert!(transfers.contains(&("keeper.near".to_string(), DEFAULT_KEEPER_FEE)));
+    }
+
+    #[test]
+    fn test_sweep_skips_claimed_triggers() {
+        let owner: AccountId = "owner.near".parse().unwrap();
+        let user: AccountId = "alice.near".parse().unwrap();
+        testing_env!(get_context(owner.clone(), 0).build());
+        let mut contract = TriggerPay::new(owner);
+
+        fund_storage(&mut contract, &user);
+        testing_env!(get_context(user.clone(), MINIMUM_DEPOSIT).build());
+        let trigger_id = contract.create_trigger(sample_condition(), sample_payout());
+
+        let mut context = get_context(user, 0);
+        context.block_timestamp(contract.get_trigger(trigger_id.clone()).unwrap().expires_at + 1);
+        testing_env!(context.build());
+        let _ = contract.claim_refund(trigger_id.clone());
+        assert_eq!(contract.get_expired_count(10), 0);
+        assert_eq!(contract.sweep_expired(10), 0);
+        assert_eq!(contract.get_trigger(trigger_id).unwrap().status, Status::Refunded);
+    }
+
+    #[test]
+    #[should_panic(expected = "Exposure limit reached for flight:AA1234:2026-02-15:ETH")]
+    fn test_exposure_limit_per_flight() {
+        let owner: AccountId = "owner.near".parse().unwrap();
+        let user: AccountId = "alice.near".parse().unwrap();
+        testing_env!(get_context(owner.clone(), 0).build());
+        let mut contract = TriggerPay::new(owner);
+
+        // Room for two 0.5 ETH payouts on the flight
+        contract.set_exposure_limits("ETH".to_string(), ExposureLimits {
+            per_flight: Some("1000000000000000000".to_string()),
+            ..ExposureLimits::default()
+        });
+
+        fund_storage(&mut contract, &user);
+        testing_env!(get_context(user, 10 * MINIMUM_DEPOSIT).build());
+        contract.create_trigger(sample_condition(), sample_payout());
+        let other_spelling = Condition {
+            flight_number: "aa 01234".to_string(),
+            ..sample_condition()
+        };
+        contract.create_trigger(other_spelling, sample_payout());
+        contract.create_trigger(sample_condition(), sample_payout());
+    }
+
+    #[test]
+    fn test_exposure_counted_per_payout_token() {
+        let owner: AccountId = "owner.near".parse().unwrap();
+        let user: AccountId = "alice.near".parse().unwrap();
+        testing_env!(get_context(owner.clone(), 0).build());
+        let mut contract = TriggerPay::new(owner);
+        contract.set_exposure_limits("eth".to_string(), ExposureLimits {
+            per_chain: Some("500000000000000000".to_string()),
+            ..ExposureLimits::default()
+        });
+
+        fund_storage(&mut contract, &user);
+        testing_env!(get_context(user, 10 * MINIMUM_DEPOSIT).build());
+        contract.create_trigger(sample_condition(), sample_payout());
+
+        // USDC on the same chain neither counts against nor is capped by ETH
+        let usdc_payout = Payout {
+            amount: "5000000000".to_string(), // 5,000 USDC
+            token: "USDC".to_string(),
+            ..sample_payout()
+        };
+        contract.create_trigger(sample_condition(), usdc_payout.clone());
+
+        let eth = contract.get_exposure_capacity(sample_condition(), sample_payout());
+        assert_eq!(eth[0].bucket, "chain:Ethereum:ETH");
+        assert_eq!(eth[0].remaining, Some("0".to_string()));
+        let usdc = contract.get_exposure_capacity(sample_condition(), usdc_payout);
+        assert_eq!(usdc[0].bucket, "chain:Ethereum:USDC");
+        assert_eq!(usdc[0].exposure, "5000000000");
+        assert_eq!(usdc[0].limit, None);
+    }
+
+    #[test]
+    fn test_exposure_released_on_refund() {
+        let owner: AccountId = "owner.near".parse().unwrap();
+        let user: AccountId = "alice.near".parse().unwrap();
+        testing_env!(get_context(owner.clone(), 0).build());
+        let mut contract = TriggerPay::new(owner);
+        contract.set_exposure_limits("ETH".to_string(), ExposureLimits {
+            per_chain: Some("2000000000000000000".to_string()),
+            ..ExposureLimits::default()
+        });
+
+        fund_storage(&mut contract, &user);
+        testing_env!(get_context(user.clone(), MINIMUM_DEPOSIT).build());
+        let trigger_id = contract.create_trigger(sample_condition(), sample_payout());
+
+        let capacity = contract.get_exposure_capacity(sample_condition(), sample_payout());
+        let buckets: Vec<&str> = capacity.iter().map(|c| c.bucket.as_str()).collect();
+        assert_eq!(buckets, vec!["chain:Ethereum:ETH", "flight:AA1234:2026-02-15:ETH", "day:2026-02-15:ETH"]);
+        assert_eq!(capacity[0].exposure, "500000000000000000");
+        assert_eq!(capacity[0].remaining, Some("1500000000000000000".to_string()));
+        assert_eq!(capacity[1].remaining, None);
+
+        let mut context = get_context(user, 0);
+        context.block_timestamp(contract.get_trigger(trigger_id.clone()).unwrap().expires_at + 1);
+        testing_env!(context.build());
+        let _ = contract.claim_refund(trigger_id);
+
+        let capacity = contract.get_exposure_capacity(sample_condition(), sample_payout());
+        assert_eq!(capacity[0].exposure, "0");
+        assert_eq!(format_iso_date(parse_iso_date("2028-02-29").unwrap()), "2028-02-29");
+    }
+
+    #[test]
+    fn test_quote_premium_uses_most_specific_rate() {
+        let owner: AccountId = "owner.near".parse().unwrap();
+        testing_env!(get_context(owner.clone(), 0).build());
+        let mut contract = TriggerPay::new(owner.clone());
+        setup_pool(&mut contract, 100 * MINIMUM_DEPOSIT);
+
+        testing_env!(get_context(owner, 0).build());
+        contract.set_risk_rates(vec![
+            RiskRate {
+                route: Some("JFK-LAX".to_string()),
+                airline: Some("AA".to_string()),
+                month: Some(2),
+                ..flat_rate(2_000)
+            },
+            RiskRate {
+                airline: Some("AA".to_string()),
+                ..flat_rate(500)
+            },
+        ]);
+        contract.set_pricing_config(PricingConfig {
+            loading_bps: 2_000,
+            min_margin: "0".to_string(),
+            utilisation_surcharge_bps: 5_000,
+            slippage_bps: 100,
+        });
+
+        // 20% claim rate, +20% loading, +5% surcharge at 10% utilisation
+        let routed = Condition {
+            route: "jfk-lax".to_string(),
+            ..sample_condition()
+        };
+        let quote = contract.quote_premium(routed, sample_payout(), U128(10 * MINIMUM_DEPOSIT));
+        assert_eq!(quote.probability_bps, 2_000);
+        assert_eq!(quote.expected_loss, (2 * MINIMUM_DEPOSIT).to_string());
+        assert_eq!(quote.utilisation_bps, 1_000);
+        assert_eq!(quote.premium, (MINIMUM_DEPOSIT / 100 * 252).to_string());
+
+        // Without a route the airline-wide rate applies
+        let quote = contract.quote_premium(sample_condition(), sample_payout(), U128(10 * MINIMUM_DEPOSIT));
+        assert_eq!(quote.probability_bps, 500);
+
+        // Other airlines fall back to the flat rate
+        let other = Condition {
+            flight_number: "BA117".to_string(),
+            ..sample_condition()
+        };
+        let quote = contract.quote_premium(other, sample_payout(), U128(10 * MINIMUM_DEPOSIT));
+        assert_eq!(quote.probability_bps, 1_000);
+    }
+
+    #[test]
+    #[should_panic(expected = "Premium does not match the quote")]
+    fn test_covered_trigger_premium_outside_slippage() {
+        let owner: AccountId = "owner.near".parse().unwrap();
+        let user: AccountId = "alice.near".parse().unwrap();
+        testing_env!(get_context(owner.clone(), 0).build());
+        let mut contract = TriggerPay::new(owner);
+        setup_pool(&mut contract, 100 * MINIMUM_DEPOSIT);
+
+        // Quote is 1 NEAR with 1% slippage
+        fund_storage(&mut contract, &user);
+        testing_env!(get_context(user, MINIMUM_DEPOSIT / 100 * 98).build());
+        contract.create_covered_trigger(sample_condition(), sample_payout(), U128(10 * MINIMUM_DEPOSIT));
+    }
+
+    #[test]
+    #[should_panic(expected = "Receiver is not registered for storage")]
+    fn test_pool_share_transfer_requires_registration() {
+        let owner: AccountId = "owner.near".parse().unwrap();
+        testing_env!(get_context(owner.clone(), 0).build());
+        let mut contract = TriggerPay::new(owner);
+        setup_pool(&mut contract, 100 * MINIMUM_DEPOSIT);
+
+        testing_env!(get_context("lp.near".parse().unwrap(), 1).build());
+        contract.ft_transfer("bob.near".parse().unwrap(), U128(MINIMUM_DEPOSIT), None);
+    }
+
+    #[test]
+    fn test_status_transition_matrix() {
+        use Status::*;
+        let legal = [
+            (Pending, Active),
+            (Pending, Cancelled),
+            (Active, PendingPayout),
+            (Active, PayoutSigned),
+            (Active, Disputed),
+            (Active, Cancelled),
+            (Active, Refunded),
+            (Active, Expired),
+            (PendingPayout, PayoutSigned),
+            (PendingPayout, PayoutFailed),
+            (PayoutSigned, Settled),
+            (PayoutSigned, Disputed),
+            (PayoutFailed, PendingPayout),
+            (PayoutFailed, Disputed),
+            (Disputed, Active),
+            (Disputed, PendingPayout),
+            (Disputed, Settled),
+            (Disputed, Refunded),
+            (Cancelled, Active),
+            (Refunded, Active),
+            (Expired, Active),
+        ];
+        for from in Status::ALL.iter() {
+            for to in Status::ALL.iter() {
+                let expected = legal.iter().any(|(f, t)| f == from && t == to);
+                assert_eq!(
+                    from.can_transition_to(to),
+                    expected,
+                    "{:?} -> {:?}",
+                    from,
+                    to
+                );
+            }
+        }
+    }
+
+    #[test]
+    fn test_payout_lifecycle_is_recorded() {
+        let owner: AccountId = "owner.near".parse().unwrap();
+        let user: AccountId = "alice.near".parse().unwrap();
+        testing_env!(get_context(owner.clone(), 0).build());
+        let mut contract = TriggerPay::new(owner.clone());
+        fund_storage(&mut contract, &user);
+        testing_env!(get_context(user.clone(), 10 * MINIMUM_DEPOSIT).build());
+        let trigger_id = contract.create_trigger(sample_condition(), sample_payout());
+
+        assert!(contract.submit_attestation(base_attestation(&trigger_id, true)).is_some());
+        assert_eq!(contract.get_trigger(trigger_id.clone()).unwrap().status, Status::PendingPayout);
+
+        // A failed signature can be retried by the trigger owner
+        callback_env(near_sdk::PromiseResult::Failed);
+        contract.on_payout_signed(trigger_id.clone(), Err(near_sdk::PromiseError::Failed));
+        assert_eq!(contract.get_trigger(trigger_id.clone()).unwrap().status, Status::PayoutFailed);
+        testing_env!(get_context(user.clone(), 0).build());
+        contract.retry_payout(trigger_id.clone()).detach();
+
+        callback_env(near_sdk::PromiseResult::Successful(vec![]));
+        contract.on_payout_signed(trigger_id.clone(), Ok(near_sdk::serde_json::Value::Null));
+        testing_env!(get_context(owner, 0).build());
+        contract.confirm_payout(trigger_id.clone(), "0xabc".to_string());
+
+        let trigger = contract.get_trigger(trigger_id.clone()).unwrap();
+        assert_eq!(trigger.status, Status::Settled);
+        assert_eq!(trigger.executed_tx, Some("0xabc".to_string()));
+
+        let history = contract.get_status_history(trigger_id);
+        let moves: Vec<(Status, Status)> = history.iter().map(|c| (c.from.clone(), c.to.clone())).collect();
+        assert_eq!(
+            moves,
+            vec![
+                (Status::Pending, Status::Active),
+                (Status::Active, Status::PendingPayout),
+                (Status::PendingPayout, Status::PayoutFailed),
+                (Status::PayoutFailed, Status::PendingPayout),
+                (Status::PendingPayout, Status::PayoutSigned),
+                (Status::PayoutSigned, Status::Settled),
+            ]
+        );
+        assert_eq!(history[0].actor, user.to_string());
+        assert_eq!(history[2].actor, accounts(0).to_string());
+        assert_eq!(history[5].actor, "owner.near");
+        assert!(history.iter().all(|c| c.at == NOW));
+    }
+
+    #[test]
+    fn test_disputed_trigger_is_frozen_until_resolved() {
+        let owner: AccountId = "owner.near".parse().unwrap();
+        let user: AccountId = "alice.near".parse().unwrap();
+        testing_env!(get_context(owner.clone(), 0).build());
+        let mut contract = TriggerPay::new(owner.clone());
+        fund_storage(&mut contract, &user);
+        testing_env!(get_context(user.clone(), 10 * MINIMUM_DEPOSIT).build());
+        let trigger_id = contract.create_trigger(sample_condition(), sample_payout());
+
+        contract.dispute_trigger(trigger_id.clone(), "Flight data is wrong".to_string());
+        assert_eq!(contract.get_active_triggers().len(), 0);
+
+        // Resolving to a refund returns the escrow
+        testing_env!(get_context(owner, 0).build());
+        assert!(contract.resolve_dispute(trigger_id.clone(), Status::Refunded).is_some());
+        assert_eq!(contract.get_trigger(trigger_id).unwrap().status, Status::Refunded);
+    }
+
+    #[test]
+    #[should_panic(expected = "Illegal status transition from Active to Settled")]
+    fn test_illegal_transition_is_rejected() {
+        let owner: AccountId = "owner.near".parse().unwrap();
+        let user: AccountId = "alice.near".parse().unwrap();
+        testing_env!(get_context(owner.clone(), 0).build());
+        let mut contract = TriggerPay::new(owner.clone());
+        fund_storage(&mut contract, &user);
+        testing_env!(get_context(user, 10 * MINIMUM_DEPOSIT).build());
+        let trigger_id = contract.create_trigger(sample_condition(), sample_payout());
+
+        testing_env!(get_context(owner, 0).build());
+        contract.confirm_payout(trigger_id, "0xabc".to_string());
+    }
+
+    #[test]
+    fn test_trigger_history_timeline() {
+        let owner: AccountId = "owner.near".parse().unwrap();
+        let user: AccountId = "alice.near".parse().unwrap();
+        let funder: AccountId = "bob.near".parse().unwrap();
+        testing_env!(get_context(owner.clone(), 0).build());
+        let mut contract = TriggerPay::new(owner.clone());
+        fund_storage(&mut contract, &user);
+        fund_storage(&mut contract, &funder);
+        testing_env!(get_context(user.clone(), 10 * MINIMUM_DEPOSIT).build());
+        let trigger_id = contract.create_trigger(sample_condition(), sample_payout());
+
+        testing_env!(get_context(funder.clone(), MINIMUM_DEPOSIT).build());
+        contract.fund_trigger(trigger_id.clone(), None);
+        testing_env!(get_context(owner.clone(), 0).build());
+        assert!(contract.submit_attestation(base_attestation(&trigger_id, true)).is_some());
+        callback_env(near_sdk::PromiseResult::Successful(vec![]));
+        contract.on_payout_signed(trigger_id.clone(), Ok(near_sdk::serde_json::Value::Null));
+
+        let history = contract.get_trigger_history(trigger_id.clone(), None, None);
+        let events: Vec<TriggerEvent> = history.iter().map(|e| e.event.clone()).collect();
+        assert_eq!(
+            events,
+            vec![
+                TriggerEvent::Created { amount: (10 * MINIMUM_DEPOSIT).to_string() },
+                TriggerEvent::StatusChanged { from: Status::Pending, to: Status::Active },
+                TriggerEvent::Funded { amount: MINIMUM_DEPOSIT.to_string() },
+                TriggerEvent::Attested { condition_met: true, leaf_index: None },
+                TriggerEvent::StatusChanged { from: Status::Active, to: Status::PendingPayout },
+                TriggerEvent::PayoutRequested { instalment: None },
+                TriggerEvent::PayoutSigned { instalment: None },
+                TriggerEvent::StatusChanged { from: Status::PendingPayout, to: Status::PayoutSigned },
+            ]
+        );
+        assert_eq!(history[2].actor, funder.to_string());
+        assert_eq!(history[3].actor, owner.to_string());
+        assert!(history.iter().enumerate().all(|(i, e)| e.index == i as u64));
+
+        // Pages pick up where the previous one ended
+        let page = contract.get_trigger_history(trigger_id, Some(6), Some(5));
+        assert_eq!(page.len(), 2);
+        assert_eq!(page[0].index, 6);
+    }
+
+    #[test]
+    fn test_trigger_history_keeps_latest_events() {
+        let owner: AccountId = "owner.near".parse().unwrap();
+        let user: AccountId = "alice.near".parse().unwrap();
+        testing_env!(get_context(owner.clone(), 0).build());
+        let mut contract = TriggerPay::new(owner.clone());
+        fund_storage(&mut contract, &user);
+        testing_env!(get_context(user, 10 * MINIMUM_DEPOSIT).build());
+        let trigger_id = contract.create_trigger(sample_condition(), sample_payout());
+
+        testing_env!(get_context(owner, 0).build());
+        for _ in 0..MAX_TRIGGER_EVENTS {
+            assert!(contract.submit_attestation(base_attestation(&trigger_id, false)).is_none());
+        }
+
+        // Created and the first status change have been overwritten
+        let history = contract.get_trigger_history(trigger_id.clone(), Some(0), None);
+        assert_eq!(history[0].index, 2);
+        assert_eq!(history[0].event, TriggerEvent::Attested { condition_met: false, leaf_index: None });
+        let last = contract.get_trigger_history(trigger_id, Some(MAX_TRIGGER_EVENTS), None);
+        assert_eq!(last.len(), 2);
+        assert_eq!(last[1].index, MAX_TRIGGER_EVENTS + 1);
+    }
+
+    #[test]
+    fn test_transferred_trigger_follows_new_owner() {
+        let owner: AccountId = "owner.near".parse().unwrap();
+        let user: AccountId = "alice.near".parse().unwrap();
+        let buyer: AccountId = "bob.near".parse().unwrap();
+        testing_env!(get_context(owner.clone(), 0).build());
+        let mut contract = TriggerPay::new(owner);
+        fund_storage(&mut contract, &user);
+        fund_storage(&mut contract, &buyer);
+        testing_env!(get_context(user.clone(), MINIMUM_DEPOSIT).build());
+        let trigger_id = contract.create_trigger(sample_condition(), sample_payout());
+
+        testing_env!(get_context(user.clone(), 1).build());
+        contract.nft_transfer(buyer.clone(), trigger_id.clone(), None, None);
+        assert!(contract.get_user_triggers(user).is_empty());
+        assert_eq!(contract.get_user_triggers(buyer.clone())[0].id, trigger_id);
+
+        let token = contract.nft_token(trigger_id.clone()).unwrap();
+        assert_eq!(token.owner_id, buyer.to_string());
+        let metadata = token.metadata.unwrap();
+        assert_eq!(
+            metadata.description.unwrap(),
+            "Flight AA1234 on 2026-02-15: pays 500000000000000000 ETH on Ethereum. Status: Active"
+        );
+
+        // The holder claims the refund
+        let mut context = get_context(buyer.clone(), 0);
+        context.block_timestamp(contract.get_trigger(trigger_id.clone()).unwrap().expires_at + 1);
+        testing_env!(context.build());
+        assert!(contract.claim_refund(trigger_id.clone()).is_some());
+        let receipts = near_sdk::test_utils::get_created_receipts();
+        assert_eq!(receipts.last().unwrap().receiver_id, buyer);
+    }
+
+    #[test]
+    fn test_approved_marketplace_transfers_and_receiver_can_return() {
+        let owner: AccountId = "owner.near".parse().unwrap();
+        let user: AccountId = "alice.near".parse().unwrap();
+        let market: AccountId = "market.near".parse().unwrap();
+        let buyer: AccountId = "bob.near".parse().unwrap();
+        testing_env!(get_context(owner.clone(), 0).build());
+        let mut contract = TriggerPay::new(owner);
+        fund_storage(&mut contract, &user);
+        fund_storage(&mut contract, &buyer);
+        testing_env!(get_context(user.clone(), MINIMUM_DEPOSIT).build());
+        let trigger_id = contract.create_trigger(sample_condition(), sample_payout());
+
+        testing_env!(get_context(user.clone(), 1).build());
+        assert!(contract.nft_approve(trigger_id.clone(), market.clone(), None).is_none());
+        assert!(contract.nft_is_approved(trigger_id.clone(), market.clone(), Some(0)));
+        assert!(!contract.nft_is_approved(trigger_id.clone(), market.clone(), Some(1)));
+
+        testing_env!(get_context(market.clone(), 1).build());
+        let _ = contract.nft_transfer_call(buyer.clone(), trigger_id.clone(), Some(0), None, String::new());
+        assert_eq!(contract.get_trigger(trigger_id.clone()).unwrap().owner, buyer.to_string());
+        assert!(!contract.nft_is_approved(trigger_id.clone(), market.clone(), None));
+
+        // The receiver asks for the trigger to be returned
+        callback_env(near_sdk::PromiseResult::Successful(b"true".to_vec()));
+        let mut approvals = std::collections::HashMap::new();
+        approvals.insert(market.clone(), 0);
+        assert!(!contract.nft_resolve_transfer(user.clone(), buyer, trigger_id.clone(), Some(approvals)));
+        assert_eq!(contract.get_trigger(trigger_id.clone()).unwrap().owner, user.to_string());
+        assert_eq!(contract.get_user_triggers(user)[0].id, trigger_id);
+        assert!(contract.nft_is_approved(trigger_id, market, Some(0)));
+    }
+
+    #[test]
+    #[should_panic(expected = "Sender is not approved to transfer this trigger")]
+    fn test_unapproved_transfer_is_rejected() {
+        let owner: AccountId = "owner.near".parse().unwrap();
+        let user: AccountId = "alice.near".parse().unwrap();
+        let buyer: AccountId = "bob.near".parse().unwrap();
+        testing_env!(get_context(owner.clone(), 0).build());
+        let mut contract = TriggerPay::new(owner);
+        fund_storage(&mut contract, &user);
+        fund_storage(&mut contract, &buyer);
+        testing_env!(get_context(user, MINIMUM_DEPOSIT).build());
+        let trigger_id = contract.create_trigger(sample_condition(), sample_payout());
+
+        testing_env!(get_context(buyer.clone(), 1).build());
+        contract.nft_transfer(buyer, trigger_id, None, None);
+    }
+
+    const NEW_PAYOUT_ADDRESS: &str = "0x2B5AD5c4795c026514f8317c7a215E218DcCD6cF";
+    // Key 1's EIP-191 signature of the veto message for the change proposed at NOW
+    const VETO_SIGNATURE: &str = "0x9377c312145a5afb911bf9e8c067bcf6094c533603687850df502b61290bbf5e\
+                                  773fe02ebcff7e5782d74ca7405857768c61c9d9e5c0faefa3ec50366ab59baf1b";
+
+    fn setup_payout_change() -> (TriggerPay, TriggerId) {
+        let owner: AccountId = "owner.near".parse().unwrap();
+        let user: AccountId = "alice.near".parse().unwrap();
+        testing_env!(get_context(owner.clone(), 0).build());
+        let mut contract = TriggerPay::new(owner);
+        fund_storage(&mut contract, &user);
+
+        // Address of secp256k1 private key 1
+        let payout = Payout {
+            address: "0x7E5F4552091A69125d5DfCb7b8C2659029395Bdf".to_string(),
+            ..sample_payout()
+        };
+        testing_env!(get_context(user.clone(), MINIMUM_DEPOSIT).build());
+        let trigger_id = contract.create_trigger(sample_condition(), payout);
+
+        testing_env!(get_context(user, 0).build());
+        contract.propose_payout_change(trigger_id.clone(), NEW_PAYOUT_ADDRESS.to_string());
+        (contract, trigger_id)
+    }
+
+    #[test]
+    fn test_payout_change_applies_after_delay() {
+        let (mut contract, trigger_id) = setup_payout_change();
+        let change = contract.get_payout_change(trigger_id.clone()).unwrap();
+        assert_eq!(change.executable_at, NOW + DEFAULT_PAYOUT_CHANGE_DELAY);
+
+        let mut context = get_context("alice.near".parse().unwrap(), 0);
+        context.block_timestamp(change.executable_at);
+        testing_env!(context.build());
+        contract.apply_payout_change(trigger_id.clone());
+
+        let trigger = contract.get_trigger(trigger_id.clone()).unwrap();
+        assert_eq!(trigger.payout.address, NEW_PAYOUT_ADDRESS);
+        assert!(contract.get_payout_change(trigger_id.clone()).is_none());
+        let history = contract.get_trigger_history(trigger_id, None, None);
+        assert_eq!(
+            history.last().unwrap().event,
+            TriggerEvent::PayoutChangeApplied {
+                old_address: "0x7E5F4552091A69125d5DfCb7b8C2659029395Bdf".to_string(),
+                new_address: NEW_PAYOUT_ADDRESS.to_string(),
+            }
+        );
+    }
+
+    #[test]
+    #[should_panic(expected = "Payout change is still time-locked")]
+    fn test_payout_change_is_time_locked() {
+        let (mut contract, trigger_id) = setup_payout_change();
+        contract.apply_payout_change(trigger_id);
+    }
+
+    #[test]
+    fn test_old_payout_address_vetoes_change() {
+        let (mut contract, trigger_id) = setup_payout_change();
+        assert_eq!(
+            contract.payout_change_veto_message(trigger_id.clone()),
+            "Cancel TriggerPay payout change for trig_00000001 to 0x2b5ad5c4795c026514f8317c7a215e218dccd6cf \
+             proposed at 1770681600000000000"
+        );
+
+        // Anyone may relay the old address's signature
+        testing_env!(get_context("relayer.near".parse().unwrap(), 0).build());
+        contract.cancel_payout_change(trigger_id.clone(), Some(VETO_SIGNATURE.to_string()));
+        assert!(contract.get_payout_change(trigger_id).is_none());
+    }
+
+    #[test]
+    #[should_panic(expected = "Signature is not from the payout address")]
+    fn test_payout_change_veto_not_replayable() {
+        let (mut contract, trigger_id) = setup_payout_change();
+        testing_env!(get_context("relayer.near".parse().unwrap(), 0).build());
+        contract.cancel_payout_change(trigger_id.clone(), Some(VETO_SIGNATURE.to_string()));
+
+        // The same address proposed again needs a fresh veto
+        let mut context = get_context("alice.near".parse().unwrap(), 0);
+        context.block_timestamp(NOW + 1);
+        testing_env!(context.build());
+        contract.propose_payout_change(trigger_id.clone(), NEW_PAYOUT_ADDRESS.to_string());
+        testing_env!(get_context("relayer.near".parse().unwrap(), 0).build());
+        contract.cancel_payout_change(trigger_id, Some(VETO_SIGNATURE.to_string()));
+    }
+
+    #[test]
+    #[should_panic(expected = "Signature is not from the payout address")]
+    fn test_payout_change_veto_requires_payout_address_signature() {
+        let (mut contract, trigger_id) = setup_payout_change();
+        testing_env!(get_context("relayer.near".parse().unwrap(), 0).build());
+        let signature = format!("0x{}1b", "11".repeat(64));
+        contract.cancel_payout_change(trigger_id, Some(signature));
+    }
+
+    #[test]
+    fn test_payout_change_dropped_when_trigger_resolves() {
+        let (mut contract, trigger_id) = setup_payout_change();
+        testing_env!(get_context("owner.near".parse().unwrap(), 0).build());
+        assert!(contract.submit_attestation(base_attestation(&trigger_id, true)).is_some());
+        assert!(contract.get_payout_change(trigger_id.clone()).is_none());
+
+        let history = contract.get_trigger_history(trigger_id, None, None);
+        assert!(history.iter().any(|e| e.event == TriggerEvent::PayoutChangeCancelled));
+    }
+
+    fn setup_roles() -> (TriggerPay, TriggerId) {
+        let owner: AccountId = "owner.near".parse().unwrap();
+        let funder: AccountId = "acme.near".parse().unwrap();
+        testing_env!(get_context(owner.clone(), 0).build());
+        let mut contract = TriggerPay::new(owner);
+        fund_storage(&mut contract, &funder);
+        testing_env!(get_context(funder.clone(), MINIMUM_DEPOSIT).build());
+        let trigger_id = contract.create_trigger(sample_condition(), sample_payout());
+
+        testing_env!(get_context(funder, 0).build());
+        contract.set_traveller(trigger_id.clone(), Some("alice.near".parse().unwrap()));
+        contract.set_beneficiary(trigger_id.clone(), Some("bob.near".parse().unwrap()));
+        (contract, trigger_id)
+    }
+
+    #[test]
+    fn test_trigger_roles_have_their_own_views_and_permissions() {
+        let (mut contract, trigger_id) = setup_roles();
+        let funder: AccountId = "acme.near".parse().unwrap();
+        let traveller: AccountId = "alice.near".parse().unwrap();
+        let beneficiary: AccountId = "bob.near".parse().unwrap();
+
+        let by_role = |contract: &TriggerPay, account: &AccountId, role: TriggerRole| -> Vec<TriggerId> {
+            contract
+                .get_triggers_by_role(account.clone(), role)
+                .into_iter()
+                .map(|t| t.id)
+                .collect()
+        };
+        assert_eq!(by_role(&contract, &funder, TriggerRole::Funder), vec![trigger_id.clone()]);
+        assert!(by_role(&contract, &funder, TriggerRole::Beneficiary).is_empty());
+        assert_eq!(by_role(&contract, &traveller, TriggerRole::Traveller), vec![trigger_id.clone()]);
+        assert_eq!(by_role(&contract, &beneficiary, TriggerRole::Beneficiary), vec![trigger_id.clone()]);
+
+        // The beneficiary controls the payout address and can hand the role on
+        testing_env!(get_context(beneficiary.clone(), 0).build());
+        contract.propose_payout_change(trigger_id.clone(), "0x2B5AD5c4795c026514f8317c7a215E218DcCD6cF".to_string());
+        contract.set_beneficiary(trigger_id.clone(), Some("carol.near".parse().unwrap()));
+        assert!(contract.get_payout_change(trigger_id.clone()).is_none());
+        assert!(by_role(&contract, &beneficiary, TriggerRole::Beneficiary).is_empty());
+
+        // The traveller can dispute; refunds still go to the funder
+        testing_env!(get_context(traveller, 0).build());
+        contract.dispute_trigger(trigger_id.clone(), "Flight was cancelled".to_string());
+        testing_env!(get_context("owner.near".parse().unwrap(), 0).build());
+        assert!(contract.resolve_dispute(trigger_id.clone(), Status::Refunded).is_some());
+        let receipts = near_sdk::test_utils::get_created_receipts();
+        assert_eq!(receipts.last().unwrap().receiver_id, funder);
+
+        let view = contract.get_trigger(trigger_id).unwrap();
+        assert_eq!(view.beneficiary, Some("carol.near".to_string()));
+        assert_eq!(view.traveller, Some("alice.near".to_string()));
+    }
+
+    #[test]
+    #[should_panic(expected = "Only the trigger beneficiary can change the payout")]
+    fn test_funder_cannot_redirect_beneficiary_payout() {
+        let (mut contract, trigger_id) = setup_roles();
+        contract.propose_payout_change(trigger_id, "0x2B5AD5c4795c026514f8317c7a215E218DcCD6cF".to_string());
+    }
+
+    #[test]
+    fn test_schedule_top_up_spread_over_remaining_instalments() {
+        let owner: AccountId = "owner.near".parse().unwrap();
+        let user: AccountId = "alice.near".parse().unwrap();
+        testing_env!(get_context(owner.clone(), 0).build());
+        let mut contract = TriggerPay::new(owner);
+
+        fund_storage(&mut contract, &user);
+        testing_env!(get_context(user.clone(), 2 * MINIMUM_DEPOSIT).build());
+        let trigger_id = contract.create_trigger(monthly_schedule(NOW + DAY_NS, 2), sample_payout());
+
+        let mut context = get_context("keeper.near".parse().unwrap(), 0);
+        context.block_timestamp(NOW + DAY_NS);
+        testing_env!(context.build());
+        let _ = contract.execute_schedule(trigger_id.clone());
+        contract.on_instalment_signed(trigger_id.clone(), 0, Ok(serde_json::json!({})));
+
+        // Topped up between instalments, the rest of the escrow goes to the last one
+        let mut context = get_context(user, 2 * MINIMUM_DEPOSIT);
+        context.block_timestamp(NOW + 2 * DAY_NS);
+        testing_env!(context.build());
+        contract.fund_trigger(trigger_id.clone(), None);
+
+        let mut context = get_context("keeper.near".parse().unwrap(), 0);
+        context.block_timestamp(NOW + 31 * DAY_NS);
+        testing_env!(context.build());
+        let _ = contract.execute_schedule(trigger_id.clone());
+        contract.on_instalment_signed(trigger_id.clone(), 1, Ok(serde_json::json!({})));
+
+        let trigger = contract.get_trigger(trigger_id).unwrap();
+        assert_eq!(trigger.status, Status::PayoutSigned);
+        let amounts: Vec<&str> = trigger.instalments.iter().map(|i| i.escrow_amount.as_str()).collect();
+        assert_eq!(amounts, vec![MINIMUM_DEPOSIT.to_string(), (3 * MINIMUM_DEPOSIT).to_string()]);
+        assert_eq!(trigger.funded_amount, (4 * MINIMUM_DEPOSIT).to_string());
+    }
+
+    #[test]
+    fn test_sports_window_follows_trigger_expiry() {
+        let owner: AccountId = "owner.near".parse().unwrap();
+        let user: AccountId = "alice.near".parse().unwrap();
+        testing_env!(get_context(owner.clone(), 0).build());
+        let mut contract = TriggerPay::new(owner.clone());
+
+        fund_storage(&mut contract, &user);
+        testing_env!(get_context(user, 10 * MINIMUM_DEPOSIT).build());
+        let trigger_id = contract.create_trigger(sports_condition(OutcomeSelector::HomeWin), sample_payout());
+        let expires_at = contract.get_trigger(trigger_id.clone()).unwrap().expires_at;
+
+        // Shortening the buffer later does not close an existing trigger's window
+        testing_env!(get_context(owner.clone(), 0).build());
+        contract.set_settlement_buffer(ConditionType::SportsOutcome, 0);
+        let mut context = get_context(owner, 0);
+        context.block_timestamp(expires_at);
+        testing_env!(context.build());
+        assert!(contract
+            .submit_attestation(sports_attestation(&trigger_id, MatchStatus::Finished, 2, 0, true))
+            .is_some());
+    }
+
+    #[test]
+    fn test_sports_total_does_not_overflow() {
+        let owner: AccountId = "owner.near".parse().unwrap();
+        let user: AccountId = "alice.near".parse().unwrap();
+        testing_env!(get_context(owner.clone(), 0).build());
+        let mut contract = TriggerPay::new(owner);
+
+        fund_storage(&mut contract, &user);
+        testing_env!(get_context(user, 10 * MINIMUM_DEPOSIT).build());
+        let line = OutcomeSelector::TotalOver { line: "2.5".to_string() };
+        let trigger_id = contract.create_trigger(sports_condition(line), sample_payout());
+
+        assert!(contract
+            .submit_attestation(sports_attestation(&trigger_id, MatchStatus::Finished, u32::MAX, 1, true))
+            .is_some());
+    }
+
+    #[test]
+    fn test_storage_freed_on_refund_is_credited() {
+        let owner: AccountId = "owner.near".parse().unwrap();
+        let user: AccountId = "alice.near".parse().unwrap();
+        testing_env!(get_context(owner.clone(), 0).build());
+        let mut contract = TriggerPay::new(owner);
+
+        fund_storage(&mut contract, &user);
+        testing_env!(get_context(user.clone(), MINIMUM_DEPOSIT).build());
+        let trigger_id = contract.create_trigger(sample_condition(), sample_payout());
+
+        let mut context = get_context(user.clone(), 0);
+        context.block_timestamp(contract.get_trigger(trigger_id.clone()).unwrap().expires_at + 1);
+        testing_env!(context.build());
+        let available = |contract: &TriggerPay| -> i128 {
+            contract.storage_balance_of(user.clone()).unwrap().available.parse().unwrap()
+        };
+        let (usage, before) = (env::storage_usage(), available(&contract));
+        let _ = contract.claim_refund(trigger_id);
+
+        // The expiry entry and exposure buckets it frees offset the new history
+        let grown = env::storage_usage() as i128 - usage as i128;
+        let byte_cost = env::storage_byte_cost().as_yoctonear() as i128;
+        assert_eq!(before - available(&contract), grown * byte_cost);
+    }
+
+    #[test]
+    fn test_trigger_storage_moves_with_transfer() {
+        let owner: AccountId = "owner.near".parse().unwrap();
+        let user: AccountId = "alice.near".parse().unwrap();
+        let buyer: AccountId = "bob.near".parse().unwrap();
+        testing_env!(get_context(owner.clone(), 0).build());
+        let mut contract = TriggerPay::new(owner);
+        fund_storage(&mut contract, &user);
+        fund_storage(&mut contract, &buyer);
+        let fresh = contract.storage_balance_of(buyer.clone()).unwrap().available;
+        testing_env!(get_context(user.clone(), MINIMUM_DEPOSIT).build());
+        let trigger_id = contract.create_trigger(sample_condition(), sample_payout());
+        assert_ne!(contract.storage_balance_of(user.clone()).unwrap().available, fresh);
+
+        testing_env!(get_context(user.clone(), 1).build());
+        contract.nft_transfer(buyer.clone(), trigger_id, None, None);
+
+        // The seller no longer pays for the trigger; the buyer does
+        assert_eq!(contract.storage_balance_of(user).unwrap().available, fresh);
+        let available: Balance = contract.storage_balance_of(buyer).unwrap().available.parse().unwrap();
+        assert!(available < fresh.parse().unwrap());
+    }
+
+    #[test]
+    #[should_panic(expected = "Insufficient storage balance")]
+    fn test_transfer_requires_receiver_storage() {
+        let owner: AccountId = "owner.near".parse().unwrap();
+        let user: AccountId = "alice.near".parse().unwrap();
+        let buyer: AccountId = "bob.near".parse().unwrap();
+        testing_env!(get_context(owner.clone(), 0).build());
+        let mut contract = TriggerPay::new(owner);
+        fund_storage(&mut contract, &user);
+        testing_env!(get_context(buyer.clone(), MINIMUM_DEPOSIT).build());
+        contract.storage_deposit(None, Some(true));
+        testing_env!(get_context(user.clone(), MINIMUM_DEPOSIT).build());
+        let trigger_id = contract.create_trigger(sample_condition(), sample_payout());
+
+        testing_env!(get_context(user, 1).build());
+        contract.nft_transfer(buyer, trigger_id, None, None);
+    }
+
+    #[test]
+    #[should_panic(expected = "Settlement time is too far in the future")]
+    fn test_far_future_settlement_rejected() {
+        let owner: AccountId = "owner.near".parse().unwrap();
+        let user: AccountId = "alice.near".parse().unwrap();
+        testing_env!(get_context(owner.clone(), 0).build());
+        let mut contract = TriggerPay::new(owner);
+
+        fund_storage(&mut contract, &user);
+        testing_env!(get_context(user, 10 * MINIMUM_DEPOSIT).build());
+        contract.create_shipment_trigger(shipment_condition(u64::MAX - 1), sample_payout(), buyer_payout());
+    }
+
+    #[test]
+    fn test_sweep_passes_over_triggers_it_cannot_refund() {
+        let owner: AccountId = "owner.near".parse().unwrap();
+        let user: AccountId = "alice.near".parse().unwrap();
+        testing_env!(get_context(owner.clone(), 0).build());
+        let mut contract = TriggerPay::new(owner);
+
+        fund_storage(&mut contract, &user);
+        testing_env!(get_context(user.clone(), MINIMUM_DEPOSIT).build());
+        let scheduled = contract.create_trigger(monthly_schedule(NOW + DAY_NS, 1), sample_payout());
+        testing_env!(get_context(user, MINIMUM_DEPOSIT).build());
+        let later_flight = Condition {
+            flight_date: "2026-02-20".to_string(),
+            ..sample_condition()
+        };
+        let flight = contract.create_trigger(later_flight, sample_payout());
+
+        // The oldest entry has an instalment in flight and stays queued
+        let mut context = get_context("keeper.near".parse().unwrap(), 0);
+        context.block_timestamp(contract.get_trigger(flight.clone()).unwrap().expires_at + 1);
+        testing_env!(context.build());
+        let _ = contract.execute_schedule(scheduled.clone());
+        assert_eq!(contract.sweep_expired(1), 1);
+        assert_eq!(contract.get_trigger(flight).unwrap().status, Status::Expired);
+        assert_eq!(contract.get_trigger(scheduled).unwrap().status, Status::Active);
+    }
 }
@@ -0,0 +1,276 @@
+use near_sdk::borsh::{BorshDeserialize, BorshSerialize};
+use near_sdk::collections::Vector;
+use near_sdk::serde::{Deserialize, Serialize};
+use near_sdk::serde_json::Value;
+use near_sdk::{env, Gas, Promise, PromiseError};
+use schemars::JsonSchema;
+
+use crate::{
+    PayoutBranch, Status, StorageKey, Trigger, TriggerEvent, TriggerId, TriggerPay, TriggerPayExt,
+};
+
+// ============================================================================
+// Constants
+// ============================================================================
+
+const GAS_FOR_PAYOUT_CALLBACK: Gas = Gas::from_tgas(10);
+
+// ============================================================================
+// Types
+// ============================================================================
+
+/// One recorded status transition
+#[derive(BorshDeserialize, BorshSerialize, Serialize, Deserialize, Clone, Debug, JsonSchema)]
+#[borsh(crate = "near_sdk::borsh")]
+#[serde(crate = "near_sdk::serde")]
+pub struct StatusChange {
+    pub from: Status,
+    pub to: Status,
+    pub at: u64,       // Nanoseconds
+    pub actor: String, // Predecessor account; the contract itself for callbacks
+}
+
+impl Status {
+    /// Every lifecycle state, in order
+    pub const ALL: [Status; 10] = [
+        Status::Pending,
+        Status::Active,
+        Status::PendingPayout,
+        Status::PayoutSigned,
+        Status::Settled,
+        Status::PayoutFailed,
+        Status::Disputed,
+        Status::Cancelled,
+        Status::Refunded,
+        Status::Expired,
+    ];
+
+    /// Legal moves of the trigger lifecycle. Refund states may return to
+    /// Active only when a refund transfer fails and the escrow is restored.
+    pub fn can_transition_to(&self, next: &Status) -> bool {
+        use Status::*;
+        matches!(
+            (self, next),
+            (Pending, Active | Cancelled)
+                | (Active, PendingPayout | PayoutSigned | Disputed | Cancelled | Refunded | Expired)
+                | (PendingPayout, PayoutSigned | PayoutFailed)
+                | (PayoutSigned, Settled | Disputed)
+                | (PayoutFailed, PendingPayout | Disputed)
+                | (Disputed, Active | PendingPayout | Settled | Refunded)
+                | (Cancelled | Refunded | Expired, Active)
+        )
+    }
+}
+
+// ============================================================================
+// Lifecycle
+// ============================================================================
+
+#[near_sdk::near]
+impl TriggerPay {
+    pub fn get_status_history(&self, trigger_id: TriggerId) -> Vec<StatusChange> {
+        self.status_history
+            .get(&trigger_id)
+            .map(|v: Vector<StatusChange>| v.iter().collect())
+            .unwrap_or_default()
+    }
+
+    /// Callback from the MPC signer for a trigger's payout
+    #[private]
+    pub fn on_payout_signed(
+        &mut self,
+        trigger_id: TriggerId,
+        #[callback_result] result: Result<Value, PromiseError>,
+    ) {
+        let mut trigger = self.triggers.get(&trigger_id).expect("Trigger not found");
+        let (next, event) = if result.is_ok() {
+            (Status::PayoutSigned, TriggerEvent::PayoutSigned { instalment: None })
+        } else {
+            (Status::PayoutFailed, TriggerEvent::PayoutFailed { instalment: None })
+        };
+        env::log_str(&format!("Payout for {}: {:?}", trigger_id, next));
+        self.record_event(&trigger, event);
+        self.transition(&mut trigger, next);
+    }
+
+    /// Request the signature again after a failed payout. Any trigger party
+    /// or the contract owner can call this.
+    pub fn retry_payout(&mut self, trigger_id: TriggerId) -> Promise {
+        let mut trigger = self.triggers.get(&trigger_id).expect("Trigger not found");
+        self.assert_trigger_party(&trigger);
+        assert!(
+            trigger.status == Status::PayoutFailed,
+            "Payout has not failed"
+        );
+        self.transition(&mut trigger, Status::PendingPayout);
+        self.request_payout(&trigger)
+    }
+
+    /// Record the broadcast transaction of a signed payout (only owner can call)
+    pub fn confirm_payout(&mut self, trigger_id: TriggerId, tx_hash: String) {
+        self.assert_owner();
+        let mut trigger = self.triggers.get(&trigger_id).expect("Trigger not found");
+        assert!(!tx_hash.is_empty(), "Transaction hash is required");
+        trigger.executed_tx = Some(tx_hash.clone());
+        self.record_event(&trigger, TriggerEvent::PayoutConfirmed { tx_hash });
+        self.transition(&mut trigger, Status::Settled);
+    }
+
+    /// Freeze a trigger whose outcome or payout is contested. Any trigger
+    /// party or the contract owner can call this.
+    pub fn dispute_trigger(&mut self, trigger_id: TriggerId, reason: String) {
+        let mut trigger = self.triggers.get(&trigger_id).expect("Trigger not found");
+        self.assert_trigger_party(&trigger);
+        self.transition(&mut trigger, Status::Disputed);
+
+        env::log_str(&format!("Trigger {} disputed: {}", trigger_id, reason));
+    }
+
+    /// Settle a dispute (only owner can call): resume watching (Active), pay
+    /// out (PendingPayout), accept a signed payout (Settled) or refund the
+    /// escrow (Refunded). Triggers disputed after firing can only be paid or
+    /// settled.
+    pub fn resolve_dispute(&mut self, trigger_id: TriggerId, outcome: Status) -> Option<Promise> {
+        self.assert_owner();
+        let mut trigger = self.triggers.get(&trigger_id).expect("Trigger not found");
+        assert!(trigger.status == Status::Disputed, "Trigger is not disputed");
+
+        // A fired branch means the payout was already booked against the pool
+        // and exposure caps, so only payout outcomes remain
+        let paid = trigger.fired_branch.is_some();
+        match outcome {
+            Status::Active => {
+                assert!(!paid, "Payout has already been initiated");
+                self.transition(&mut trigger, Status::Active);
+                self.index_expiry(&trigger);
+                None
+            }
+            Status::PendingPayout if paid => {
+                self.transition(&mut trigger, Status::PendingPayout);
+                Some(self.request_payout(&trigger))
+            }
+            Status::PendingPayout => Some(self.begin_payout(trigger, PayoutBranch::Primary)),
+            Status::Settled => {
+                assert!(paid, "Trigger has no payout to settle");
+                self.transition(&mut trigger, Status::Settled);
+                None
+            }
+            Status::Refunded => {
+                assert!(!paid, "Payout has already been initiated");
+                assert!(
+                    !Self::instalment_in_flight(&trigger),
+                    "An instalment is still being signed"
+                );
+                self.transition(&mut trigger, Status::Refunded);
+                self.release_coverage(&trigger, false);
+                self.release_exposure(&trigger);
+                self.unindex_expiry(&trigger);
+                if trigger.coverage.is_some() {
+                    return None;
+                }
+                let refundable = Self::refundable_escrow(&trigger);
+                let fee = self.refund_fee(&trigger).min(refundable);
+                Some(self.refund_escrow(&trigger, refundable - fee, fee))
+            }
+            _ => panic!("Invalid dispute outcome"),
+        }
+    }
+}
+
+// ============================================================================
+// Internal Methods
+// ============================================================================
+
+impl TriggerPay {
+    /// The single place trigger status changes. Rejects moves outside the
+    /// lifecycle, records the change and stores the trigger.
+    pub(crate) fn transition(&mut self, trigger: &mut Trigger, next: Status) {
+        assert!(
+            trigger.status.can_transition_to(&next),
+            "Illegal status transition from {:?} to {:?}",
+            trigger.status,
+            next
+        );
+
+        let initial_storage = env::storage_usage();
+        let from = trigger.status.clone();
+        self.update_escrow(trigger, |trigger| trigger.status = next.clone());
+        self.triggers.insert(&trigger.id, trigger);
+        self.charge_trigger_storage(trigger, initial_storage, false);
+        let resolved = next != Status::Active;
+        self.record_status_change(trigger, from, next);
+
+        // A pending payout address change does not outlive resolution
+        if resolved {
+            self.drop_payout_change(trigger, "resolved");
+        }
+    }
+
+    /// Activate a trigger being created. It is funded up front, so it is
+    /// first stored Active; `record_status_change` then opens its history
+    /// with the move out of Pending.
+    pub(crate) fn activate_new_trigger(&mut self, trigger: &mut Trigger) {
+        assert!(trigger.status == Status::Pending, "Trigger is already active");
+        self.update_escrow(trigger, |trigger| trigger.status = Status::Active);
+    }
+
+    /// Append a status change to the trigger's status history and event log
+    pub(crate) fn record_status_change(&mut self, trigger: &Trigger, from: Status, to: Status) {
+        let initial_storage = env::storage_usage();
+        let mut history = self.status_history.get(&trigger.id).unwrap_or_else(|| {
+            Vector::new(StorageKey::StatusHistoryInner {
+                trigger_id: trigger.id.clone(),
+            })
+        });
+        history.push(&StatusChange {
+            from: from.clone(),
+            to: to.clone(),
+            at: env::block_timestamp(),
+            actor: env::predecessor_account_id().to_string(),
+        });
+        self.status_history.insert(&trigger.id, &history);
+        self.charge_trigger_storage(trigger, initial_storage, false);
+
+        self.record_event(trigger, TriggerEvent::StatusChanged { from, to });
+    }
+
+    /// Move a trigger whose condition resolved into PendingPayout: book the
+    /// payout against the pool and exposure caps, then request the signature
+    pub(crate) fn begin_payout(&mut self, mut trigger: Trigger, branch: PayoutBranch) -> Promise {
+        trigger.fired_branch = Some(branch);
+        self.transition(&mut trigger, Status::PendingPayout);
+        self.release_coverage(&trigger, true);
+        self.release_exposure(&trigger);
+        self.unindex_expiry(&trigger);
+        self.request_payout(&trigger)
+    }
+
+    /// Sign the fired branch's payout; the callback records the outcome
+    fn request_payout(&mut self, trigger: &Trigger) -> Promise {
+        let payout = match trigger.fired_branch {
+            Some(PayoutBranch::Fallback) => trigger
+                .fallback_payout
+                .as_ref()
+                .expect("Fallback payout missing"),
+            _ => &trigger.payout,
+        };
+        self.record_event(trigger, TriggerEvent::PayoutRequested { instalment: None });
+        self.initiate_payout(trigger, payout, None).then(
+            Self::ext(env::current_account_id())
+                .with_static_gas(GAS_FOR_PAYOUT_CALLBACK)
+                .on_payout_signed(trigger.id.clone()),
+        )
+    }
+
+    /// Funder, traveller, beneficiary or contract owner
+    fn assert_trigger_party(&self, trigger: &Trigger) {
+        let caller = env::predecessor_account_id();
+        assert!(
+            caller == trigger.owner
+                || trigger.traveller.as_ref() == Some(&caller)
+                || trigger.beneficiary.as_ref() == Some(&caller)
+                || caller == self.owner,
+            "Only a trigger party or contract owner can call this method"
+        );
+    }
+}
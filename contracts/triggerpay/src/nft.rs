@@ -0,0 +1,465 @@
+use std::collections::HashMap;
+
+use near_sdk::borsh::{BorshDeserialize, BorshSerialize};
+use near_sdk::collections::Vector;
+use near_sdk::serde::{Deserialize, Serialize};
+use near_sdk::serde_json::{json, Value};
+use near_sdk::{assert_one_yocto, env, AccountId, Gas, NearToken, Promise, PromiseOrValue};
+use schemars::JsonSchema;
+
+use crate::{
+    ConditionType, StorageKey, Trigger, TriggerEvent, TriggerId, TriggerPay, TriggerPayExt,
+};
+
+// ============================================================================
+// Constants
+// ============================================================================
+
+const GAS_FOR_NFT_ON_TRANSFER: Gas = Gas::from_tgas(35);
+const GAS_FOR_NFT_RESOLVE_TRANSFER: Gas = Gas::from_tgas(15);
+const GAS_FOR_NFT_ON_APPROVE: Gas = Gas::from_tgas(20);
+const NFT_METADATA_SPEC: &str = "nft-1.0.0";
+
+// ============================================================================
+// Types
+// ============================================================================
+
+/// NEP-177 contract metadata for trigger tokens
+#[derive(Serialize, Deserialize, Clone, Debug, JsonSchema)]
+#[serde(crate = "near_sdk::serde")]
+pub struct NFTContractMetadata {
+    pub spec: String,
+    pub name: String,
+    pub symbol: String,
+    pub icon: Option<String>,
+    pub base_uri: Option<String>,
+    pub reference: Option<String>,
+    pub reference_hash: Option<String>,
+}
+
+/// NEP-177 token metadata summarising the trigger
+#[derive(Serialize, Deserialize, Clone, Debug, JsonSchema)]
+#[serde(crate = "near_sdk::serde")]
+pub struct TokenMetadata {
+    pub title: Option<String>,
+    pub description: Option<String>,
+    pub media: Option<String>,
+    pub media_hash: Option<String>,
+    pub copies: Option<u64>,
+    pub issued_at: Option<String>,  // Milliseconds since epoch
+    pub expires_at: Option<String>, // Milliseconds since epoch
+    pub starts_at: Option<String>,
+    pub updated_at: Option<String>,
+    pub extra: Option<String>, // JSON: condition, payout and status
+    pub reference: Option<String>,
+    pub reference_hash: Option<String>,
+}
+
+/// NEP-171 token; the token ID is the trigger ID
+#[derive(Serialize, Deserialize, Clone, Debug, JsonSchema)]
+#[serde(crate = "near_sdk::serde")]
+pub struct Token {
+    pub token_id: String,
+    pub owner_id: String, // AccountId as string for JsonSchema compatibility
+    pub metadata: Option<TokenMetadata>,
+    pub approved_account_ids: Option<HashMap<String, u64>>,
+}
+
+/// NEP-178 approvals of one trigger token
+#[derive(BorshDeserialize, BorshSerialize, Default)]
+#[borsh(crate = "near_sdk::borsh")]
+pub struct TokenApprovals {
+    next_approval_id: u64,
+    accounts: HashMap<AccountId, u64>,
+}
+
+// ============================================================================
+// NEP-171 Trigger Tokens
+// ============================================================================
+
+#[near_sdk::near]
+impl TriggerPay {
+    /// Transfer a trigger to a new owner, who takes over every owner-only
+    /// action. Requires exactly 1 yoctoNEAR and a receiver registered
+    /// through `storage_deposit`.
+    #[payable]
+    pub fn nft_transfer(
+        &mut self,
+        receiver_id: AccountId,
+        token_id: TriggerId,
+        approval_id: Option<u64>,
+        memo: Option<String>,
+    ) {
+        assert_one_yocto();
+        let sender_id = env::predecessor_account_id();
+        self.internal_transfer_trigger(&sender_id, &receiver_id, &token_id, approval_id, memo);
+    }
+
+    /// Transfer a trigger and notify the receiver, which may return it
+    #[payable]
+    pub fn nft_transfer_call(
+        &mut self,
+        receiver_id: AccountId,
+        token_id: TriggerId,
+        approval_id: Option<u64>,
+        memo: Option<String>,
+        msg: String,
+    ) -> PromiseOrValue<bool> {
+        assert_one_yocto();
+        let sender_id = env::predecessor_account_id();
+        let (previous_owner_id, approvals) =
+            self.internal_transfer_trigger(&sender_id, &receiver_id, &token_id, approval_id, memo);
+
+        Promise::new(receiver_id.clone())
+            .function_call(
+                "nft_on_transfer".to_string(),
+                json!({
+                    "sender_id": sender_id,
+                    "previous_owner_id": previous_owner_id,
+                    "token_id": token_id,
+                    "msg": msg,
+                })
+                .to_string()
+                .into_bytes(),
+                NearToken::from_yoctonear(0),
+                GAS_FOR_NFT_ON_TRANSFER,
+            )
+            .then(
+                Self::ext(env::current_account_id())
+                    .with_static_gas(GAS_FOR_NFT_RESOLVE_TRANSFER)
+                    .nft_resolve_transfer(previous_owner_id, receiver_id, token_id, Some(approvals)),
+            )
+            .into()
+    }
+
+    /// Return the trigger to its previous owner if the receiver asked to, or
+    /// its call failed. Returns whether the transfer stands.
+    #[private]
+    pub fn nft_resolve_transfer(
+        &mut self,
+        previous_owner_id: AccountId,
+        receiver_id: AccountId,
+        token_id: TriggerId,
+        approved_account_ids: Option<HashMap<AccountId, u64>>,
+    ) -> bool {
+        // A failed or malformed receiver call returns the token
+        let must_return = env::promise_result_checked(0, 16)
+            .ok()
+            .and_then(|data| near_sdk::serde_json::from_slice::<bool>(&data).ok())
+            .unwrap_or(true);
+        if !must_return {
+            return true;
+        }
+
+        // The receiver may already have passed the token on
+        let Some(mut trigger) = self.triggers.get(&token_id) else {
+            return true;
+        };
+        if trigger.owner != receiver_id {
+            return true;
+        }
+
+        self.move_trigger(&mut trigger, &previous_owner_id, None, false);
+        if let Some(accounts) = approved_account_ids {
+            let initial_storage = env::storage_usage();
+            let mut approvals = self.nft_approvals.get(&token_id).unwrap_or_default();
+            approvals.accounts = accounts;
+            self.nft_approvals.insert(&token_id, &approvals);
+            self.charge_trigger_storage(&trigger, initial_storage, false);
+        }
+        false
+    }
+
+    pub fn nft_token(&self, token_id: TriggerId) -> Option<Token> {
+        self.triggers.get(&token_id).map(|trigger| self.trigger_token(&trigger))
+    }
+
+    pub fn nft_metadata(&self) -> NFTContractMetadata {
+        NFTContractMetadata {
+            spec: NFT_METADATA_SPEC.to_string(),
+            name: "TriggerPay Policies".to_string(),
+            symbol: "TPAY".to_string(),
+            icon: None,
+            base_uri: None,
+            reference: None,
+            reference_hash: None,
+        }
+    }
+
+    // ========================================================================
+    // NEP-178 Approvals
+    // ========================================================================
+
+    /// Let `account_id` transfer the trigger, e.g. a marketplace listing it.
+    /// With `msg`, the account is notified through `nft_on_approve`.
+    #[payable]
+    pub fn nft_approve(
+        &mut self,
+        token_id: TriggerId,
+        account_id: AccountId,
+        msg: Option<String>,
+    ) -> Option<Promise> {
+        assert!(
+            env::attached_deposit().as_yoctonear() >= 1,
+            "Requires attached deposit of at least 1 yoctoNEAR"
+        );
+        let trigger = self.triggers.get(&token_id).expect("Trigger not found");
+        assert!(
+            env::predecessor_account_id() == trigger.owner,
+            "Only trigger owner can approve"
+        );
+
+        let initial_storage = env::storage_usage();
+        let mut approvals = self.nft_approvals.get(&token_id).unwrap_or_default();
+        let approval_id = approvals.next_approval_id;
+        approvals.accounts.insert(account_id.clone(), approval_id);
+        approvals.next_approval_id += 1;
+        self.nft_approvals.insert(&token_id, &approvals);
+        self.charge_trigger_storage(&trigger, initial_storage, true);
+
+        let msg = msg?;
+        Some(
+            Promise::new(account_id).function_call(
+                "nft_on_approve".to_string(),
+                json!({
+                    "token_id": token_id,
+                    "owner_id": trigger.owner,
+                    "approval_id": approval_id,
+                    "msg": msg,
+                })
+                .to_string()
+                .into_bytes(),
+                NearToken::from_yoctonear(0),
+                GAS_FOR_NFT_ON_APPROVE,
+            ),
+        )
+    }
+
+    #[payable]
+    pub fn nft_revoke(&mut self, token_id: TriggerId, account_id: AccountId) {
+        assert_one_yocto();
+        let trigger = self.triggers.get(&token_id).expect("Trigger not found");
+        assert!(
+            env::predecessor_account_id() == trigger.owner,
+            "Only trigger owner can revoke"
+        );
+        let initial_storage = env::storage_usage();
+        if let Some(mut approvals) = self.nft_approvals.get(&token_id) {
+            approvals.accounts.remove(&account_id);
+            self.nft_approvals.insert(&token_id, &approvals);
+        }
+        self.charge_trigger_storage(&trigger, initial_storage, false);
+    }
+
+    #[payable]
+    pub fn nft_revoke_all(&mut self, token_id: TriggerId) {
+        assert_one_yocto();
+        let trigger = self.triggers.get(&token_id).expect("Trigger not found");
+        assert!(
+            env::predecessor_account_id() == trigger.owner,
+            "Only trigger owner can revoke"
+        );
+        let initial_storage = env::storage_usage();
+        if let Some(mut approvals) = self.nft_approvals.get(&token_id) {
+            approvals.accounts.clear();
+            self.nft_approvals.insert(&token_id, &approvals);
+        }
+        self.charge_trigger_storage(&trigger, initial_storage, false);
+    }
+
+    pub fn nft_is_approved(
+        &self,
+        token_id: TriggerId,
+        approved_account_id: AccountId,
+        approval_id: Option<u64>,
+    ) -> bool {
+        let Some(approvals) = self.nft_approvals.get(&token_id) else {
+            return false;
+        };
+        match approvals.accounts.get(&approved_account_id) {
+            Some(id) => approval_id.is_none_or(|expected| expected == *id),
+            None => false,
+        }
+    }
+}
+
+// ============================================================================
+// Internal Methods
+// ============================================================================
+
+impl TriggerPay {
+    /// Add a trigger to an account's list, creating the list on first use
+    pub(crate) fn add_user_trigger(&mut self, account_id: &AccountId, trigger_id: &TriggerId) {
+        let mut trigger_ids = self.user_triggers.get(account_id).unwrap_or_else(|| {
+            Vector::new(StorageKey::UserTriggersInner {
+                account_hash: env::sha256(account_id.as_bytes()),
+            })
+        });
+        trigger_ids.push(trigger_id);
+        self.user_triggers.insert(account_id, &trigger_ids);
+    }
+
+    pub(crate) fn emit_nft_mint(trigger: &Trigger) {
+        emit_nft_event(
+            "nft_mint",
+            json!({ "owner_id": trigger.owner, "token_ids": [trigger.id] }),
+        );
+    }
+
+    /// Check the sender may move the trigger, then give it to the receiver.
+    /// Returns the previous owner and the approvals the transfer cleared.
+    fn internal_transfer_trigger(
+        &mut self,
+        sender_id: &AccountId,
+        receiver_id: &AccountId,
+        token_id: &TriggerId,
+        approval_id: Option<u64>,
+        memo: Option<String>,
+    ) -> (AccountId, HashMap<AccountId, u64>) {
+        let mut trigger = self.triggers.get(token_id).expect("Trigger not found");
+        let previous_owner_id = trigger.owner.clone();
+        if *sender_id != previous_owner_id {
+            assert!(
+                self.nft_is_approved(token_id.clone(), sender_id.clone(), approval_id),
+                "Sender is not approved to transfer this trigger"
+            );
+        }
+        assert!(*receiver_id != previous_owner_id, "Sender and receiver must differ");
+        assert!(
+            self.storage_accounts.get(receiver_id).is_some(),
+            "Receiver is not registered for storage"
+        );
+
+        let initial_storage = env::storage_usage();
+        let approvals = self
+            .nft_approvals
+            .remove(token_id)
+            .map(|approvals| approvals.accounts)
+            .unwrap_or_default();
+        self.charge_trigger_storage(&trigger, initial_storage, false);
+        self.move_trigger(&mut trigger, receiver_id, memo, true);
+        (previous_owner_id, approvals)
+    }
+
+    /// Reassign the trigger and its place in `user_triggers`. The storage the
+    /// trigger holds moves to the new owner, who must be able to cover it
+    /// unless the trigger is being returned.
+    fn move_trigger(
+        &mut self,
+        trigger: &mut Trigger,
+        receiver_id: &AccountId,
+        memo: Option<String>,
+        require_balance: bool,
+    ) {
+        let previous_owner_id = std::mem::replace(&mut trigger.owner, receiver_id.clone());
+        self.transfer_trigger_storage(trigger, &previous_owner_id);
+
+        let initial_storage = env::storage_usage();
+        if let Some(mut trigger_ids) = self.user_triggers.get(&previous_owner_id) {
+            if let Some(index) = trigger_ids.iter().position(|id| id == trigger.id) {
+                trigger_ids.swap_remove(index as u64);
+                self.user_triggers.insert(&previous_owner_id, &trigger_ids);
+            }
+        }
+        self.add_user_trigger(receiver_id, &trigger.id);
+        self.triggers.insert(&trigger.id, trigger);
+        self.charge_trigger_storage(trigger, initial_storage, require_balance);
+        self.record_event(
+            trigger,
+            TriggerEvent::Transferred {
+                from: previous_owner_id.to_string(),
+                to: receiver_id.to_string(),
+            },
+        );
+
+        // The previous owner's proposal must not redirect the new owner's payout
+        self.drop_payout_change(trigger, "transferred");
+
+        let mut data = json!({
+            "old_owner_id": previous_owner_id,
+            "new_owner_id": receiver_id,
+            "token_ids": [trigger.id],
+        });
+        if let Some(memo) = memo {
+            data["memo"] = Value::String(memo);
+        }
+        emit_nft_event("nft_transfer", data);
+    }
+
+    fn trigger_token(&self, trigger: &Trigger) -> Token {
+        let approvals = self.nft_approvals.get(&trigger.id).unwrap_or_default();
+        Token {
+            token_id: trigger.id.clone(),
+            owner_id: trigger.owner.to_string(),
+            metadata: Some(Self::trigger_metadata(trigger)),
+            approved_account_ids: Some(
+                approvals
+                    .accounts
+                    .into_iter()
+                    .map(|(account_id, id)| (account_id.to_string(), id))
+                    .collect(),
+            ),
+        }
+    }
+
+    fn trigger_metadata(trigger: &Trigger) -> TokenMetadata {
+        let condition = &trigger.condition;
+        let subject = match condition.condition_type {
+            ConditionType::FlightCancellation => {
+                format!("Flight {} on {}", condition.flight_number, condition.flight_date)
+            }
+            _ => format!("{:?} condition", condition.condition_type),
+        };
+        let payout = &trigger.payout;
+        let fallback = trigger
+            .fallback_payout
+            .as_ref()
+            .map(|f| format!(", or {} {} on {:?} if the deadline is missed", f.amount, f.token, f.chain))
+            .unwrap_or_default();
+        let description = format!(
+            "{}: pays {} {} on {:?}{}. Status: {:?}",
+            subject, payout.amount, payout.token, payout.chain, fallback, trigger.status
+        );
+        let mut extra = json!({
+            "condition_type": condition.condition_type,
+            "flight_number": condition.flight_number,
+            "flight_date": condition.flight_date,
+            "route": condition.route,
+            "payout_amount": payout.amount,
+            "payout_token": payout.token,
+            "payout_chain": payout.chain,
+            "status": trigger.status,
+        });
+        if let Some(fallback) = &trigger.fallback_payout {
+            extra["fallback_amount"] = json!(fallback.amount);
+            extra["fallback_token"] = json!(fallback.token);
+            extra["fallback_chain"] = json!(fallback.chain);
+        }
+
+        TokenMetadata {
+            title: Some(format!("TriggerPay {}", trigger.id)),
+            description: Some(description),
+            media: None,
+            media_hash: None,
+            copies: Some(1),
+            issued_at: Some((trigger.created_at / 1_000_000).to_string()),
+            expires_at: Some((trigger.expires_at / 1_000_000).to_string()),
+            starts_at: None,
+            updated_at: None,
+            extra: Some(extra.to_string()),
+            reference: None,
+            reference_hash: None,
+        }
+    }
+}
+
+/// Log a NEP-297 event for trigger tokens
+fn emit_nft_event(event: &str, data: Value) {
+    let event = json!({
+        "standard": "nep171",
+        "version": "1.0.0",
+        "event": event,
+        "data": [data],
+    });
+    env::log_str(&format!("EVENT_JSON:{}", event));
+}
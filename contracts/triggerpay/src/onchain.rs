@@ -0,0 +1,229 @@
+use near_sdk::borsh::{BorshDeserialize, BorshSerialize};
+use near_sdk::serde::{Deserialize, Serialize};
+use near_sdk::serde_json::Value;
+use near_sdk::{env, AccountId, Gas, NearToken, Promise, PromiseError};
+use schemars::JsonSchema;
+
+use crate::http::{compare_numeric, compare_values, is_numeric_op};
+use crate::{
+    CompareOp, ConditionType, Status, TriggerEvent, TriggerId, TriggerPay, TriggerPayExt, GAS_FOR_SIGN,
+};
+
+// ============================================================================
+// Constants
+// ============================================================================
+
+const GAS_FOR_VIEW: Gas = Gas::from_tgas(10);
+const GAS_FOR_VIEW_CALLBACK: Gas = Gas::from_tgas(GAS_FOR_SIGN.as_tgas() + 15);
+
+// ============================================================================
+// Types
+// ============================================================================
+
+/// A predicate over the result of a view call on another NEAR contract,
+/// e.g. `ft_balance_of` dropping below a level or a DAO proposal's status
+#[derive(BorshDeserialize, BorshSerialize, Serialize, Deserialize, Clone, Debug, JsonSchema)]
+#[borsh(crate = "near_sdk::borsh")]
+#[serde(crate = "near_sdk::serde")]
+pub struct OnChainCondition {
+    pub contract_id: String, // AccountId as string for JsonSchema compatibility
+    pub method: String,      // View method: "ft_balance_of", "get_proposal"
+    pub args: String,        // JSON-encoded arguments: "{\"account_id\":\"alice.near\"}"
+    pub result_path: String, // Dot path into the result: "" for the whole value, "status"
+    pub operator: CompareOp,
+    pub expected: String,
+}
+
+// ============================================================================
+// View Allowlist
+// ============================================================================
+
+#[near_sdk::near]
+impl TriggerPay {
+    /// Allow OnChainView conditions to call a contract's view method (only
+    /// owner can call). This contract and the treasury's staking pools are
+    /// never allowed.
+    pub fn add_allowed_view(&mut self, contract_id: AccountId, method: String) {
+        self.assert_owner();
+        let method = method.trim().to_string();
+        assert!(!method.is_empty(), "View method is required");
+        self.assert_view_target(&contract_id);
+        self.allowed_views.insert(&(contract_id.clone(), method.clone()));
+
+        env::log_str(&format!("View allowed: {}.{}", contract_id, method));
+    }
+
+    /// Stop new OnChainView conditions from calling a view method (only owner
+    /// can call)
+    pub fn remove_allowed_view(&mut self, contract_id: AccountId, method: String) {
+        self.assert_owner();
+        let method = method.trim().to_string();
+        assert!(
+            self.allowed_views.remove(&(contract_id.clone(), method.clone())),
+            "View is not allowed"
+        );
+
+        env::log_str(&format!("View removed: {}.{}", contract_id, method));
+    }
+
+    /// Get all contract and view method pairs OnChainView conditions may call
+    pub fn get_allowed_views(&self) -> Vec<(AccountId, String)> {
+        self.allowed_views.to_vec()
+    }
+}
+
+// ============================================================================
+// Permissionless Checks
+// ============================================================================
+
+#[near_sdk::near]
+impl TriggerPay {
+    /// Call the condition's view method and evaluate the result in a callback.
+    /// Anyone can call this; no TEE attestation is involved.
+    pub fn check_onchain_condition(&mut self, trigger_id: TriggerId, leaf_index: Option<u32>) -> Promise {
+        let trigger = self.triggers.get(&trigger_id).expect("Trigger not found");
+        assert!(
+            trigger.status == Status::Active,
+            "Trigger is no longer active"
+        );
+
+        assert!(env::block_timestamp() <= trigger.expires_at, "Trigger has expired");
+
+        let (leaf, _, _) = Self::resolve_leaf(&trigger, leaf_index);
+        assert!(
+            leaf.condition_type == ConditionType::OnChainView,
+            "Condition is not an on-chain view"
+        );
+        let onchain = leaf.onchain.expect("On-chain condition missing");
+
+        // A pool may have been whitelisted after the trigger was created
+        let contract_id: AccountId = onchain.contract_id.parse().expect("Invalid contract ID");
+        self.assert_view_target(&contract_id);
+        Promise::new(contract_id)
+            .function_call(
+                onchain.method,
+                onchain.args.into_bytes(),
+                NearToken::from_yoctonear(0),
+                GAS_FOR_VIEW,
+            )
+            .then(
+                Self::ext(env::current_account_id())
+                    .with_static_gas(GAS_FOR_VIEW_CALLBACK)
+                    .on_onchain_view(trigger_id, leaf_index),
+            )
+    }
+
+    /// Callback from the view call made by `check_onchain_condition`
+    #[private]
+    pub fn on_onchain_view(
+        &mut self,
+        trigger_id: TriggerId,
+        leaf_index: Option<u32>,
+        #[callback_result] result: Result<Value, PromiseError>,
+    ) -> Option<Promise> {
+        let trigger = self.triggers.get(&trigger_id).expect("Trigger not found");
+
+        // The trigger may have resolved while the view call was in flight
+        if trigger.status != Status::Active {
+            env::log_str(&format!("On-chain check for {} skipped: no longer active", trigger_id));
+            return None;
+        }
+
+        let Ok(value) = result else {
+            env::log_str(&format!("On-chain check for {} failed: view call error", trigger_id));
+            return None;
+        };
+
+        let (leaf, _, index) = Self::resolve_leaf(&trigger, leaf_index);
+        let onchain = leaf.onchain.expect("On-chain condition missing");
+
+        let Some(actual) = extract_path(&value, &onchain.result_path) else {
+            env::log_str(&format!(
+                "On-chain check for {} failed: no value at '{}'",
+                trigger_id, onchain.result_path
+            ));
+            return None;
+        };
+        let Some(met) = compare_values(&actual, &onchain.operator, &onchain.expected) else {
+            env::log_str(&format!(
+                "On-chain check for {} failed: '{}' is not numeric",
+                trigger_id, actual
+            ));
+            return None;
+        };
+
+        env::log_str(&format!(
+            "On-chain check for {}: {}.{} = {}, condition_met={}",
+            trigger_id, onchain.contract_id, onchain.method, actual, met
+        ));
+
+        self.record_event(
+            &trigger,
+            TriggerEvent::Attested {
+                condition_met: met,
+                leaf_index,
+            },
+        );
+        self.apply_condition_result(trigger, index, met)
+    }
+}
+
+// ============================================================================
+// Internal Methods
+// ============================================================================
+
+impl TriggerPay {
+    pub(crate) fn validate_onchain_condition(&self, condition: &OnChainCondition) {
+        let contract_id: AccountId = condition.contract_id.parse().expect("Invalid contract ID");
+        assert!(!condition.method.is_empty(), "View method is required");
+        self.assert_view_target(&contract_id);
+        assert!(
+            self.allowed_views.contains(&(contract_id, condition.method.clone())),
+            "View is not on the allowlist"
+        );
+        assert!(
+            matches!(
+                near_sdk::serde_json::from_str::<Value>(&condition.args),
+                Ok(Value::Object(_))
+            ),
+            "View args must be a JSON object"
+        );
+        if is_numeric_op(&condition.operator) {
+            assert!(
+                compare_numeric(&condition.expected, &condition.expected).is_some(),
+                "Expected value must be numeric for ordered comparisons"
+            );
+        }
+    }
+
+    /// The contract never calls itself or the pools holding its stake
+    fn assert_view_target(&self, contract_id: &AccountId) {
+        assert!(
+            *contract_id != env::current_account_id(),
+            "View calls to this contract are not allowed"
+        );
+        let staking_pool = self.treasury.policy.staking_pool.as_deref();
+        assert!(
+            !self.staking_pools.contains(contract_id) && staking_pool != Some(contract_id.as_str()),
+            "View calls to staking pools are not allowed"
+        );
+    }
+}
+
+/// Follow a dot path ("proposal.status", "items.0") and render the value as text.
+/// Strings are unquoted so "Approved" compares equal to an expected `Approved`.
+fn extract_path(value: &Value, path: &str) -> Option<String> {
+    let mut current = value;
+    for segment in path.split('.').filter(|s| !s.is_empty()) {
+        current = match current {
+            Value::Object(map) => map.get(segment)?,
+            Value::Array(items) => items.get(segment.parse::<usize>().ok()?)?,
+            _ => return None,
+        };
+    }
+
+    Some(match current {
+        Value::String(s) => s.clone(),
+        other => other.to_string(),
+    })
+}
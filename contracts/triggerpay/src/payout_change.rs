@@ -0,0 +1,245 @@
+use near_sdk::borsh::{BorshDeserialize, BorshSerialize};
+use near_sdk::serde::{Deserialize, Serialize};
+use near_sdk::serde_json::{json, Value};
+use near_sdk::env;
+use schemars::JsonSchema;
+
+use crate::flight::NANOS_PER_DAY;
+use crate::{Status, Trigger, TriggerEvent, TriggerId, TriggerPay, TriggerPayExt};
+
+// ============================================================================
+// Constants
+// ============================================================================
+
+pub const DEFAULT_PAYOUT_CHANGE_DELAY: u64 = 2 * NANOS_PER_DAY;
+const MIN_PAYOUT_CHANGE_DELAY: u64 = NANOS_PER_DAY / 24; // 1 hour
+
+// ============================================================================
+// Types
+// ============================================================================
+
+/// A proposed payout address, applicable once `executable_at` has passed
+#[derive(BorshDeserialize, BorshSerialize, Serialize, Deserialize, Clone, Debug, JsonSchema)]
+#[borsh(crate = "near_sdk::borsh")]
+#[serde(crate = "near_sdk::serde")]
+pub struct PayoutChange {
+    pub new_address: String,
+    pub proposed_at: u64,   // Nanoseconds
+    pub executable_at: u64, // Nanoseconds
+}
+
+// ============================================================================
+// Payout Address Changes
+// ============================================================================
+
+#[near_sdk::near]
+impl TriggerPay {
+    /// Set how long a payout address change waits before it can be applied
+    /// (only owner can call)
+    pub fn set_payout_change_delay(&mut self, delay: u64) {
+        self.assert_owner();
+        assert!(delay >= MIN_PAYOUT_CHANGE_DELAY, "Delay must be at least one hour");
+        self.payout_change_delay = delay;
+
+        env::log_str(&format!("Payout change delay set: {}", delay));
+    }
+
+    pub fn get_payout_change_delay(&self) -> u64 {
+        self.payout_change_delay
+    }
+
+    pub fn get_payout_change(&self, trigger_id: TriggerId) -> Option<PayoutChange> {
+        self.payout_changes.get(&trigger_id)
+    }
+
+    /// Propose a new payout address for an active trigger. It can be applied
+    /// after the delay; a new proposal restarts the delay.
+    pub fn propose_payout_change(&mut self, trigger_id: TriggerId, new_address: String) -> PayoutChange {
+        let trigger = self.triggers.get(&trigger_id).expect("Trigger not found");
+        assert!(
+            env::predecessor_account_id() == *Self::beneficiary_of(&trigger),
+            "Only the trigger beneficiary can change the payout"
+        );
+        assert!(trigger.status == Status::Active, "Trigger is not active");
+        Self::validate_payout_address(&new_address);
+        assert!(
+            !new_address.eq_ignore_ascii_case(&trigger.payout.address),
+            "Address is already the payout address"
+        );
+
+        let now = env::block_timestamp();
+        let change = PayoutChange {
+            new_address,
+            proposed_at: now,
+            executable_at: now + self.payout_change_delay,
+        };
+        let initial_storage = env::storage_usage();
+        self.payout_changes.insert(&trigger_id, &change);
+        self.charge_trigger_storage(&trigger, initial_storage, true);
+        self.record_event(
+            &trigger,
+            TriggerEvent::PayoutChangeProposed {
+                new_address: change.new_address.clone(),
+                executable_at: change.executable_at,
+            },
+        );
+
+        emit_payout_change_event(
+            "payout_change_proposed",
+            json!({
+                "trigger_id": trigger_id,
+                "old_address": trigger.payout.address,
+                "new_address": change.new_address,
+                "executable_at": change.executable_at.to_string(),
+            }),
+        );
+        change
+    }
+
+    /// Apply a proposed address once its delay has passed. A fallback payout
+    /// to the same address moves with it.
+    pub fn apply_payout_change(&mut self, trigger_id: TriggerId) {
+        let mut trigger = self.triggers.get(&trigger_id).expect("Trigger not found");
+        assert!(
+            env::predecessor_account_id() == *Self::beneficiary_of(&trigger),
+            "Only the trigger beneficiary can change the payout"
+        );
+        assert!(trigger.status == Status::Active, "Trigger is not active");
+        let change = self
+            .payout_changes
+            .get(&trigger_id)
+            .expect("No pending payout change");
+        assert!(
+            env::block_timestamp() >= change.executable_at,
+            "Payout change is still time-locked"
+        );
+
+        let old_address = std::mem::replace(&mut trigger.payout.address, change.new_address.clone());
+        if let Some(fallback) = trigger.fallback_payout.as_mut() {
+            if fallback.address.eq_ignore_ascii_case(&old_address) {
+                fallback.address = change.new_address.clone();
+            }
+        }
+        self.payout_changes.remove(&trigger_id);
+        self.triggers.insert(&trigger_id, &trigger);
+        self.record_event(
+            &trigger,
+            TriggerEvent::PayoutChangeApplied {
+                old_address: old_address.clone(),
+                new_address: change.new_address.clone(),
+            },
+        );
+
+        emit_payout_change_event(
+            "payout_change_applied",
+            json!({
+                "trigger_id": trigger_id,
+                "old_address": old_address,
+                "new_address": change.new_address,
+            }),
+        );
+    }
+
+    /// Cancel a pending change. The beneficiary can always cancel; the
+    /// current payout address vetoes by signing (EIP-191) the message from
+    /// `payout_change_veto_message`.
+    pub fn cancel_payout_change(&mut self, trigger_id: TriggerId, signature: Option<String>) {
+        let trigger = self.triggers.get(&trigger_id).expect("Trigger not found");
+        let change = self
+            .payout_changes
+            .get(&trigger_id)
+            .expect("No pending payout change");
+        let reason = match signature {
+            Some(signature) => {
+                let message = Self::veto_message(&trigger_id, &change);
+                let signer = recover_eth_address(&message, &signature);
+                assert!(
+                    signer.is_some_and(|signer| signer.eq_ignore_ascii_case(&trigger.payout.address)),
+                    "Signature is not from the payout address"
+                );
+                "vetoed"
+            }
+            None => {
+                assert!(
+                    env::predecessor_account_id() == *Self::beneficiary_of(&trigger),
+                    "Only the trigger beneficiary can cancel without a signature"
+                );
+                "cancelled"
+            }
+        };
+
+        self.drop_payout_change(&trigger, reason);
+    }
+
+    /// Message the current payout address signs to veto the pending change
+    pub fn payout_change_veto_message(&self, trigger_id: TriggerId) -> String {
+        let change = self
+            .payout_changes
+            .get(&trigger_id)
+            .expect("No pending payout change");
+        Self::veto_message(&trigger_id, &change)
+    }
+}
+
+// ============================================================================
+// Internal Methods
+// ============================================================================
+
+impl TriggerPay {
+    /// Discard a pending change, e.g. once the trigger resolves or changes hands
+    pub(crate) fn drop_payout_change(&mut self, trigger: &Trigger, reason: &str) {
+        let initial_storage = env::storage_usage();
+        if self.payout_changes.remove(&trigger.id).is_none() {
+            return;
+        }
+        self.charge_trigger_storage(trigger, initial_storage, false);
+        self.record_event(trigger, TriggerEvent::PayoutChangeCancelled);
+
+        emit_payout_change_event(
+            "payout_change_cancelled",
+            json!({ "trigger_id": trigger.id, "reason": reason }),
+        );
+    }
+
+    /// Names the proposal time, so a veto cannot be replayed against a later
+    /// proposal of the same address
+    fn veto_message(trigger_id: &str, change: &PayoutChange) -> String {
+        format!(
+            "Cancel TriggerPay payout change for {} to {} proposed at {}",
+            trigger_id,
+            change.new_address.to_ascii_lowercase(),
+            change.proposed_at
+        )
+    }
+}
+
+/// Recover the signer of an EIP-191 personal message from a 65-byte hex
+/// signature, as a lowercase 0x address
+fn recover_eth_address(message: &str, signature: &str) -> Option<String> {
+    let bytes = hex::decode(signature.trim_start_matches("0x")).ok()?;
+    if bytes.len() != 65 {
+        return None;
+    }
+    let v = match bytes[64] {
+        27 | 28 => bytes[64] - 27,
+        0 | 1 => bytes[64],
+        _ => return None,
+    };
+
+    let prefixed = format!("\x19Ethereum Signed Message:\n{}{}", message.len(), message);
+    let hash = env::keccak256_array(prefixed.as_bytes());
+    let public_key = env::ecrecover(&hash, &bytes[..64], v, true)?;
+    let address = env::keccak256_array(public_key);
+    Some(format!("0x{}", hex::encode(&address[12..])))
+}
+
+/// Log a NEP-297 event for payout address changes
+fn emit_payout_change_event(event: &str, data: Value) {
+    let event = json!({
+        "standard": "triggerpay",
+        "version": "1.0.0",
+        "event": event,
+        "data": [data],
+    });
+    env::log_str(&format!("EVENT_JSON:{}", event));
+}
@@ -0,0 +1,393 @@
+use near_sdk::borsh::{BorshDeserialize, BorshSerialize};
+use near_sdk::json_types::U128;
+use near_sdk::serde::{Deserialize, Serialize};
+use near_sdk::{env, AccountId, Gas, NearToken, Promise, PromiseError};
+use schemars::JsonSchema;
+
+use crate::escrow::Funding;
+use crate::{Balance, Condition, ConditionType, Payout, Trigger, TriggerId, TriggerPay, TriggerPayExt};
+
+// ============================================================================
+// Constants
+// ============================================================================
+
+const MAX_BPS: u16 = 10_000;
+const DEFAULT_MAX_UTILISATION_BPS: u16 = 8_000; // 80%
+const NEAR_DECIMALS: u8 = 24;
+const GAS_FOR_FT_TRANSFER: Gas = Gas::from_tgas(10);
+const GAS_FOR_WITHDRAW_CALLBACK: Gas = Gas::from_tgas(10);
+
+// Generated code trips lints we don't control
+#[allow(clippy::manual_div_ceil, clippy::assign_op_pattern)]
+mod u256 {
+    uint::construct_uint! {
+        /// 256-bit integer for share math, where amount * supply overflows u128
+        pub struct U256(4);
+    }
+}
+use u256::U256;
+
+/// `a * b / denominator`, rounding down
+pub(crate) fn mul_div(a: Balance, b: Balance, denominator: Balance) -> Balance {
+    (U256::from(a) * U256::from(b) / U256::from(denominator)).as_u128()
+}
+
+// ============================================================================
+// Types
+// ============================================================================
+
+/// Underwriting pool that pays covered triggers. Capital is held in NEAR or a
+/// single NEP-141 token; LPs own it pro rata to their share tokens.
+#[derive(BorshDeserialize, BorshSerialize, Clone, Debug)]
+#[borsh(crate = "near_sdk::borsh")]
+pub struct Pool {
+    pub token: Option<AccountId>, // None for NEAR
+    pub total_capital: Balance,   // LP deposits plus premiums, minus payouts and withdrawals
+    pub reserved: Balance,        // Coverage held for active covered triggers
+    pub total_shares: Balance,
+    pub max_utilisation_bps: u16, // Withdrawals may not push reserved / capital above this
+    pub share_decimals: u8,       // Matches the pool asset, since first deposits mint 1:1
+    pub premiums_earned: Balance,
+    pub claims_paid: Balance,
+}
+
+impl Default for Pool {
+    fn default() -> Self {
+        Self {
+            token: None,
+            total_capital: 0,
+            reserved: 0,
+            total_shares: 0,
+            max_utilisation_bps: DEFAULT_MAX_UTILISATION_BPS,
+            share_decimals: NEAR_DECIMALS,
+            premiums_earned: 0,
+            claims_paid: 0,
+        }
+    }
+}
+
+impl Pool {
+    pub fn free_capital(&self) -> Balance {
+        self.total_capital.saturating_sub(self.reserved)
+    }
+
+    /// Reserved capital as basis points of total capital
+    pub fn utilisation_bps(&self, total_capital: Balance) -> u128 {
+        if total_capital == 0 {
+            return if self.reserved == 0 { 0 } else { MAX_BPS as u128 };
+        }
+        mul_div(self.reserved, MAX_BPS as u128, total_capital)
+    }
+
+    fn shares_for(&self, amount: Balance) -> Balance {
+        if self.total_shares == 0 {
+            return amount;
+        }
+        // Outstanding shares with no capital behind them can't be priced;
+        // minting 1:1 would hand new capital to the old holders
+        assert!(self.total_capital > 0, "Pool capital is exhausted; deposits are closed");
+        mul_div(amount, self.total_shares, self.total_capital)
+    }
+
+    fn amount_for(&self, shares: Balance) -> Balance {
+        if self.total_shares == 0 {
+            0
+        } else {
+            mul_div(shares, self.total_capital, self.total_shares)
+        }
+    }
+
+    /// Pool asset backing one whole share, in the asset's smallest unit
+    pub fn nav_per_share(&self) -> Balance {
+        let one_share = 10u128.pow(self.share_decimals as u32);
+        if self.total_shares == 0 {
+            one_share
+        } else {
+            self.amount_for(one_share)
+        }
+    }
+
+    /// Claims paid as basis points of premiums earned
+    pub fn loss_ratio_bps(&self) -> u128 {
+        if self.premiums_earned == 0 {
+            0
+        } else {
+            mul_div(self.claims_paid, MAX_BPS as u128, self.premiums_earned)
+        }
+    }
+}
+
+#[derive(Serialize, Deserialize, Debug, JsonSchema)]
+#[serde(crate = "near_sdk::serde")]
+pub struct PoolView {
+    pub token: Option<String>,  // AccountId as string for JsonSchema compatibility
+    pub total_capital: String,  // Strings for JSON compatibility
+    pub total_exposure: String, // Coverage of all active covered triggers
+    pub free_capital: String,
+    pub total_shares: String,
+    pub nav_per_share: String, // Pool asset per whole share token
+    pub utilisation_bps: u32,
+    pub max_utilisation_bps: u16,
+    pub premiums_earned: String,
+    pub claims_paid: String,
+    pub loss_ratio_bps: u32, // Realised: claims paid / premiums earned
+}
+
+// ============================================================================
+// Liquidity Pool
+// ============================================================================
+
+#[near_sdk::near]
+impl TriggerPay {
+    /// Set the pool's asset and risk limits (only owner can call). The asset can
+    /// only change while the pool holds no liquidity. `share_decimals` should
+    /// match the token's decimals; NEAR pools use 24.
+    pub fn configure_pool(
+        &mut self,
+        token: Option<AccountId>,
+        share_decimals: u8,
+        max_utilisation_bps: u16,
+    ) {
+        self.assert_owner();
+        assert!(
+            max_utilisation_bps <= MAX_BPS,
+            "Basis points must be at most 10000"
+        );
+        assert!(share_decimals <= NEAR_DECIMALS, "Share decimals must be at most 24");
+        if token != self.pool.token || share_decimals != self.pool.share_decimals {
+            assert!(
+                self.pool.total_shares == 0 && self.pool.reserved == 0,
+                "Pool asset can only change while the pool is empty"
+            );
+            if let Some(token_id) = &token {
+                assert!(
+                    self.escrow_tokens.get(token_id).is_some(),
+                    "Token is not accepted for escrow"
+                );
+            }
+        }
+
+        self.pool.token = token;
+        self.pool.share_decimals = share_decimals;
+        self.pool.max_utilisation_bps = max_utilisation_bps;
+
+        env::log_str(&format!(
+            "Pool configured: max utilisation {} bps",
+            max_utilisation_bps
+        ));
+    }
+
+    /// Set how much pool asset, in its smallest unit, backs one whole unit of
+    /// a registered payout token (only owner can call). Covered payouts in the
+    /// token are bounded by their coverage at this rate.
+    pub fn set_coverage_rate(&mut self, token: String, rate: U128) {
+        self.assert_owner();
+        let token = token.to_uppercase();
+        assert!(self.assets.get(&token).is_some(), "Asset not registered");
+        assert!(rate.0 > 0, "Coverage rate must be positive");
+        self.coverage_rates.insert(&token, &rate.0);
+
+        env::log_str(&format!("Coverage rate set: {} per {}", rate.0, token));
+    }
+
+    pub fn get_coverage_rate(&self, token: String) -> Option<U128> {
+        self.coverage_rates.get(&token.to_uppercase()).map(U128)
+    }
+
+    /// Provide NEAR liquidity to the pool in exchange for shares
+    #[payable]
+    pub fn pool_deposit(&mut self) -> U128 {
+        assert!(self.pool.token.is_none(), "Pool takes deposits in its token");
+        let amount = env::attached_deposit().as_yoctonear();
+        U128(self.internal_pool_deposit(&env::predecessor_account_id(), amount))
+    }
+
+    /// Burn share tokens for their value at NAV, subject to free capital and
+    /// the utilisation limit
+    pub fn pool_withdraw(&mut self, shares: U128) -> Promise {
+        let account_id = env::predecessor_account_id();
+        let amount = self.pool.amount_for(shares.0);
+        assert!(amount <= self.pool.free_capital(), "Not enough free capital in the pool");
+        let remaining = self.pool.total_capital - amount;
+        assert!(
+            self.pool.utilisation_bps(remaining) <= self.pool.max_utilisation_bps as u128,
+            "Withdrawal would exceed the pool utilisation limit"
+        );
+
+        self.burn_shares(&account_id, shares.0);
+        self.pool.total_capital = remaining;
+
+        env::log_str(&format!(
+            "Pool withdrawal: {} burned {} shares for {}",
+            account_id, shares.0, amount
+        ));
+
+        let transfer = match &self.pool.token {
+            None => Promise::new(account_id.clone()).transfer(NearToken::from_yoctonear(amount)),
+            Some(token_id) => Promise::new(token_id.clone()).function_call(
+                "ft_transfer".to_string(),
+                near_sdk::serde_json::json!({
+                    "receiver_id": account_id,
+                    "amount": amount.to_string(),
+                    "memo": "TriggerPay pool withdrawal",
+                })
+                .to_string()
+                .into_bytes(),
+                NearToken::from_yoctonear(1), // NEP-141 requires exactly 1 yoctoNEAR
+                GAS_FOR_FT_TRANSFER,
+            ),
+        };
+        transfer.then(
+            Self::ext(env::current_account_id())
+                .with_static_gas(GAS_FOR_WITHDRAW_CALLBACK)
+                .on_pool_withdraw(account_id, U128(shares.0), U128(amount)),
+        )
+    }
+
+    /// Callback from a pool withdrawal; restores the LP's shares if the transfer failed
+    #[private]
+    pub fn on_pool_withdraw(&mut self, account_id: AccountId, shares: U128, amount: U128) -> bool {
+        let succeeded = !matches!(env::promise_result_checked(0, 0), Err(PromiseError::Failed));
+        if !succeeded {
+            self.mint_shares(&account_id, shares.0);
+            self.pool.total_capital += amount.0;
+
+            env::log_str(&format!("Pool withdrawal for {} failed, shares restored", account_id));
+        }
+        succeeded
+    }
+
+    /// Buy pool coverage for a trigger by attaching a NEAR premium, which must
+    /// match `quote_premium` within the slippage bound. The pool reserves
+    /// `coverage` until the trigger pays out or expires.
+    #[payable]
+    pub fn create_covered_trigger(
+        &mut self,
+        condition: Condition,
+        payout: Payout,
+        coverage: U128,
+    ) -> TriggerId {
+        let funding = Funding {
+            amount: env::attached_deposit().as_yoctonear(),
+            token: None,
+            coverage: Some(coverage.0),
+        };
+        self.internal_create_trigger(env::predecessor_account_id(), funding, condition, None, payout, None)
+    }
+
+    pub fn get_pool(&self) -> PoolView {
+        let pool = &self.pool;
+        PoolView {
+            token: pool.token.as_ref().map(|token| token.to_string()),
+            total_capital: pool.total_capital.to_string(),
+            total_exposure: pool.reserved.to_string(),
+            free_capital: pool.free_capital().to_string(),
+            total_shares: pool.total_shares.to_string(),
+            nav_per_share: pool.nav_per_share().to_string(),
+            utilisation_bps: pool.utilisation_bps(pool.total_capital) as u32,
+            max_utilisation_bps: pool.max_utilisation_bps,
+            premiums_earned: pool.premiums_earned.to_string(),
+            claims_paid: pool.claims_paid.to_string(),
+            loss_ratio_bps: pool.loss_ratio_bps() as u32,
+        }
+    }
+}
+
+// ============================================================================
+// Internal Methods
+// ============================================================================
+
+impl TriggerPay {
+    pub(crate) fn internal_pool_deposit(&mut self, account_id: &AccountId, amount: Balance) -> Balance {
+        assert!(amount > 0, "Deposit must be positive");
+        // Priced at NAV, so new LPs don't share in premiums already earned
+        let shares = self.pool.shares_for(amount);
+        assert!(shares > 0, "Deposit is too small for one share");
+        self.mint_shares(account_id, shares);
+        self.pool.total_capital += amount;
+
+        env::log_str(&format!(
+            "Pool deposit: {} added {} for {} shares",
+            account_id, amount, shares
+        ));
+        shares
+    }
+
+    /// Take the premium into the pool and reserve coverage for a new trigger.
+    /// Neither payout branch may be worth more than the coverage.
+    pub(crate) fn reserve_coverage(
+        &mut self,
+        funding: &Funding,
+        condition: &Condition,
+        payout: &Payout,
+        fallback_payout: Option<&Payout>,
+    ) {
+        let Some(coverage) = funding.coverage else {
+            return;
+        };
+        assert!(
+            funding.token == self.pool.token,
+            "Premium must be paid in the pool asset"
+        );
+        assert!(coverage > 0, "Coverage must be positive");
+        for payout in std::iter::once(payout).chain(fallback_payout) {
+            let payout_amount: Balance = payout.amount.parse().expect("Invalid payout amount");
+            assert!(
+                payout_amount <= self.max_covered_payout(&payout.token, coverage),
+                "Payout exceeds the coverage"
+            );
+        }
+        assert!(
+            condition.condition_type != ConditionType::Schedule,
+            "Scheduled triggers cannot be covered by the pool"
+        );
+        let quote = self.internal_quote(condition, coverage);
+        let min_premium = quote.min_premium.parse::<Balance>().unwrap_or(0);
+        let max_premium = quote.max_premium.parse::<Balance>().unwrap_or(0);
+        assert!(
+            (min_premium..=max_premium).contains(&funding.amount),
+            "Premium does not match the quote of {}",
+            quote.premium
+        );
+
+        // Premiums are earned by LPs as soon as the coverage is written
+        self.pool.total_capital += funding.amount;
+        self.pool.premiums_earned += funding.amount;
+        assert!(
+            coverage <= self.pool.free_capital(),
+            "Not enough free capital in the pool"
+        );
+        self.pool.reserved += coverage;
+    }
+
+    /// Largest payout in `token`, in its smallest unit, that `coverage` backs
+    fn max_covered_payout(&self, token: &str, coverage: Balance) -> Balance {
+        let token = token.to_uppercase();
+        let rate = self
+            .coverage_rates
+            .get(&token)
+            .expect("Payout token has no coverage rate");
+        let decimals = self.assets.get(&token).expect("Asset not registered").decimals;
+        mul_div(coverage, 10u128.pow(decimals as u32), rate)
+    }
+
+    /// Release a covered trigger's reservation. Paid coverage leaves the pool
+    /// to fund the cross-chain payout; unpaid coverage returns to free capital.
+    pub(crate) fn release_coverage(&mut self, trigger: &Trigger, paid: bool) {
+        let Some(coverage) = trigger.coverage else {
+            return;
+        };
+        self.pool.reserved = self.pool.reserved.saturating_sub(coverage);
+        if paid {
+            self.pool.total_capital = self.pool.total_capital.saturating_sub(coverage);
+            self.pool.claims_paid += coverage;
+        }
+
+        env::log_str(&format!(
+            "Coverage for {} released: {} {}",
+            trigger.id,
+            coverage,
+            if paid { "paid out" } else { "returned to pool" }
+        ));
+    }
+}
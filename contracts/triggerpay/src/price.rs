@@ -0,0 +1,170 @@
+use near_sdk::borsh::{BorshDeserialize, BorshSerialize};
+use near_sdk::serde::{Deserialize, Serialize};
+use near_sdk::env;
+use schemars::JsonSchema;
+
+use crate::{Balance, TriggerPay, TriggerPayExt};
+
+// ============================================================================
+// Types
+// ============================================================================
+
+#[derive(BorshDeserialize, BorshSerialize, Serialize, Deserialize, Clone, Debug, PartialEq, JsonSchema)]
+#[borsh(crate = "near_sdk::borsh")]
+#[serde(crate = "near_sdk::serde")]
+pub enum PriceDirection {
+    Above, // Pays out if the price rises to or above the threshold
+    Below, // Pays out if the price falls to or below the threshold (stop-loss)
+}
+
+#[derive(BorshDeserialize, BorshSerialize, Serialize, Deserialize, Clone, Debug, JsonSchema)]
+#[borsh(crate = "near_sdk::borsh")]
+#[serde(crate = "near_sdk::serde")]
+pub struct PriceCondition {
+    pub asset: String,            // Symbol from the asset registry: "BTC", "ETH"
+    pub direction: PriceDirection,
+    pub threshold: String,        // Decimal string: "65000.50"
+    pub window_start: u64,        // Nanoseconds
+    pub window_end: u64,          // Nanoseconds
+}
+
+#[derive(BorshDeserialize, BorshSerialize, Serialize, Deserialize, Clone, Debug, JsonSchema)]
+#[borsh(crate = "near_sdk::borsh")]
+#[serde(crate = "near_sdk::serde")]
+pub struct PriceObservation {
+    pub asset: String,
+    pub price: String,            // Decimal string, same format as the threshold
+    pub source: String,           // "coingecko", "binance", ...
+    pub observed_at: u64,         // Nanoseconds
+}
+
+#[derive(BorshDeserialize, BorshSerialize, Serialize, Deserialize, Clone, Debug, JsonSchema)]
+#[borsh(crate = "near_sdk::borsh")]
+#[serde(crate = "near_sdk::serde")]
+pub struct AssetInfo {
+    pub symbol: String,
+    pub decimals: u8,
+    pub enabled: bool,
+}
+
+// ============================================================================
+// Asset Registry
+// ============================================================================
+
+#[near_sdk::near]
+impl TriggerPay {
+    /// Register or update a priced asset (only owner can call)
+    pub fn register_asset(&mut self, symbol: String, decimals: u8) {
+        self.assert_owner();
+        assert!(!symbol.is_empty(), "Asset symbol is required");
+        assert!(decimals <= 24, "Asset decimals must be at most 24");
+
+        let symbol = symbol.to_uppercase();
+        let info = AssetInfo {
+            symbol: symbol.clone(),
+            decimals,
+            enabled: true,
+        };
+        self.assets.insert(&symbol, &info);
+
+        env::log_str(&format!("Asset registered: {} ({} decimals)", symbol, decimals));
+    }
+
+    /// Enable or disable an asset for new triggers (only owner can call)
+    pub fn set_asset_enabled(&mut self, symbol: String, enabled: bool) {
+        self.assert_owner();
+        let symbol = symbol.to_uppercase();
+        let mut info = self.assets.get(&symbol).expect("Asset not registered");
+        info.enabled = enabled;
+        self.assets.insert(&symbol, &info);
+
+        env::log_str(&format!("Asset {} enabled={}", symbol, enabled));
+    }
+
+    /// Get a single registered asset
+    pub fn get_asset(&self, symbol: String) -> Option<AssetInfo> {
+        self.assets.get(&symbol.to_uppercase())
+    }
+
+    /// Get all registered assets
+    pub fn get_assets(&self) -> Vec<AssetInfo> {
+        self.assets.values().collect()
+    }
+}
+
+// ============================================================================
+// Internal Methods
+// ============================================================================
+
+impl TriggerPay {
+    pub(crate) fn validate_price_condition(&self, condition: &PriceCondition) {
+        let info = self
+            .assets
+            .get(&condition.asset.to_uppercase())
+            .expect("Asset not registered");
+        assert!(info.enabled, "Asset is disabled");
+
+        assert!(
+            parse_decimal(&condition.threshold, info.decimals).is_some(),
+            "Invalid price threshold"
+        );
+        assert!(
+            condition.window_start < condition.window_end,
+            "Price window start must be before its end"
+        );
+        assert!(
+            condition.window_end > env::block_timestamp(),
+            "Price window has already closed"
+        );
+    }
+
+    /// Check an observation against the condition and return whether it crosses the threshold.
+    /// Panics if the observation is for another asset or was taken outside the window.
+    pub(crate) fn evaluate_price(
+        &self,
+        condition: &PriceCondition,
+        observation: &PriceObservation,
+    ) -> bool {
+        assert!(
+            observation.asset.eq_ignore_ascii_case(&condition.asset),
+            "Observation is for a different asset"
+        );
+        assert!(
+            observation.observed_at >= condition.window_start
+                && observation.observed_at <= condition.window_end,
+            "Observation is outside the condition window"
+        );
+
+        let info = self
+            .assets
+            .get(&condition.asset.to_uppercase())
+            .expect("Asset not registered");
+        let threshold = parse_decimal(&condition.threshold, info.decimals)
+            .expect("Invalid price threshold");
+        let price =
+            parse_decimal(&observation.price, info.decimals).expect("Invalid observed price");
+
+        match condition.direction {
+            PriceDirection::Above => price >= threshold,
+            PriceDirection::Below => price <= threshold,
+        }
+    }
+}
+
+/// Parse a decimal string ("123", "123.45") into an integer scaled by `decimals`
+pub(crate) fn parse_decimal(value: &str, decimals: u8) -> Option<Balance> {
+    let (whole, fraction) = match value.split_once('.') {
+        Some((whole, fraction)) => (whole, fraction),
+        None => (value, ""),
+    };
+    if whole.is_empty()
+        || fraction.len() > decimals as usize
+        || !whole.bytes().all(|b| b.is_ascii_digit())
+        || !fraction.bytes().all(|b| b.is_ascii_digit())
+    {
+        return None;
+    }
+
+    let padded = format!("{}{:0<width$}", whole, fraction, width = decimals as usize);
+    padded.parse::<Balance>().ok()
+}
@@ -0,0 +1,195 @@
+use near_sdk::borsh::{BorshDeserialize, BorshSerialize};
+use near_sdk::json_types::U128;
+use near_sdk::serde::{Deserialize, Serialize};
+use near_sdk::env;
+use schemars::JsonSchema;
+
+use crate::flight::airline_designator;
+use crate::pool::mul_div;
+use crate::{Balance, Condition, ConditionType, Payout, TriggerPay, TriggerPayExt};
+
+// ============================================================================
+// Constants
+// ============================================================================
+
+const MAX_BPS: u128 = 10_000;
+const WILDCARD: &str = "*";
+
+// ============================================================================
+// Types
+// ============================================================================
+
+/// Historical outcome rates for a route, airline and month. Any of the three
+/// may be omitted to act as a fallback for more specific entries.
+#[derive(BorshDeserialize, BorshSerialize, Serialize, Deserialize, Clone, Debug, JsonSchema)]
+#[borsh(crate = "near_sdk::borsh")]
+#[serde(crate = "near_sdk::serde")]
+pub struct RiskRate {
+    #[serde(default)]
+    pub route: Option<String>, // "JFK-LAX"
+    #[serde(default)]
+    pub airline: Option<String>, // "AA", "BAW"
+    #[serde(default)]
+    pub month: Option<u8>, // 1-12
+    pub cancellation_bps: u16,
+    pub delay_bps: u16, // Published for delay products and agents; not priced yet
+}
+
+#[derive(BorshDeserialize, BorshSerialize, Serialize, Deserialize, Clone, Debug, JsonSchema)]
+#[borsh(crate = "near_sdk::borsh")]
+#[serde(crate = "near_sdk::serde")]
+pub struct PricingConfig {
+    pub loading_bps: u16,                // Added on top of the expected loss
+    pub min_margin: String,              // Minimum premium above the expected loss, in the pool asset
+    pub utilisation_surcharge_bps: u16,  // Extra loading at 100% pool utilisation, scaled linearly
+    pub slippage_bps: u16,               // How far an attached premium may differ from the quote
+}
+
+impl Default for PricingConfig {
+    fn default() -> Self {
+        Self {
+            loading_bps: 2_000,               // 20%
+            min_margin: "0".to_string(),
+            utilisation_surcharge_bps: 5_000, // +50% when the pool is fully utilised
+            slippage_bps: 100,                // 1%
+        }
+    }
+}
+
+#[derive(Serialize, Deserialize, Debug, JsonSchema)]
+#[serde(crate = "near_sdk::serde")]
+pub struct PremiumQuote {
+    pub probability_bps: u16,
+    pub expected_loss: String, // Strings for JSON compatibility
+    pub premium: String,
+    pub min_premium: String,   // Lowest premium accepted within the slippage bound
+    pub max_premium: String,   // Highest premium accepted within the slippage bound
+    pub utilisation_bps: u32,  // Pool utilisation after writing this coverage
+}
+
+// ============================================================================
+// Pricing
+// ============================================================================
+
+#[near_sdk::near]
+impl TriggerPay {
+    /// Upload historical risk rates, replacing entries with the same key
+    /// (only owner can call)
+    pub fn set_risk_rates(&mut self, rates: Vec<RiskRate>) {
+        self.assert_owner();
+        for rate in &rates {
+            assert!(
+                rate.cancellation_bps as u128 <= MAX_BPS && rate.delay_bps as u128 <= MAX_BPS,
+                "Probabilities must be at most 10000 bps"
+            );
+            assert!(
+                rate.month.is_none_or(|m| (1..=12).contains(&m)),
+                "Month must be between 1 and 12"
+            );
+            let key = risk_key(rate.route.as_deref(), rate.airline.as_deref(), rate.month);
+            self.risk_rates.insert(&key, rate);
+        }
+
+        env::log_str(&format!("Risk rates uploaded: {}", rates.len()));
+    }
+
+    pub fn get_risk_rates(&self) -> Vec<RiskRate> {
+        self.risk_rates.values().collect()
+    }
+
+    /// Set loading, margin, surcharge and slippage (only owner can call)
+    pub fn set_pricing_config(&mut self, config: PricingConfig) {
+        self.assert_owner();
+        assert!(
+            config.min_margin.parse::<Balance>().is_ok(),
+            "Invalid minimum margin"
+        );
+        assert!(
+            config.slippage_bps as u128 <= MAX_BPS,
+            "Slippage must be at most 10000 bps"
+        );
+        self.pricing = config;
+
+        env::log_str("Pricing config updated");
+    }
+
+    pub fn get_pricing_config(&self) -> PricingConfig {
+        self.pricing.clone()
+    }
+
+    /// Price pool coverage for a condition. `coverage` is the amount the pool
+    /// reserves, in the pool asset; the payout only needs to be well formed.
+    pub fn quote_premium(&self, condition: Condition, payout: Payout, coverage: U128) -> PremiumQuote {
+        Self::validate_payout(&payout);
+        self.internal_quote(&condition, coverage.0)
+    }
+}
+
+// ============================================================================
+// Internal Methods
+// ============================================================================
+
+impl TriggerPay {
+    pub(crate) fn internal_quote(&self, condition: &Condition, coverage: Balance) -> PremiumQuote {
+        let probability_bps = self.risk_probability(condition);
+        let expected_loss = mul_div(coverage, probability_bps as u128, MAX_BPS);
+
+        let loaded = mul_div(expected_loss, MAX_BPS + self.pricing.loading_bps as u128, MAX_BPS);
+        let min_margin = self.pricing.min_margin.parse::<Balance>().unwrap_or(0);
+        let base = loaded.max(expected_loss + min_margin);
+
+        // Scarce capital costs more: the surcharge grows with utilisation after this coverage
+        let utilisation_bps = mul_div(
+            self.pool.reserved + coverage,
+            MAX_BPS,
+            self.pool.total_capital.max(1),
+        )
+        .min(MAX_BPS);
+        let surcharge_bps = mul_div(utilisation_bps, self.pricing.utilisation_surcharge_bps as u128, MAX_BPS);
+        let premium = mul_div(base, MAX_BPS + surcharge_bps, MAX_BPS);
+
+        let slippage = mul_div(premium, self.pricing.slippage_bps as u128, MAX_BPS);
+        PremiumQuote {
+            probability_bps,
+            expected_loss: expected_loss.to_string(),
+            premium: premium.to_string(),
+            min_premium: (premium - slippage).to_string(),
+            max_premium: (premium + slippage).to_string(),
+            utilisation_bps: utilisation_bps as u32,
+        }
+    }
+
+    /// Most specific rate for the condition: route, airline and month are
+    /// dropped in turn, month first, until an entry matches
+    fn risk_probability(&self, condition: &Condition) -> u16 {
+        let (route, airline, month) = match condition.condition_type {
+            ConditionType::FlightCancellation => (
+                Some(condition.route.as_str()).filter(|r| !r.is_empty()),
+                airline_designator(&condition.flight_number),
+                condition.flight_date.get(5..7).and_then(|m| m.parse::<u8>().ok()),
+            ),
+            _ => (None, None, None),
+        };
+
+        (0..8u8)
+            .find_map(|mask| {
+                let key = risk_key(
+                    route.filter(|_| mask & 4 == 0),
+                    airline.as_deref().filter(|_| mask & 2 == 0),
+                    month.filter(|_| mask & 1 == 0),
+                );
+                self.risk_rates.get(&key)
+            })
+            .map(|rate| rate.cancellation_bps)
+            .expect("No risk data for this condition")
+    }
+}
+
+fn risk_key(route: Option<&str>, airline: Option<&str>, month: Option<u8>) -> String {
+    format!(
+        "{}|{}|{}",
+        route.map_or(WILDCARD.to_string(), |r| r.to_uppercase()),
+        airline.map_or(WILDCARD.to_string(), |a| a.to_uppercase()),
+        month.map_or(WILDCARD.to_string(), |m| m.to_string())
+    )
+}
@@ -0,0 +1,153 @@
+use near_sdk::borsh::{BorshDeserialize, BorshSerialize};
+use near_sdk::collections::{UnorderedSet, Vector};
+use near_sdk::serde::{Deserialize, Serialize};
+use near_sdk::{env, AccountId};
+use schemars::JsonSchema;
+
+use crate::{Status, StorageKey, Trigger, TriggerEvent, TriggerId, TriggerPay, TriggerPayExt, TriggerView};
+
+// ============================================================================
+// Types
+// ============================================================================
+
+/// Parties to a trigger. The funder is the trigger owner: it controls the
+/// trigger and receives refunds. The traveller is the covered party. The
+/// beneficiary controls the payout address and defaults to the funder.
+#[derive(BorshDeserialize, BorshSerialize, Serialize, Deserialize, Clone, Debug, PartialEq, JsonSchema)]
+#[borsh(crate = "near_sdk::borsh")]
+#[serde(crate = "near_sdk::serde")]
+pub enum TriggerRole {
+    Funder,
+    Traveller,
+    Beneficiary,
+}
+
+// ============================================================================
+// Roles
+// ============================================================================
+
+#[near_sdk::near]
+impl TriggerPay {
+    /// Name the covered party of an active trigger, or clear it (only the
+    /// funder can call)
+    pub fn set_traveller(&mut self, trigger_id: TriggerId, traveller: Option<AccountId>) {
+        let mut trigger = self.triggers.get(&trigger_id).expect("Trigger not found");
+        assert!(
+            env::predecessor_account_id() == trigger.owner,
+            "Only trigger owner can set the traveller"
+        );
+        assert!(trigger.status == Status::Active, "Trigger is not active");
+
+        let previous = std::mem::replace(&mut trigger.traveller, traveller);
+        self.reassign_role(&trigger, TriggerRole::Traveller, previous.as_ref(), trigger.traveller.as_ref());
+    }
+
+    /// Name who controls the payout of an active trigger. The funder assigns
+    /// the first beneficiary; after that only the beneficiary can hand it on.
+    /// A pending payout address change is dropped.
+    pub fn set_beneficiary(&mut self, trigger_id: TriggerId, beneficiary: Option<AccountId>) {
+        let mut trigger = self.triggers.get(&trigger_id).expect("Trigger not found");
+        assert!(
+            env::predecessor_account_id() == *Self::beneficiary_of(&trigger),
+            "Only the trigger beneficiary can reassign the payout"
+        );
+        assert!(trigger.status == Status::Active, "Trigger is not active");
+
+        let previous = std::mem::replace(&mut trigger.beneficiary, beneficiary);
+        self.reassign_role(&trigger, TriggerRole::Beneficiary, previous.as_ref(), trigger.beneficiary.as_ref());
+        self.drop_payout_change(&trigger, "beneficiary changed");
+    }
+
+    /// Triggers in which the account holds `role`. Beneficiaries include
+    /// funded triggers with no separate beneficiary.
+    pub fn get_triggers_by_role(&self, account_id: AccountId, role: TriggerRole) -> Vec<TriggerView> {
+        let funded = || -> Vec<TriggerId> {
+            self.user_triggers
+                .get(&account_id)
+                .map(|ids: Vector<TriggerId>| ids.to_vec())
+                .unwrap_or_default()
+        };
+        let assigned = |role: TriggerRole| -> Vec<TriggerId> {
+            self.role_triggers
+                .get(&(role, account_id.clone()))
+                .map(|ids| ids.to_vec())
+                .unwrap_or_default()
+        };
+
+        let trigger_ids = match role {
+            TriggerRole::Funder => funded(),
+            TriggerRole::Traveller => assigned(TriggerRole::Traveller),
+            TriggerRole::Beneficiary => {
+                // A beneficiary may also have bought the trigger
+                let mut ids = assigned(TriggerRole::Beneficiary);
+                ids.extend(funded());
+                ids.sort();
+                ids.dedup();
+                ids
+            }
+        };
+        trigger_ids
+            .iter()
+            .filter_map(|id| self.triggers.get(id))
+            .filter(|trigger| match role {
+                TriggerRole::Beneficiary => *Self::beneficiary_of(trigger) == account_id,
+                _ => true,
+            })
+            .map(|trigger| self.trigger_to_view(&trigger))
+            .collect()
+    }
+}
+
+// ============================================================================
+// Internal Methods
+// ============================================================================
+
+impl TriggerPay {
+    /// Account that controls the trigger's payout address
+    pub(crate) fn beneficiary_of(trigger: &Trigger) -> &AccountId {
+        trigger.beneficiary.as_ref().unwrap_or(&trigger.owner)
+    }
+
+    /// Move the trigger between role indexes and store it
+    fn reassign_role(
+        &mut self,
+        trigger: &Trigger,
+        role: TriggerRole,
+        previous: Option<&AccountId>,
+        next: Option<&AccountId>,
+    ) {
+        assert!(previous != next, "Role is already assigned to this account");
+        if role == TriggerRole::Beneficiary {
+            assert!(next != Some(&trigger.owner), "The funder is the default beneficiary");
+        }
+
+        let initial_storage = env::storage_usage();
+        if let Some(previous) = previous {
+            let key = (role.clone(), previous.clone());
+            if let Some(mut ids) = self.role_triggers.get(&key) {
+                ids.remove(&trigger.id);
+                self.role_triggers.insert(&key, &ids);
+            }
+        }
+        if let Some(next) = next {
+            let key = (role.clone(), next.clone());
+            let mut ids = self.role_triggers.get(&key).unwrap_or_else(|| {
+                UnorderedSet::new(StorageKey::RoleTriggersInner {
+                    role_hash: env::sha256(format!("{:?}:{}", role, next).as_bytes()),
+                })
+            });
+            ids.insert(&trigger.id);
+            self.role_triggers.insert(&key, &ids);
+        }
+        self.triggers.insert(&trigger.id, trigger);
+        self.charge_trigger_storage(trigger, initial_storage, false);
+
+        self.record_event(
+            trigger,
+            TriggerEvent::RoleAssigned {
+                role,
+                account_id: next.map(|account| account.to_string()),
+            },
+        );
+    }
+}
@@ -0,0 +1,267 @@
+use near_sdk::borsh::{BorshDeserialize, BorshSerialize};
+use near_sdk::serde::{Deserialize, Serialize};
+use near_sdk::serde_json::Value;
+use near_sdk::{env, Gas, Promise, PromiseError};
+use schemars::JsonSchema;
+
+use crate::{
+    Balance, ConditionType, Status, Trigger, TriggerEvent, TriggerId, TriggerPay, TriggerPayExt,
+};
+
+// ============================================================================
+// Constants
+// ============================================================================
+
+const MIN_SCHEDULE_INTERVAL: u64 = 60 * 60 * 1_000_000_000; // 1 hour
+const MAX_SCHEDULE_INTERVAL: u64 = 366 * crate::flight::NANOS_PER_DAY;
+const MAX_INSTALMENTS: u32 = 120;
+const GAS_FOR_INSTALMENT_CALLBACK: Gas = Gas::from_tgas(10);
+const INSTALMENT_RETRY_AFTER: u64 = 60 * 60 * 1_000_000_000; // 1 hour without a signing callback
+
+// ============================================================================
+// Types
+// ============================================================================
+
+/// Fires at `start_at`, then every `interval` until `instalments` payouts have been made.
+/// The escrow is split evenly across instalments.
+#[derive(BorshDeserialize, BorshSerialize, Serialize, Deserialize, Clone, Debug, JsonSchema)]
+#[borsh(crate = "near_sdk::borsh")]
+#[serde(crate = "near_sdk::serde")]
+pub struct ScheduleCondition {
+    pub start_at: u64,         // Nanoseconds
+    pub interval: Option<u64>, // Nanoseconds, None for a one-time payout
+    pub instalments: u32,      // 1 for a one-time payout
+}
+
+#[derive(BorshDeserialize, BorshSerialize, Serialize, Deserialize, Clone, Debug, PartialEq, JsonSchema)]
+#[borsh(crate = "near_sdk::borsh")]
+#[serde(crate = "near_sdk::serde")]
+pub enum InstalmentStatus {
+    Requested, // Signature requested from the MPC network
+    Signed,
+    Failed,    // Signing failed; the instalment can be executed again
+}
+
+#[derive(BorshDeserialize, BorshSerialize, Serialize, Deserialize, Clone, Debug, JsonSchema)]
+#[borsh(crate = "near_sdk::borsh")]
+#[serde(crate = "near_sdk::serde")]
+pub struct Instalment {
+    pub index: u32,
+    pub due_at: u64,           // Nanoseconds
+    pub requested_at: u64,     // Nanoseconds
+    pub escrow_amount: String, // yoctoNEAR released from escrow, string for JSON compatibility
+    pub status: InstalmentStatus,
+}
+
+impl ScheduleCondition {
+    pub fn due_at(&self, index: u32) -> u64 {
+        self.start_at + self.interval.unwrap_or(0) * index as u64
+    }
+
+    pub fn last_due_at(&self) -> u64 {
+        self.due_at(self.instalments.saturating_sub(1))
+    }
+}
+
+// ============================================================================
+// Scheduled Execution
+// ============================================================================
+
+#[near_sdk::near]
+impl TriggerPay {
+    /// Pay the next due instalment of a scheduled trigger. Anyone can call this
+    /// once the block time has passed the instalment's due time. An instalment
+    /// whose signature has not come back within an hour can be requested again.
+    pub fn execute_schedule(&mut self, trigger_id: TriggerId) -> Promise {
+        let mut trigger = self.triggers.get(&trigger_id).expect("Trigger not found");
+        assert!(
+            trigger.status == Status::Active,
+            "Trigger is no longer active"
+        );
+        assert!(
+            trigger.condition.condition_type == ConditionType::Schedule,
+            "Trigger is not scheduled"
+        );
+        let schedule = trigger
+            .condition
+            .schedule
+            .clone()
+            .expect("Schedule condition missing");
+
+        // Retry a failed or stuck instalment before moving on to the next one
+        let now = env::block_timestamp();
+        let stuck = |i: &Instalment| {
+            i.status == InstalmentStatus::Requested && now >= i.requested_at + INSTALMENT_RETRY_AFTER
+        };
+        let index = trigger
+            .instalments
+            .iter()
+            .position(|i| i.status == InstalmentStatus::Failed || stuck(i))
+            .unwrap_or(trigger.instalments.len()) as u32;
+        assert!(index < schedule.instalments, "All instalments have been paid");
+        assert!(
+            trigger
+                .instalments
+                .iter()
+                .all(|i| i.index == index || i.status != InstalmentStatus::Requested),
+            "Previous instalment is still being signed"
+        );
+
+        let due_at = schedule.due_at(index);
+        assert!(
+            env::block_timestamp() >= due_at,
+            "Next instalment is not due yet"
+        );
+
+        // Releasing the instalment's escrow moves the treasury's escrow total
+        self.update_escrow(&mut trigger, |trigger| {
+            let instalment = Instalment {
+                index,
+                due_at,
+                requested_at: now,
+                escrow_amount: Self::instalment_escrow(trigger, &schedule, index).to_string(),
+                status: InstalmentStatus::Requested,
+            };
+            match trigger.instalments.get_mut(index as usize) {
+                Some(existing) => *existing = instalment,
+                None => trigger.instalments.push(instalment),
+            }
+        });
+        self.triggers.insert(&trigger_id, &trigger);
+
+        env::log_str(&format!(
+            "Instalment {}/{} due for {}",
+            index + 1,
+            schedule.instalments,
+            trigger_id
+        ));
+
+        self.record_event(&trigger, TriggerEvent::PayoutRequested { instalment: Some(index) });
+        self.initiate_payout(&trigger, &trigger.payout, Some(index)).then(
+            Self::ext(env::current_account_id())
+                .with_static_gas(GAS_FOR_INSTALMENT_CALLBACK)
+                .on_instalment_signed(trigger_id, index),
+        )
+    }
+
+    /// Callback from the MPC signer for a scheduled instalment
+    #[private]
+    pub fn on_instalment_signed(
+        &mut self,
+        trigger_id: TriggerId,
+        index: u32,
+        #[callback_result] result: Result<Value, PromiseError>,
+    ) {
+        let mut trigger = self.triggers.get(&trigger_id).expect("Trigger not found");
+        assert!(
+            (index as usize) < trigger.instalments.len(),
+            "Instalment not found"
+        );
+
+        let (status, event) = if result.is_ok() {
+            (InstalmentStatus::Signed, TriggerEvent::PayoutSigned { instalment: Some(index) })
+        } else {
+            (InstalmentStatus::Failed, TriggerEvent::PayoutFailed { instalment: Some(index) })
+        };
+        env::log_str(&format!(
+            "Instalment {} for {}: {:?}",
+            index + 1,
+            trigger_id,
+            status
+        ));
+        // A failed instalment returns its escrow to the trigger
+        self.update_escrow(&mut trigger, |trigger| {
+            trigger.instalments[index as usize].status = status
+        });
+
+        // The trigger is done once every instalment has been signed
+        let total = trigger
+            .condition
+            .schedule
+            .as_ref()
+            .map(|s| s.instalments)
+            .unwrap_or(0) as usize;
+        self.triggers.insert(&trigger_id, &trigger);
+        self.record_event(&trigger, event);
+        if trigger.instalments.len() == total
+            && trigger
+                .instalments
+                .iter()
+                .all(|i| i.status == InstalmentStatus::Signed)
+        {
+            self.transition(&mut trigger, Status::PayoutSigned);
+            self.release_exposure(&trigger);
+            self.unindex_expiry(&trigger);
+        }
+    }
+}
+
+// ============================================================================
+// Internal Methods
+// ============================================================================
+
+impl TriggerPay {
+    pub(crate) fn validate_schedule_condition(&self, condition: &ScheduleCondition) {
+        assert!(
+            condition.start_at > env::block_timestamp(),
+            "Schedule must start in the future"
+        );
+        match condition.interval {
+            Some(interval) => {
+                assert!(
+                    (MIN_SCHEDULE_INTERVAL..=MAX_SCHEDULE_INTERVAL).contains(&interval),
+                    "Schedule interval must be between one hour and one year"
+                );
+                assert!(
+                    condition.instalments >= 1 && condition.instalments <= MAX_INSTALMENTS,
+                    "Instalments must be between 1 and 120"
+                );
+            }
+            None => assert!(
+                condition.instalments == 1,
+                "One-time schedules have exactly one instalment"
+            ),
+        }
+
+        // The last due time and its settlement buffer must fit in a timestamp
+        let end = condition
+            .interval
+            .unwrap_or(0)
+            .checked_mul(condition.instalments as u64 - 1)
+            .and_then(|span| condition.start_at.checked_add(span))
+            .and_then(|last_due_at| last_due_at.checked_add(self.get_settlement_buffer(ConditionType::Schedule)));
+        assert!(end.is_some(), "Schedule ends too far in the future");
+    }
+
+    /// Escrow can't be refunded while an instalment's signature may still
+    /// come back
+    pub(crate) fn instalment_in_flight(trigger: &Trigger) -> bool {
+        trigger
+            .instalments
+            .iter()
+            .any(|i| i.status == InstalmentStatus::Requested)
+    }
+
+    /// Escrow already released to instalments that were not rolled back
+    pub(crate) fn released_escrow(trigger: &Trigger) -> Balance {
+        trigger
+            .instalments
+            .iter()
+            .filter(|i| i.status != InstalmentStatus::Failed)
+            .map(|i| i.escrow_amount.parse::<Balance>().unwrap_or(0))
+            .sum()
+    }
+
+    /// Even share of the escrow not yet paid out, so a top-up spreads over
+    /// the instalments still to come; the last instalment takes the remainder
+    fn instalment_escrow(trigger: &Trigger, schedule: &ScheduleCondition, index: u32) -> Balance {
+        let paid: Balance = trigger
+            .instalments
+            .iter()
+            .filter(|i| i.index < index && i.status != InstalmentStatus::Failed)
+            .map(|i| i.escrow_amount.parse::<Balance>().unwrap_or(0))
+            .sum();
+        let remaining = trigger.funded_amount.saturating_sub(paid);
+        remaining / (schedule.instalments - index) as Balance
+    }
+}
@@ -0,0 +1,183 @@
+use near_sdk::json_types::U128;
+use near_sdk::serde::{Deserialize, Serialize};
+use near_sdk::serde_json::{json, Value};
+use near_sdk::{assert_one_yocto, env, AccountId, Gas, NearToken, Promise, PromiseOrValue};
+use schemars::JsonSchema;
+
+use crate::{Balance, TriggerPay, TriggerPayExt};
+
+// ============================================================================
+// Constants
+// ============================================================================
+
+const GAS_FOR_FT_ON_TRANSFER: Gas = Gas::from_tgas(35);
+const GAS_FOR_RESOLVE_TRANSFER: Gas = Gas::from_tgas(10);
+const FT_METADATA_SPEC: &str = "ft-1.0.0";
+
+// ============================================================================
+// Types
+// ============================================================================
+
+/// NEP-148 metadata for the pool share token
+#[derive(Serialize, Deserialize, Clone, Debug, JsonSchema)]
+#[serde(crate = "near_sdk::serde")]
+pub struct FungibleTokenMetadata {
+    pub spec: String,
+    pub name: String,
+    pub symbol: String,
+    pub icon: Option<String>,
+    pub reference: Option<String>,
+    pub reference_hash: Option<String>,
+    pub decimals: u8,
+}
+
+// ============================================================================
+// NEP-141 Pool Share Token
+// ============================================================================
+
+#[near_sdk::near]
+impl TriggerPay {
+    /// Transfer pool shares; requires exactly 1 yoctoNEAR attached and a
+    /// receiver registered through `storage_deposit`
+    #[payable]
+    pub fn ft_transfer(&mut self, receiver_id: AccountId, amount: U128, memo: Option<String>) {
+        assert_one_yocto();
+        let sender_id = env::predecessor_account_id();
+        self.internal_transfer_shares(&sender_id, &receiver_id, amount.0, memo);
+    }
+
+    /// Transfer pool shares and notify the receiver, which may return some of them
+    #[payable]
+    pub fn ft_transfer_call(
+        &mut self,
+        receiver_id: AccountId,
+        amount: U128,
+        memo: Option<String>,
+        msg: String,
+    ) -> PromiseOrValue<U128> {
+        assert_one_yocto();
+        let sender_id = env::predecessor_account_id();
+        self.internal_transfer_shares(&sender_id, &receiver_id, amount.0, memo);
+
+        Promise::new(receiver_id.clone())
+            .function_call(
+                "ft_on_transfer".to_string(),
+                json!({
+                    "sender_id": sender_id,
+                    "amount": amount,
+                    "msg": msg,
+                })
+                .to_string()
+                .into_bytes(),
+                NearToken::from_yoctonear(0),
+                GAS_FOR_FT_ON_TRANSFER,
+            )
+            .then(
+                Self::ext(env::current_account_id())
+                    .with_static_gas(GAS_FOR_RESOLVE_TRANSFER)
+                    .ft_resolve_transfer(sender_id, receiver_id, amount),
+            )
+            .into()
+    }
+
+    /// Return shares the receiver did not use; the result is the amount kept
+    #[private]
+    pub fn ft_resolve_transfer(&mut self, sender_id: AccountId, receiver_id: AccountId, amount: U128) -> U128 {
+        // A failed or malformed receiver call refunds everything
+        let unused = env::promise_result_checked(0, 64)
+            .ok()
+            .and_then(|data| near_sdk::serde_json::from_slice::<U128>(&data).ok())
+            .map_or(amount.0, |unused| unused.0.min(amount.0));
+
+        let refund = unused.min(self.lp_shares.get(&receiver_id).unwrap_or(0));
+        if refund > 0 {
+            self.internal_transfer_shares(&receiver_id, &sender_id, refund, Some("refund".to_string()));
+        }
+        U128(amount.0 - refund)
+    }
+
+    pub fn ft_total_supply(&self) -> U128 {
+        U128(self.pool.total_shares)
+    }
+
+    pub fn ft_balance_of(&self, account_id: AccountId) -> U128 {
+        U128(self.lp_shares.get(&account_id).unwrap_or(0))
+    }
+
+    pub fn ft_metadata(&self) -> FungibleTokenMetadata {
+        FungibleTokenMetadata {
+            spec: FT_METADATA_SPEC.to_string(),
+            name: "TriggerPay Pool Share".to_string(),
+            symbol: "TPLP".to_string(),
+            icon: None,
+            reference: None,
+            reference_hash: None,
+            decimals: self.pool.share_decimals,
+        }
+    }
+}
+
+// ============================================================================
+// Internal Methods
+// ============================================================================
+
+impl TriggerPay {
+    pub(crate) fn mint_shares(&mut self, account_id: &AccountId, shares: Balance) {
+        let owned = self.lp_shares.get(account_id).unwrap_or(0);
+        self.lp_shares.insert(account_id, &(owned + shares));
+        self.pool.total_shares += shares;
+
+        emit_ft_event("ft_mint", json!({ "owner_id": account_id, "amount": shares.to_string() }));
+    }
+
+    pub(crate) fn burn_shares(&mut self, account_id: &AccountId, shares: Balance) {
+        let owned = self.lp_shares.get(account_id).unwrap_or(0);
+        assert!(shares > 0 && shares <= owned, "Not enough pool shares");
+        self.lp_shares.insert(account_id, &(owned - shares));
+        self.pool.total_shares -= shares;
+
+        emit_ft_event("ft_burn", json!({ "owner_id": account_id, "amount": shares.to_string() }));
+    }
+
+    fn internal_transfer_shares(
+        &mut self,
+        sender_id: &AccountId,
+        receiver_id: &AccountId,
+        amount: Balance,
+        memo: Option<String>,
+    ) {
+        assert!(sender_id != receiver_id, "Sender and receiver must differ");
+        assert!(amount > 0, "Amount must be positive");
+        assert!(
+            self.storage_accounts.get(receiver_id).is_some(),
+            "Receiver is not registered for storage"
+        );
+
+        let sender_balance = self.lp_shares.get(sender_id).unwrap_or(0);
+        assert!(amount <= sender_balance, "Not enough pool shares");
+        self.lp_shares.insert(sender_id, &(sender_balance - amount));
+        let receiver_balance = self.lp_shares.get(receiver_id).unwrap_or(0);
+        self.lp_shares.insert(receiver_id, &(receiver_balance + amount));
+
+        let mut data = json!({
+            "old_owner_id": sender_id,
+            "new_owner_id": receiver_id,
+            "amount": amount.to_string(),
+        });
+        if let Some(memo) = memo {
+            data["memo"] = Value::String(memo);
+        }
+        emit_ft_event("ft_transfer", data);
+    }
+}
+
+/// Log a NEP-297 event for the share token
+fn emit_ft_event(event: &str, data: Value) {
+    let event = json!({
+        "standard": "nep141",
+        "version": "1.0.0",
+        "event": event,
+        "data": [data],
+    });
+    env::log_str(&format!("EVENT_JSON:{}", event));
+}
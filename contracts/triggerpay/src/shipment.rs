@@ -0,0 +1,136 @@
+use near_sdk::borsh::{BorshDeserialize, BorshSerialize};
+use near_sdk::serde::{Deserialize, Serialize};
+use near_sdk::{env, Promise};
+use schemars::JsonSchema;
+
+use crate::escrow::Funding;
+use crate::{
+    Attestation, Condition, ConditionType, Payout, PayoutBranch, Trigger, TriggerId,
+    TriggerPay, TriggerPayExt,
+};
+
+// ============================================================================
+// Types
+// ============================================================================
+
+#[derive(BorshDeserialize, BorshSerialize, Serialize, Deserialize, Clone, Debug, PartialEq, JsonSchema)]
+#[borsh(crate = "near_sdk::borsh")]
+#[serde(crate = "near_sdk::serde")]
+pub enum ShipmentStage {
+    InfoReceived,
+    InTransit,
+    OutForDelivery,
+    Delivered,
+    Exception,
+    Returned,
+}
+
+#[derive(BorshDeserialize, BorshSerialize, Serialize, Deserialize, Clone, Debug, JsonSchema)]
+#[borsh(crate = "near_sdk::borsh")]
+#[serde(crate = "near_sdk::serde")]
+pub struct ShipmentCondition {
+    pub carrier: String,        // "UPS", "DHL"
+    pub tracking_hash: String,  // Hex-encoded SHA256 of the uppercased tracking number
+    pub target_status: ShipmentStage,
+    pub deadline: u64,          // Nanoseconds
+}
+
+#[derive(BorshDeserialize, BorshSerialize, Serialize, Deserialize, Clone, Debug, JsonSchema)]
+#[borsh(crate = "near_sdk::borsh")]
+#[serde(crate = "near_sdk::serde")]
+pub struct ShipmentObservation {
+    pub tracking_hash: String,
+    pub status: ShipmentStage,
+    pub status_at: u64, // Nanoseconds, when the carrier recorded the status
+}
+
+// ============================================================================
+// Two-Outcome Triggers
+// ============================================================================
+
+#[near_sdk::near]
+impl TriggerPay {
+    /// Create a shipment trigger that pays `on_delivered` if the target status is
+    /// reached by the deadline, and `on_late` if the deadline passes first
+    #[payable]
+    pub fn create_shipment_trigger(
+        &mut self,
+        condition: Condition,
+        on_delivered: Payout,
+        on_late: Payout,
+    ) -> TriggerId {
+        assert!(
+            condition.condition_type == ConditionType::ShipmentStatus,
+            "Two-outcome payouts require a ShipmentStatus condition"
+        );
+
+        let deposit = env::attached_deposit();
+        let owner = env::predecessor_account_id();
+        self.internal_create_trigger(
+            owner,
+            Funding::near(deposit.as_yoctonear()),
+            condition,
+            None,
+            on_delivered,
+            Some(on_late),
+        )
+    }
+}
+
+// ============================================================================
+// Internal Methods
+// ============================================================================
+
+impl TriggerPay {
+    pub(crate) fn validate_shipment_condition(&self, condition: &ShipmentCondition) {
+        assert!(!condition.carrier.is_empty(), "Carrier is required");
+        assert!(
+            condition.tracking_hash.len() == 64
+                && condition.tracking_hash.bytes().all(|b| b.is_ascii_hexdigit()),
+            "Tracking hash must be a hex-encoded SHA256"
+        );
+        assert!(
+            condition.deadline > env::block_timestamp(),
+            "Delivery deadline must be in the future"
+        );
+    }
+
+    /// Return whether the target status was reached on or before the deadline
+    pub(crate) fn evaluate_shipment(
+        &self,
+        condition: &ShipmentCondition,
+        observation: &ShipmentObservation,
+    ) -> bool {
+        assert!(
+            observation.tracking_hash.eq_ignore_ascii_case(&condition.tracking_hash),
+            "Observation is for a different shipment"
+        );
+        observation.status == condition.target_status && observation.status_at <= condition.deadline
+    }
+
+    /// Pay the late branch once an attestation shows the deadline passed
+    /// without the target status. Returns None if the late branch does not apply.
+    pub(crate) fn maybe_pay_late_branch(
+        &mut self,
+        trigger: &Trigger,
+        attestation: &Attestation,
+    ) -> Option<Promise> {
+        trigger.fallback_payout.as_ref()?;
+        let shipment = trigger.condition.shipment.as_ref()?;
+        if attestation.condition_met || env::block_timestamp() <= shipment.deadline {
+            return None;
+        }
+
+        env::log_str(&format!(
+            "Delivery deadline missed for {}! Paying late branch...",
+            trigger.id
+        ));
+
+        Some(self.begin_payout(trigger.clone(), PayoutBranch::Fallback))
+    }
+}
+
+/// Hex-encoded SHA256 of a tracking number, as stored in `ShipmentCondition`
+pub fn tracking_hash(tracking_number: &str) -> String {
+    hex::encode(env::sha256(tracking_number.trim().to_uppercase().as_bytes()))
+}
@@ -0,0 +1,117 @@
+use near_sdk::borsh::{BorshDeserialize, BorshSerialize};
+use near_sdk::env;
+use near_sdk::serde::{Deserialize, Serialize};
+use schemars::JsonSchema;
+
+use crate::http::compare_numeric;
+use crate::TriggerPay;
+
+// ============================================================================
+// Types
+// ============================================================================
+
+#[derive(BorshDeserialize, BorshSerialize, Serialize, Deserialize, Clone, Debug, PartialEq, JsonSchema)]
+#[borsh(crate = "near_sdk::borsh")]
+#[serde(crate = "near_sdk::serde")]
+pub enum OutcomeSelector {
+    HomeWin,
+    AwayWin,
+    Draw,
+    TotalOver { line: String },  // Combined score strictly above the line: "2.5"
+    TotalUnder { line: String }, // Combined score strictly below the line
+    TeamAdvances { team: String },
+}
+
+#[derive(BorshDeserialize, BorshSerialize, Serialize, Deserialize, Clone, Debug, JsonSchema)]
+#[borsh(crate = "near_sdk::borsh")]
+#[serde(crate = "near_sdk::serde")]
+pub struct SportsCondition {
+    pub league: String,   // "EPL", "NBA"
+    pub match_id: String, // Provider's match identifier
+    pub outcome: OutcomeSelector,
+    pub kickoff_at: u64,  // Nanoseconds
+}
+
+#[derive(BorshDeserialize, BorshSerialize, Serialize, Deserialize, Clone, Debug, PartialEq, JsonSchema)]
+#[borsh(crate = "near_sdk::borsh")]
+#[serde(crate = "near_sdk::serde")]
+pub enum MatchStatus {
+    Scheduled,
+    InProgress,
+    Finished,
+    Postponed,
+    Abandoned,
+}
+
+#[derive(BorshDeserialize, BorshSerialize, Serialize, Deserialize, Clone, Debug, JsonSchema)]
+#[borsh(crate = "near_sdk::borsh")]
+#[serde(crate = "near_sdk::serde")]
+pub struct SportsObservation {
+    pub match_id: String,
+    pub match_status: MatchStatus,
+    pub home_score: u32,
+    pub away_score: u32,
+    pub advancing_team: Option<String>, // For knockout ties
+}
+
+// ============================================================================
+// Internal Methods
+// ============================================================================
+
+impl TriggerPay {
+    pub(crate) fn validate_sports_condition(&self, condition: &SportsCondition) {
+        assert!(!condition.league.is_empty(), "League is required");
+        assert!(!condition.match_id.is_empty(), "Match ID is required");
+        assert!(
+            condition.kickoff_at > env::block_timestamp(),
+            "Match has already started"
+        );
+        match &condition.outcome {
+            OutcomeSelector::TotalOver { line } | OutcomeSelector::TotalUnder { line } => {
+                assert!(
+                    compare_numeric(line, line).is_some(),
+                    "Total line must be numeric"
+                );
+            }
+            OutcomeSelector::TeamAdvances { team } => {
+                assert!(!team.is_empty(), "Team is required");
+            }
+            _ => {}
+        }
+    }
+
+    /// Return whether the observed result satisfies the selector. Only finished
+    /// matches can pay out; postponed or abandoned matches stay open until expiry.
+    pub(crate) fn evaluate_sports(
+        &self,
+        condition: &SportsCondition,
+        observation: &SportsObservation,
+    ) -> bool {
+        assert!(
+            observation.match_id == condition.match_id,
+            "Observation is for a different match"
+        );
+        if observation.match_status != MatchStatus::Finished {
+            return false;
+        }
+
+        let (home, away) = (observation.home_score, observation.away_score);
+        // Widened so attested scores cannot overflow the sum
+        let total = (home as u64 + away as u64).to_string();
+        match &condition.outcome {
+            OutcomeSelector::HomeWin => home > away,
+            OutcomeSelector::AwayWin => away > home,
+            OutcomeSelector::Draw => home == away,
+            OutcomeSelector::TotalOver { line } => {
+                compare_numeric(&total, line) == Some(std::cmp::Ordering::Greater)
+            }
+            OutcomeSelector::TotalUnder { line } => {
+                compare_numeric(&total, line) == Some(std::cmp::Ordering::Less)
+            }
+            OutcomeSelector::TeamAdvances { team } => observation
+                .advancing_team
+                .as_ref()
+                .is_some_and(|advancing| advancing.eq_ignore_ascii_case(team)),
+        }
+    }
+}
@@ -0,0 +1,225 @@
+use near_sdk::borsh::{BorshDeserialize, BorshSerialize};
+use near_sdk::json_types::U128;
+use near_sdk::serde::{Deserialize, Serialize};
+use near_sdk::{assert_one_yocto, env, AccountId, NearToken, Promise, StorageUsage};
+use schemars::JsonSchema;
+
+use crate::{Balance, Trigger, TriggerPay, TriggerPayExt};
+
+// ============================================================================
+// Constants
+// ============================================================================
+
+// Bytes taken by a storage account entry: key prefix + account ID + record
+const STORAGE_ACCOUNT_BYTES: StorageUsage = 1 + 64 + 4 + 16 + 8;
+
+// ============================================================================
+// Types
+// ============================================================================
+
+/// Per-account storage ledger
+#[derive(BorshDeserialize, BorshSerialize, Clone, Debug)]
+#[borsh(crate = "near_sdk::borsh")]
+pub struct StorageAccount {
+    pub total: Balance,          // yoctoNEAR deposited for storage
+    pub used_bytes: StorageUsage, // Bytes of triggers and attestations owned by the account
+}
+
+/// NEP-145 storage balance; amounts are yoctoNEAR strings for JSON compatibility
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq, JsonSchema)]
+#[serde(crate = "near_sdk::serde")]
+pub struct StorageBalance {
+    pub total: String,
+    pub available: String,
+}
+
+#[derive(Serialize, Deserialize, Clone, Debug, JsonSchema)]
+#[serde(crate = "near_sdk::serde")]
+pub struct StorageBalanceBounds {
+    pub min: String,
+    pub max: Option<String>,
+}
+
+impl StorageAccount {
+    fn locked(&self) -> Balance {
+        env::storage_byte_cost().as_yoctonear() * (self.used_bytes + STORAGE_ACCOUNT_BYTES) as Balance
+    }
+
+    fn available(&self) -> Balance {
+        self.total.saturating_sub(self.locked())
+    }
+
+    fn to_balance(&self) -> StorageBalance {
+        StorageBalance {
+            total: self.total.to_string(),
+            available: self.available().to_string(),
+        }
+    }
+}
+
+// ============================================================================
+// NEP-145 Storage Management
+// ============================================================================
+
+#[near_sdk::near]
+impl TriggerPay {
+    /// Deposit NEAR to cover storage for `account_id` (defaults to the caller).
+    /// With `registration_only`, only the minimum is kept and the rest refunded.
+    #[payable]
+    pub fn storage_deposit(
+        &mut self,
+        account_id: Option<AccountId>,
+        registration_only: Option<bool>,
+    ) -> StorageBalance {
+        let amount = env::attached_deposit().as_yoctonear();
+        let account_id = account_id.unwrap_or_else(env::predecessor_account_id);
+        let min = Self::storage_minimum();
+
+        let mut account = self.storage_accounts.get(&account_id);
+        let refund = match (&mut account, registration_only.unwrap_or(false)) {
+            (Some(_), true) => amount,
+            (Some(existing), false) => {
+                existing.total += amount;
+                0
+            }
+            (None, registration_only) => {
+                assert!(amount >= min, "Deposit is below the minimum storage balance");
+                let kept = if registration_only { min } else { amount };
+                account = Some(StorageAccount {
+                    total: kept,
+                    used_bytes: 0,
+                });
+                amount - kept
+            }
+        };
+        let account = account.expect("Storage account missing");
+        self.storage_accounts.insert(&account_id, &account);
+
+        if refund > 0 {
+            Promise::new(env::predecessor_account_id())
+                .transfer(NearToken::from_yoctonear(refund))
+                .detach();
+        }
+        account.to_balance()
+    }
+
+    /// Withdraw unused storage balance; requires exactly 1 yoctoNEAR attached
+    #[payable]
+    pub fn storage_withdraw(&mut self, amount: Option<U128>) -> StorageBalance {
+        assert_one_yocto();
+        let account_id = env::predecessor_account_id();
+        let mut account = self
+            .storage_accounts
+            .get(&account_id)
+            .expect("Account is not registered");
+
+        let available = account.available();
+        let amount = amount.map(|a| a.0).unwrap_or(available);
+        assert!(amount <= available, "Amount exceeds the available storage balance");
+
+        account.total -= amount;
+        self.storage_accounts.insert(&account_id, &account);
+        if amount > 0 {
+            Promise::new(account_id)
+                .transfer(NearToken::from_yoctonear(amount))
+                .detach();
+        }
+        account.to_balance()
+    }
+
+    pub fn storage_balance_of(&self, account_id: AccountId) -> Option<StorageBalance> {
+        self.storage_accounts
+            .get(&account_id)
+            .map(|account| account.to_balance())
+    }
+
+    pub fn storage_balance_bounds(&self) -> StorageBalanceBounds {
+        StorageBalanceBounds {
+            min: Self::storage_minimum().to_string(),
+            max: None,
+        }
+    }
+}
+
+// ============================================================================
+// Internal Methods
+// ============================================================================
+
+impl TriggerPay {
+    fn storage_minimum() -> Balance {
+        env::storage_byte_cost().as_yoctonear() * STORAGE_ACCOUNT_BYTES as Balance
+    }
+
+    /// Charge bytes written since `initial_usage` to `account_id`, or credit
+    /// back bytes freed since then. New triggers require enough storage
+    /// balance; attestations are always recorded so that payouts are never
+    /// blocked, and any shortfall is settled by later deposits.
+    pub(crate) fn charge_storage(
+        &mut self,
+        account_id: &AccountId,
+        initial_usage: StorageUsage,
+        require_balance: bool,
+    ) {
+        let usage = env::storage_usage();
+        let Some(mut account) = self.storage_accounts.get(account_id) else {
+            assert!(!require_balance, "Account is not registered for storage");
+            return;
+        };
+        if usage >= initial_usage {
+            account.used_bytes += usage - initial_usage;
+        } else {
+            account.used_bytes = account.used_bytes.saturating_sub(initial_usage - usage);
+        }
+        if require_balance {
+            assert!(
+                account.total >= account.locked(),
+                "Insufficient storage balance: {} yoctoNEAR required",
+                account.locked()
+            );
+        }
+        self.storage_accounts.insert(account_id, &account);
+    }
+
+    /// `charge_storage` for state that belongs to a trigger, charged to its
+    /// owner. The trigger keeps a tally of its bytes so that they can follow
+    /// it to a new owner.
+    pub(crate) fn charge_trigger_storage(
+        &mut self,
+        trigger: &Trigger,
+        initial_usage: StorageUsage,
+        require_balance: bool,
+    ) {
+        // The tally's own entry is written first so that it is counted too
+        let bytes = match self.trigger_storage.get(&trigger.id) {
+            Some(bytes) => bytes,
+            None => {
+                self.trigger_storage.insert(&trigger.id, &0);
+                0
+            }
+        };
+        let usage = env::storage_usage();
+        let bytes = if usage >= initial_usage {
+            bytes + (usage - initial_usage)
+        } else {
+            bytes.saturating_sub(initial_usage - usage)
+        };
+        self.trigger_storage.insert(&trigger.id, &bytes);
+        self.charge_storage(&trigger.owner, initial_usage, require_balance);
+    }
+
+    /// Move a trigger's bytes from its previous owner's storage balance to its
+    /// new owner's, who must be registered
+    pub(crate) fn transfer_trigger_storage(&mut self, trigger: &Trigger, from: &AccountId) {
+        let bytes = self.trigger_storage.get(&trigger.id).unwrap_or(0);
+        if let Some(mut account) = self.storage_accounts.get(from) {
+            account.used_bytes = account.used_bytes.saturating_sub(bytes);
+            self.storage_accounts.insert(from, &account);
+        }
+        let mut account = self
+            .storage_accounts
+            .get(&trigger.owner)
+            .expect("Receiver is not registered for storage");
+        account.used_bytes += bytes;
+        self.storage_accounts.insert(&trigger.owner, &account);
+    }
+}
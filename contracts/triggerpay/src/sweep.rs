@@ -0,0 +1,177 @@
+use near_sdk::json_types::U128;
+use near_sdk::{env, AccountId, Gas, NearToken, Promise};
+
+use crate::{Balance, Status, Trigger, TriggerId, TriggerPay, TriggerPayExt};
+
+// ============================================================================
+// Constants
+// ============================================================================
+
+pub const DEFAULT_KEEPER_FEE: Balance = 10_000_000_000_000_000_000_000; // 0.01 NEAR
+const MAX_SWEEP_BATCH: u32 = 50;
+const MAX_SWEEP_SCAN: usize = 200; // Entries looked at per call, including skipped ones
+const GAS_FOR_FT_TRANSFER: Gas = Gas::from_tgas(10);
+
+// ============================================================================
+// Expiry Sweeper
+// ============================================================================
+
+#[near_sdk::near]
+impl TriggerPay {
+    /// Set the fee kept from each swept NEAR refund and paid to the keeper
+    /// (only owner can call)
+    pub fn set_keeper_fee(&mut self, fee: U128) {
+        self.assert_owner();
+        assert!(fee.0 < crate::MINIMUM_DEPOSIT, "Keeper fee must be below the minimum deposit");
+        self.keeper_fee = fee.0;
+
+        env::log_str(&format!("Keeper fee set: {}", fee.0));
+    }
+
+    pub fn get_keeper_fee(&self) -> U128 {
+        U128(self.keeper_fee)
+    }
+
+    /// Refund up to `limit` expired triggers, oldest first, and pay the caller
+    /// a bounty from their refund fees: the keeper fee for NEAR escrow, the
+    /// token's refund fee for token escrow. Triggers that cannot be refunded
+    /// yet are passed over without counting against `limit`. Returns how many
+    /// were expired.
+    pub fn sweep_expired(&mut self, limit: u32) -> u32 {
+        let keeper = env::predecessor_account_id();
+        let now = env::block_timestamp();
+        let batch = limit.min(MAX_SWEEP_BATCH);
+        let due: Vec<(u64, TriggerId)> = self
+            .expiry_index
+            .iter()
+            .take_while(|((expires_at, _), _)| *expires_at < now)
+            .take(MAX_SWEEP_SCAN)
+            .map(|(key, _)| key)
+            .collect();
+
+        let mut swept = 0;
+        let mut near_bounty: Balance = 0;
+        let mut token_bounties: Vec<(AccountId, Balance)> = Vec::new();
+        for key in due {
+            if swept == batch {
+                break;
+            }
+            let Some(trigger) = self.triggers.get(&key.1).filter(|t| t.status == Status::Active) else {
+                self.expiry_index.remove(&key);
+                continue;
+            };
+            // Left queued until the treasury has unstaked enough to refund it,
+            // or until an instalment's signature comes back
+            let near_refund = trigger.escrow_token.is_none() && trigger.coverage.is_none();
+            if near_refund && Self::refundable_escrow(&trigger) + self.pending_yield(&trigger) > self.liquid_near() {
+                continue;
+            }
+            if Self::instalment_in_flight(&trigger) {
+                continue;
+            }
+            self.unindex_expiry(&trigger);
+
+            let fee = self.expire_trigger(trigger.clone());
+            match trigger.escrow_token {
+                None => near_bounty += fee,
+                Some(token_id) => match token_bounties.iter_mut().find(|(t, _)| *t == token_id) {
+                    Some((_, total)) => *total += fee,
+                    None => token_bounties.push((token_id, fee)),
+                },
+            }
+            swept += 1;
+        }
+
+        if near_bounty > 0 {
+            Promise::new(keeper.clone())
+                .transfer(NearToken::from_yoctonear(near_bounty))
+                .detach();
+        }
+        for (token_id, amount) in token_bounties.into_iter().filter(|(_, amount)| *amount > 0) {
+            Promise::new(token_id)
+                .function_call(
+                    "ft_transfer".to_string(),
+                    near_sdk::serde_json::json!({
+                        "receiver_id": keeper,
+                        "amount": amount.to_string(),
+                        "memo": "TriggerPay keeper bounty",
+                    })
+                    .to_string()
+                    .into_bytes(),
+                    NearToken::from_yoctonear(1), // NEP-141 requires exactly 1 yoctoNEAR
+                    GAS_FOR_FT_TRANSFER,
+                )
+                .detach();
+        }
+
+        env::log_str(&format!(
+            "Swept {} expired triggers; keeper {} earned {} yoctoNEAR",
+            swept, keeper, near_bounty
+        ));
+        swept
+    }
+
+    /// Number of active triggers past their expiry, up to `limit`
+    pub fn get_expired_count(&self, limit: u32) -> u32 {
+        let now = env::block_timestamp();
+        self.expiry_index
+            .iter()
+            .take_while(|((expires_at, _), _)| *expires_at < now)
+            .take(limit as usize)
+            .count() as u32
+    }
+}
+
+// ============================================================================
+// Internal Methods
+// ============================================================================
+
+impl TriggerPay {
+    /// Queue the trigger for the sweeper, charging the entry to its owner
+    pub(crate) fn index_expiry(&mut self, trigger: &Trigger) {
+        let initial_storage = env::storage_usage();
+        self.expiry_index.insert(&(trigger.expires_at, trigger.id.clone()), &());
+        self.charge_trigger_storage(trigger, initial_storage, false);
+    }
+
+    /// Drop the trigger from the sweeper's queue, crediting its owner
+    pub(crate) fn unindex_expiry(&mut self, trigger: &Trigger) {
+        let initial_storage = env::storage_usage();
+        self.expiry_index.remove(&(trigger.expires_at, trigger.id.clone()));
+        self.charge_trigger_storage(trigger, initial_storage, false);
+    }
+
+    /// Mark an expired trigger Expired and refund its owner, less the keeper's
+    /// fee, which is returned. The fee leaves the trigger's escrow before the
+    /// refund, so a failed refund cannot pay it twice. Covered triggers only
+    /// free their coverage.
+    fn expire_trigger(&mut self, mut trigger: Trigger) -> Balance {
+        self.transition(&mut trigger, Status::Expired);
+        self.release_exposure(&trigger);
+        if trigger.coverage.is_some() {
+            self.release_coverage(&trigger, false);
+            return 0;
+        }
+
+        let refundable = Self::refundable_escrow(&trigger);
+        let fee = match trigger.escrow_token {
+            None => self.keeper_fee,
+            Some(_) => self.refund_fee(&trigger),
+        }
+        .min(refundable);
+        trigger.funded_amount -= fee;
+        self.triggers.insert(&trigger.id, &trigger);
+
+        let refund_amount = refundable - fee;
+        if refund_amount > 0 {
+            // The token refund fee went to the keeper, so none is kept back
+            self.refund_escrow(&trigger, refund_amount, 0).detach();
+        }
+
+        env::log_str(&format!(
+            "Trigger {} expired: refunded {} to {}",
+            trigger.id, refund_amount, trigger.owner
+        ));
+        fee
+    }
+}
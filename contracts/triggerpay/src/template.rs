@@ -0,0 +1,213 @@
+use near_sdk::borsh::{BorshDeserialize, BorshSerialize};
+use near_sdk::serde::{Deserialize, Serialize};
+use near_sdk::{env, NearToken};
+use schemars::JsonSchema;
+
+use crate::escrow::Funding;
+use crate::{Balance, Chain, Condition, ConditionType, Payout, TriggerId, TriggerPay, TriggerPayExt};
+
+// ============================================================================
+// Types
+// ============================================================================
+
+#[derive(BorshDeserialize, BorshSerialize, Serialize, Deserialize, Clone, Debug, Default, PartialEq, JsonSchema)]
+#[borsh(crate = "near_sdk::borsh")]
+#[serde(crate = "near_sdk::serde")]
+pub enum TemplateStatus {
+    #[default]
+    Active,
+    Paused,  // Temporarily closed to new triggers
+    Retired, // Permanently closed to new triggers
+}
+
+/// Limits on the parameters a user may pick for a template
+#[derive(BorshDeserialize, BorshSerialize, Serialize, Deserialize, Clone, Debug, Default, JsonSchema)]
+#[borsh(crate = "near_sdk::borsh")]
+#[serde(crate = "near_sdk::serde")]
+pub struct ParamBounds {
+    #[serde(default)]
+    pub max_payout_amount: Option<String>, // In the payout token's smallest unit
+    #[serde(default)]
+    pub min_lead_time: Option<u64>, // Nanoseconds from creation to the settlement point
+    #[serde(default)]
+    pub max_lead_time: Option<u64>, // Nanoseconds from creation to the settlement point
+}
+
+/// A product the owner offers without a contract upgrade, e.g. "flight-delay-us"
+#[derive(BorshDeserialize, BorshSerialize, Serialize, Deserialize, Clone, Debug, JsonSchema)]
+#[borsh(crate = "near_sdk::borsh")]
+#[serde(crate = "near_sdk::serde")]
+pub struct ConditionTemplate {
+    pub name: String,
+    pub condition_type: ConditionType,
+    #[serde(default)]
+    pub bounds: ParamBounds,
+    pub allowed_chains: Vec<Chain>,
+    pub allowed_tokens: Vec<String>, // "ETH", "USDC"
+    pub min_deposit: String,         // yoctoNEAR, string for JSON compatibility
+    pub expiry_buffer: u64,          // Nanoseconds after the settlement point
+    #[serde(default)]
+    pub status: TemplateStatus,
+}
+
+/// User-chosen parameters for `create_trigger_from_template`
+#[derive(Serialize, Deserialize, Clone, Debug, JsonSchema)]
+#[serde(crate = "near_sdk::serde")]
+pub struct TemplateParams {
+    pub condition: Condition,
+    pub payout: Payout,
+}
+
+// ============================================================================
+// Template Management
+// ============================================================================
+
+#[near_sdk::near]
+impl TriggerPay {
+    /// Register a named condition template (only owner can call)
+    pub fn register_template(&mut self, template_id: String, template: ConditionTemplate) {
+        self.assert_owner();
+        assert!(!template_id.is_empty(), "Template ID is required");
+        assert!(
+            self.templates.get(&template_id).is_none(),
+            "Template already exists"
+        );
+        assert!(!template.allowed_chains.is_empty(), "At least one chain is required");
+        assert!(!template.allowed_tokens.is_empty(), "At least one token is required");
+        assert!(
+            template.min_deposit.parse::<Balance>().is_ok(),
+            "Invalid minimum deposit"
+        );
+        if let Some(max) = &template.bounds.max_payout_amount {
+            assert!(max.parse::<u128>().is_ok(), "Invalid maximum payout amount");
+        }
+
+        let template = ConditionTemplate {
+            status: TemplateStatus::Active,
+            ..template
+        };
+        self.templates.insert(&template_id, &template);
+
+        env::log_str(&format!("Template registered: {} ({})", template_id, template.name));
+    }
+
+    /// Pause, resume or retire a template; existing triggers are unaffected
+    /// (only owner can call)
+    pub fn set_template_status(&mut self, template_id: String, status: TemplateStatus) {
+        self.assert_owner();
+        let mut template = self.templates.get(&template_id).expect("Template not found");
+        assert!(
+            template.status != TemplateStatus::Retired,
+            "Template has been retired"
+        );
+        template.status = status;
+        self.templates.insert(&template_id, &template);
+
+        env::log_str(&format!("Template {} is now {:?}", template_id, template.status));
+    }
+
+    pub fn get_template(&self, template_id: String) -> Option<ConditionTemplate> {
+        self.templates.get(&template_id)
+    }
+
+    pub fn get_templates(&self) -> Vec<(String, ConditionTemplate)> {
+        self.templates.iter().collect()
+    }
+
+    /// Create a trigger from a template; the template fixes the condition type,
+    /// chains, tokens, minimum deposit and expiry buffer
+    #[payable]
+    pub fn create_trigger_from_template(
+        &mut self,
+        template_id: String,
+        params: TemplateParams,
+    ) -> TriggerId {
+        let template = self.templates.get(&template_id).expect("Template not found");
+        assert!(
+            template.status == TemplateStatus::Active,
+            "Template is not accepting new triggers"
+        );
+
+        let deposit = env::attached_deposit();
+        let min_deposit = template.min_deposit.parse::<Balance>().unwrap_or(0);
+        assert!(
+            deposit >= NearToken::from_yoctonear(min_deposit),
+            "Deposit is below the template minimum"
+        );
+        Self::validate_template_params(&template, &params);
+
+        let custom_expiry = params.condition.expires_at;
+        let settles_at = Self::settlement_point(&params.condition, env::block_timestamp());
+
+        let owner = env::predecessor_account_id();
+        let trigger_id = self.internal_create_trigger(
+            owner,
+            Funding::near(deposit.as_yoctonear()),
+            params.condition,
+            None,
+            params.payout,
+            None,
+        );
+
+        // The template's buffer replaces the per-type default
+        let mut trigger = self.triggers.get(&trigger_id).expect("Trigger not found");
+        trigger.template_id = Some(template_id);
+        if custom_expiry.is_none() {
+            self.unindex_expiry(&trigger);
+            trigger.expires_at = settles_at
+                .checked_add(template.expiry_buffer)
+                .expect("Settlement time is too far in the future");
+            self.index_expiry(&trigger);
+        }
+        let initial_storage = env::storage_usage();
+        self.triggers.insert(&trigger_id, &trigger);
+        self.charge_trigger_storage(&trigger, initial_storage, true);
+
+        trigger_id
+    }
+}
+
+// ============================================================================
+// Internal Methods
+// ============================================================================
+
+impl TriggerPay {
+    fn validate_template_params(template: &ConditionTemplate, params: &TemplateParams) {
+        assert!(
+            params.condition.condition_type == template.condition_type,
+            "Condition type does not match the template"
+        );
+        assert!(
+            template.allowed_chains.contains(&params.payout.chain),
+            "Payout chain is not allowed by the template"
+        );
+        assert!(
+            template
+                .allowed_tokens
+                .iter()
+                .any(|token| token.eq_ignore_ascii_case(&params.payout.token)),
+            "Payout token is not allowed by the template"
+        );
+
+        let bounds = &template.bounds;
+        if let Some(max) = &bounds.max_payout_amount {
+            let amount = params
+                .payout
+                .amount
+                .parse::<u128>()
+                .expect("Invalid payout amount");
+            assert!(
+                amount <= max.parse::<u128>().unwrap_or(0),
+                "Payout amount exceeds the template maximum"
+            );
+        }
+
+        let now = env::block_timestamp();
+        let lead_time = Self::settlement_point(&params.condition, now).saturating_sub(now);
+        assert!(
+            bounds.min_lead_time.is_none_or(|min| lead_time >= min)
+                && bounds.max_lead_time.is_none_or(|max| lead_time <= max),
+            "Settlement time is outside the template bounds"
+        );
+    }
+}
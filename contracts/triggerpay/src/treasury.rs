@@ -0,0 +1,424 @@
+use near_sdk::borsh::{BorshDeserialize, BorshSerialize};
+use near_sdk::json_types::U128;
+use near_sdk::serde::{Deserialize, Serialize};
+use near_sdk::serde_json::json;
+use near_sdk::{env, AccountId, Gas, NearToken, Promise, PromiseError};
+use schemars::JsonSchema;
+
+use crate::pool::mul_div;
+use crate::{Balance, Status, Trigger, TriggerPay, TriggerPayExt};
+
+// ============================================================================
+// Constants
+// ============================================================================
+
+const MAX_BPS: u16 = 10_000;
+const GAS_FOR_STAKING_CALL: Gas = Gas::from_tgas(50);
+const GAS_FOR_STAKING_VIEW: Gas = Gas::from_tgas(10);
+const GAS_FOR_TREASURY_CALLBACK: Gas = Gas::from_tgas(20);
+const YIELD_PRECISION: u128 = 1_000_000_000_000_000_000_000_000; // Accumulator scale: 1 NEAR
+
+// ============================================================================
+// Types
+// ============================================================================
+
+/// How idle NEAR is delegated and where staking rewards go
+#[derive(BorshDeserialize, BorshSerialize, Serialize, Deserialize, Clone, Debug, JsonSchema)]
+#[borsh(crate = "near_sdk::borsh")]
+#[serde(crate = "near_sdk::serde")]
+pub struct TreasuryPolicy {
+    pub staking_pool: Option<String>, // Whitelisted pool AccountId, None disables staking
+    pub stake_share_bps: u16,         // Share of NEAR above the reserve to delegate
+    pub reserve_bps: u16,             // Liquid reserve as a share of NEAR liabilities
+    pub users_bps: u16,               // Yield split; the three shares sum to 10000
+    pub lps_bps: u16,
+    pub treasury_bps: u16,
+}
+
+impl Default for TreasuryPolicy {
+    fn default() -> Self {
+        Self {
+            staking_pool: None,
+            stake_share_bps: 5_000, // 50%
+            reserve_bps: 3_000,     // 30% of escrow and pool capital stays liquid
+            users_bps: 5_000,
+            lps_bps: 3_000,
+            treasury_bps: 2_000,
+        }
+    }
+}
+
+#[derive(BorshDeserialize, BorshSerialize, Clone, Debug, Default)]
+#[borsh(crate = "near_sdk::borsh")]
+pub struct Treasury {
+    pub policy: TreasuryPolicy,
+    pub staked: Balance,    // Delegated principal plus harvested rewards
+    pub unstaking: Balance, // Unstaked, waiting out the pool's withdrawal delay
+    pub earnings: Balance,  // Treasury share of rewards, withdrawable by the owner
+    pub yield_distributed: Balance,
+    pub near_escrow: Balance,      // Refundable escrow of active uncovered NEAR triggers
+    pub yield_per_escrow: Balance, // Users' yield per yoctoNEAR of escrow, scaled by YIELD_PRECISION
+    pub yield_owed: Balance,       // Users' yield booked but not yet credited to a trigger
+}
+
+#[derive(Serialize, Deserialize, Debug, JsonSchema)]
+#[serde(crate = "near_sdk::serde")]
+pub struct TreasuryView {
+    pub policy: TreasuryPolicy,
+    pub staked: String, // Strings for JSON compatibility
+    pub unstaking: String,
+    pub earnings: String,
+    pub yield_distributed: String,
+    pub liquid: String,      // NEAR held by the contract, excluding storage
+    pub liabilities: String, // Refundable NEAR escrow, NEAR pool capital and earnings
+    pub reserve: String,     // Liquid NEAR that is never delegated
+    pub stakeable: String,   // What `treasury_stake` would delegate now
+}
+
+// ============================================================================
+// Treasury
+// ============================================================================
+
+#[near_sdk::near]
+impl TriggerPay {
+    /// Whitelist a staking pool for treasury delegation (only owner can call)
+    pub fn add_staking_pool(&mut self, pool_id: AccountId) {
+        self.assert_owner();
+        self.staking_pools.insert(&pool_id);
+
+        env::log_str(&format!("Staking pool whitelisted: {}", pool_id));
+    }
+
+    /// Remove a staking pool from the whitelist (only owner can call)
+    pub fn remove_staking_pool(&mut self, pool_id: AccountId) {
+        self.assert_owner();
+        assert!(
+            self.treasury.policy.staking_pool.as_deref() != Some(pool_id.as_str()),
+            "Staking pool is in use by the treasury policy"
+        );
+        self.staking_pools.remove(&pool_id);
+
+        env::log_str(&format!("Staking pool removed: {}", pool_id));
+    }
+
+    pub fn get_staking_pools(&self) -> Vec<String> {
+        self.staking_pools.iter().map(|pool| pool.to_string()).collect()
+    }
+
+    /// Set the staking pool, stake share, reserve and yield split (only owner
+    /// can call). The pool can only change while nothing is delegated.
+    pub fn set_treasury_policy(&mut self, policy: TreasuryPolicy) {
+        self.assert_owner();
+        assert!(
+            policy.stake_share_bps <= MAX_BPS && policy.reserve_bps <= MAX_BPS,
+            "Basis points must be at most 10000"
+        );
+        assert!(
+            policy.users_bps as u32 + policy.lps_bps as u32 + policy.treasury_bps as u32 == MAX_BPS as u32,
+            "Yield shares must sum to 10000 bps"
+        );
+        if let Some(pool) = &policy.staking_pool {
+            let pool_id: AccountId = pool.parse().expect("Invalid staking pool");
+            assert!(
+                self.staking_pools.contains(&pool_id),
+                "Staking pool is not whitelisted"
+            );
+        }
+        if policy.staking_pool != self.treasury.policy.staking_pool {
+            assert!(
+                self.treasury.staked == 0 && self.treasury.unstaking == 0,
+                "Staking pool can only change while nothing is delegated"
+            );
+        }
+        self.treasury.policy = policy;
+
+        env::log_str("Treasury policy updated");
+    }
+
+    pub fn get_treasury(&self) -> TreasuryView {
+        let treasury = &self.treasury;
+        TreasuryView {
+            policy: treasury.policy.clone(),
+            staked: treasury.staked.to_string(),
+            unstaking: treasury.unstaking.to_string(),
+            earnings: treasury.earnings.to_string(),
+            yield_distributed: treasury.yield_distributed.to_string(),
+            liquid: self.liquid_near().to_string(),
+            liabilities: self.near_liabilities().to_string(),
+            reserve: self.liquid_reserve().to_string(),
+            stakeable: self.stakeable_near().to_string(),
+        }
+    }
+
+    /// Delegate idle NEAR above the reserve, up to the policy's stake share
+    /// (only owner can call)
+    pub fn treasury_stake(&mut self) -> Promise {
+        self.assert_owner();
+        let pool_id = self.treasury_pool();
+        let amount = self.stakeable_near();
+        assert!(amount > 0, "Nothing to stake");
+        self.treasury.staked += amount;
+
+        env::log_str(&format!("Treasury staking {} with {}", amount, pool_id));
+
+        Promise::new(pool_id)
+            .function_call(
+                "deposit_and_stake".to_string(),
+                Vec::new(),
+                NearToken::from_yoctonear(amount),
+                GAS_FOR_STAKING_CALL,
+            )
+            .then(
+                Self::ext(env::current_account_id())
+                    .with_static_gas(GAS_FOR_TREASURY_CALLBACK)
+                    .on_treasury_stake(U128(amount)),
+            )
+    }
+
+    /// Start unstaking; the NEAR can be withdrawn after the pool's delay
+    /// (only owner can call)
+    pub fn treasury_unstake(&mut self, amount: U128) -> Promise {
+        self.assert_owner();
+        let pool_id = self.treasury_pool();
+        assert!(
+            amount.0 > 0 && amount.0 <= self.treasury.staked,
+            "Amount exceeds the staked balance"
+        );
+        self.treasury.staked -= amount.0;
+        self.treasury.unstaking += amount.0;
+
+        Promise::new(pool_id)
+            .function_call(
+                "unstake".to_string(),
+                json!({ "amount": amount }).to_string().into_bytes(),
+                NearToken::from_yoctonear(0),
+                GAS_FOR_STAKING_CALL,
+            )
+            .then(
+                Self::ext(env::current_account_id())
+                    .with_static_gas(GAS_FOR_TREASURY_CALLBACK)
+                    .on_treasury_unstake(amount),
+            )
+    }
+
+    /// Withdraw everything unstaked back into the liquid balance
+    /// (only owner can call)
+    pub fn treasury_withdraw(&mut self) -> Promise {
+        self.assert_owner();
+        let pool_id = self.treasury_pool();
+        let amount = self.treasury.unstaking;
+        assert!(amount > 0, "Nothing to withdraw");
+
+        Promise::new(pool_id)
+            .function_call(
+                "withdraw".to_string(),
+                json!({ "amount": U128(amount) }).to_string().into_bytes(),
+                NearToken::from_yoctonear(0),
+                GAS_FOR_STAKING_CALL,
+            )
+            .then(
+                Self::ext(env::current_account_id())
+                    .with_static_gas(GAS_FOR_TREASURY_CALLBACK)
+                    .on_treasury_withdraw(U128(amount)),
+            )
+    }
+
+    /// Read the pool balance and distribute any rewards by policy
+    pub fn treasury_harvest(&mut self) -> Promise {
+        let pool_id = self.treasury_pool();
+        Promise::new(pool_id)
+            .function_call(
+                "get_account_total_balance".to_string(),
+                json!({ "account_id": env::current_account_id() })
+                    .to_string()
+                    .into_bytes(),
+                NearToken::from_yoctonear(0),
+                GAS_FOR_STAKING_VIEW,
+            )
+            .then(
+                Self::ext(env::current_account_id())
+                    .with_static_gas(GAS_FOR_TREASURY_CALLBACK)
+                    .on_treasury_harvest(),
+            )
+    }
+
+    /// Send treasury earnings to the owner (only owner can call)
+    pub fn withdraw_treasury_earnings(&mut self, amount: U128) -> Promise {
+        self.assert_owner();
+        assert!(
+            amount.0 > 0 && amount.0 <= self.treasury.earnings,
+            "Amount exceeds treasury earnings"
+        );
+        assert!(
+            amount.0 <= self.liquid_near(),
+            "Not enough liquid NEAR; unstake first"
+        );
+        self.treasury.earnings -= amount.0;
+
+        Promise::new(self.owner.clone()).transfer(NearToken::from_yoctonear(amount.0))
+    }
+
+    /// Callback from `deposit_and_stake`; a failed call returns the deposit
+    #[private]
+    pub fn on_treasury_stake(&mut self, amount: U128) -> bool {
+        let succeeded = !matches!(env::promise_result_checked(0, 0), Err(PromiseError::Failed));
+        if !succeeded {
+            self.treasury.staked = self.treasury.staked.saturating_sub(amount.0);
+            env::log_str("Treasury stake failed");
+        }
+        succeeded
+    }
+
+    /// Callback from `unstake`; restores the staked balance on failure
+    #[private]
+    pub fn on_treasury_unstake(&mut self, amount: U128) -> bool {
+        let succeeded = !matches!(env::promise_result_checked(0, 0), Err(PromiseError::Failed));
+        if !succeeded {
+            self.treasury.unstaking = self.treasury.unstaking.saturating_sub(amount.0);
+            self.treasury.staked += amount.0;
+            env::log_str("Treasury unstake failed");
+        }
+        succeeded
+    }
+
+    /// Callback from `withdraw`; the NEAR is liquid again on success
+    #[private]
+    pub fn on_treasury_withdraw(&mut self, amount: U128) -> bool {
+        let succeeded = !matches!(env::promise_result_checked(0, 0), Err(PromiseError::Failed));
+        if succeeded {
+            self.treasury.unstaking = self.treasury.unstaking.saturating_sub(amount.0);
+            env::log_str(&format!("Treasury withdrew {} from staking", amount.0));
+        }
+        succeeded
+    }
+
+    /// Callback from `get_account_total_balance`. Anything above the tracked
+    /// principal is a reward; it stays delegated and is booked to users, LPs
+    /// and the treasury by policy.
+    #[private]
+    pub fn on_treasury_harvest(&mut self, #[callback_result] total: Result<U128, PromiseError>) -> U128 {
+        let Ok(total) = total else {
+            env::log_str("Treasury harvest failed");
+            return U128(0);
+        };
+        let rewards = total
+            .0
+            .saturating_sub(self.treasury.staked + self.treasury.unstaking);
+        if rewards > 0 {
+            self.treasury.staked += rewards;
+            self.distribute_yield(rewards);
+        }
+        U128(rewards)
+    }
+}
+
+// ============================================================================
+// Internal Methods
+// ============================================================================
+
+impl TriggerPay {
+    fn treasury_pool(&self) -> AccountId {
+        self.treasury
+            .policy
+            .staking_pool
+            .as_ref()
+            .and_then(|pool| pool.parse().ok())
+            .expect("Treasury staking is not configured")
+    }
+
+    /// NEAR held by the contract, excluding storage and any attached deposit
+    pub(crate) fn liquid_near(&self) -> Balance {
+        let storage = env::storage_byte_cost().as_yoctonear() * env::storage_usage() as Balance;
+        env::account_balance()
+            .as_yoctonear()
+            .saturating_sub(storage)
+            .saturating_sub(env::attached_deposit().as_yoctonear())
+    }
+
+    /// NEAR the contract may have to pay out: refundable escrow of active
+    /// NEAR triggers and their uncredited yield, capital of a NEAR pool and
+    /// unpaid treasury earnings
+    fn near_liabilities(&self) -> Balance {
+        let pool_capital = if self.pool.token.is_none() { self.pool.total_capital } else { 0 };
+        self.treasury.near_escrow + self.treasury.yield_owed + pool_capital + self.treasury.earnings
+    }
+
+    fn liquid_reserve(&self) -> Balance {
+        mul_div(self.near_liabilities(), self.treasury.policy.reserve_bps as u128, MAX_BPS as u128)
+    }
+
+    /// Stake share of everything above the reserve, less what is already delegated
+    fn stakeable_near(&self) -> Balance {
+        let liquid = self.liquid_near();
+        let idle = liquid.saturating_sub(self.liquid_reserve());
+        let target = mul_div(
+            idle + self.treasury.staked,
+            self.treasury.policy.stake_share_bps as u128,
+            MAX_BPS as u128,
+        );
+        target.saturating_sub(self.treasury.staked).min(idle)
+    }
+
+    /// Book rewards: users' share is owed to active NEAR escrow pro rata
+    /// through the yield accumulator, LPs' share goes to a NEAR pool's
+    /// capital, the rest to the treasury. Shares with no recipient fall to
+    /// the treasury.
+    fn distribute_yield(&mut self, rewards: Balance) {
+        let policy = self.treasury.policy.clone();
+        let mut remaining = rewards;
+
+        if self.treasury.near_escrow > 0 {
+            let users_share = mul_div(rewards, policy.users_bps as u128, MAX_BPS as u128);
+            self.treasury.yield_per_escrow += mul_div(users_share, YIELD_PRECISION, self.treasury.near_escrow);
+            self.treasury.yield_owed += users_share;
+            remaining -= users_share;
+        }
+
+        if self.pool.token.is_none() && self.pool.total_shares > 0 {
+            let lps_share = mul_div(rewards, policy.lps_bps as u128, MAX_BPS as u128);
+            self.pool.total_capital += lps_share;
+            remaining -= lps_share;
+        }
+
+        self.treasury.earnings += remaining;
+        self.treasury.yield_distributed += rewards;
+
+        env::log_str(&format!(
+            "Staking rewards distributed: {} ({} to the treasury)",
+            rewards, remaining
+        ));
+    }
+
+    /// Apply a change that may move a trigger's share of the NEAR escrow
+    /// (status, funding, released instalments), keeping the escrow total in
+    /// step. Yield accrued at the old share is credited to the escrow first.
+    pub(crate) fn update_escrow(&mut self, trigger: &mut Trigger, change: impl FnOnce(&mut Trigger)) {
+        let before = Self::yield_weight(trigger);
+        let credit = self.pending_yield(trigger);
+        trigger.yield_checkpoint = self.treasury.yield_per_escrow;
+        trigger.funded_amount += credit;
+        self.treasury.yield_owed = self.treasury.yield_owed.saturating_sub(credit);
+
+        change(trigger);
+        let after = Self::yield_weight(trigger);
+        self.treasury.near_escrow = self.treasury.near_escrow + after - before;
+    }
+
+    /// Users' yield accrued to a trigger since it was last credited
+    pub(crate) fn pending_yield(&self, trigger: &Trigger) -> Balance {
+        mul_div(
+            Self::yield_weight(trigger),
+            self.treasury.yield_per_escrow - trigger.yield_checkpoint,
+            YIELD_PRECISION,
+        )
+    }
+
+    /// Escrow that earns users' yield: refundable NEAR of active uncovered triggers
+    fn yield_weight(trigger: &Trigger) -> Balance {
+        if trigger.status == Status::Active && trigger.escrow_token.is_none() && trigger.coverage.is_none() {
+            Self::refundable_escrow(trigger)
+        } else {
+            0
+        }
+    }
+}